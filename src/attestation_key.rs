@@ -0,0 +1,337 @@
+//! Attestation keys and quote signatures, which may use either P-256 or (with the `p384` feature)
+//! P-384
+//!
+//! Quotes parsed without verification may contain keys or signatures which cannot be decoded. These
+//! are kept as raw bytes, so that the quote can still be inspected and serialized.
+use crate::{AttestionKeyType, QuoteParseError, QuoteVerificationError};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+#[cfg(feature = "p384")]
+use sha2::Sha384;
+use sha2::{Digest, Sha256};
+
+/// The key used by the quoting enclave to sign quotes
+#[non_exhaustive]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttestationKey {
+    P256(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::p256_key"))] VerifyingKey),
+    #[cfg(feature = "p384")]
+    P384(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::p384_key"))]
+        p384::ecdsa::VerifyingKey,
+    ),
+    /// A P-256 key which is not a valid point on the curve, as given in the quote
+    RawP256(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))] [u8; 64]),
+    /// A P-384 key which is not a valid point on the curve, as given in the quote
+    #[cfg(feature = "p384")]
+    RawP384(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))] [u8; 96]),
+}
+
+impl AttestationKey {
+    /// Decode an attestation key of the given type from the untagged, uncompressed encoding used
+    /// in quotes
+    pub fn from_raw_bytes(
+        attestation_key_type: &AttestionKeyType,
+        input: &[u8],
+    ) -> Result<Self, QuoteParseError> {
+        match attestation_key_type {
+            AttestionKeyType::ECDSA256WithP256 if input.len() == 64 => Ok(Self::P256(
+                VerifyingKey::from_encoded_point(&p256::EncodedPoint::from_untagged_bytes(
+                    input.into(),
+                ))
+                .map_err(|_| QuoteParseError::MalformedAttestationKey)?,
+            )),
+            #[cfg(feature = "p384")]
+            AttestionKeyType::ECDSA384WithP384 if input.len() == 96 => Ok(Self::P384(
+                p384::ecdsa::VerifyingKey::from_encoded_point(
+                    &p384::EncodedPoint::from_untagged_bytes(input.into()),
+                )
+                .map_err(|_| QuoteParseError::MalformedAttestationKey)?,
+            )),
+            _ => Err(QuoteParseError::UnsupportedAttestationKeyType),
+        }
+    }
+
+    /// Decode an attestation key as with [AttestationKey::from_raw_bytes], but keep the raw bytes
+    /// of a key which is not a valid point
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_raw_bytes_lenient(
+        attestation_key_type: &AttestionKeyType,
+        input: &[u8],
+    ) -> Result<Self, QuoteParseError> {
+        match Self::from_raw_bytes(attestation_key_type, input) {
+            Err(QuoteParseError::MalformedAttestationKey) => match attestation_key_type {
+                AttestionKeyType::ECDSA256WithP256 => Ok(Self::RawP256(
+                    input
+                        .try_into()
+                        .map_err(|_| QuoteParseError::MalformedAttestationKey)?,
+                )),
+                #[cfg(feature = "p384")]
+                AttestionKeyType::ECDSA384WithP384 => Ok(Self::RawP384(
+                    input
+                        .try_into()
+                        .map_err(|_| QuoteParseError::MalformedAttestationKey)?,
+                )),
+                #[allow(unreachable_patterns)]
+                _ => Err(QuoteParseError::UnsupportedAttestationKeyType),
+            },
+            result => result,
+        }
+    }
+
+    /// Encode the key as it is in a quote - uncompressed, without the 0x04 prefix
+    #[cfg(feature = "alloc")]
+    pub fn to_raw_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::P256(key) => key.to_encoded_point(false).as_bytes()[1..].to_vec(),
+            #[cfg(feature = "p384")]
+            Self::P384(key) => key.to_encoded_point(false).as_bytes()[1..].to_vec(),
+            Self::RawP256(bytes) => bytes.to_vec(),
+            #[cfg(feature = "p384")]
+            Self::RawP384(bytes) => bytes.to_vec(),
+        }
+    }
+
+    /// The type of this key, as given in the quote header
+    pub fn attestation_key_type(&self) -> AttestionKeyType {
+        match self {
+            Self::P256(_) | Self::RawP256(_) => AttestionKeyType::ECDSA256WithP256,
+            #[cfg(feature = "p384")]
+            Self::P384(_) | Self::RawP384(_) => AttestionKeyType::ECDSA384WithP384,
+        }
+    }
+
+    /// The decoded P-256 key, if this is one
+    pub fn as_p256(&self) -> Option<&VerifyingKey> {
+        match self {
+            Self::P256(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Verify a quote signature made with this key
+    pub fn verify(
+        &self,
+        message: &[u8],
+        signature: &QuoteSignature,
+    ) -> Result<(), QuoteVerificationError> {
+        let result = match (self, signature) {
+            (Self::P256(key), QuoteSignature::P256(signature)) => key.verify(message, signature),
+            #[cfg(feature = "p384")]
+            (Self::P384(key), QuoteSignature::P384(signature)) => key.verify(message, signature),
+            (Self::RawP256(_), _) => return Err(QuoteVerificationError::BadAttestationKey),
+            #[cfg(feature = "p384")]
+            (Self::RawP384(_), _) => return Err(QuoteVerificationError::BadAttestationKey),
+            _ => return Err(QuoteVerificationError::BadAttestationKeySignature),
+        };
+        result.map_err(|_| QuoteVerificationError::BadAttestationKeySignature)
+    }
+
+    /// The report data which the QE report should contain for this key - the hash of the raw
+    /// encoded key and the QE authentication data, followed by null bytes
+    #[cfg(feature = "mock")]
+    pub(crate) fn qe_report_data(&self, qe_authentication_data: &[u8]) -> [u8; 64] {
+        let mut qe_report_data = [0; 64];
+        self.write_qe_report_data_hash(qe_authentication_data, &mut qe_report_data);
+        qe_report_data
+    }
+
+    /// Whether the given QE report data begins with the hash of this key and the QE
+    /// authentication data. The null bytes which follow the hash are ignored.
+    pub(crate) fn matches_qe_report_data(
+        &self,
+        qe_authentication_data: &[u8],
+        qe_report_data: &[u8],
+    ) -> bool {
+        let mut expected = [0; 64];
+        let hash_length = self.write_qe_report_data_hash(qe_authentication_data, &mut expected);
+        qe_report_data.get(..hash_length) == Some(&expected[..hash_length])
+    }
+
+    /// Write the hash of the raw encoded key and the QE authentication data to the start of the
+    /// given output, returning the length of the hash. SHA256 is used for P-256 keys, and SHA384
+    /// for P-384 keys.
+    fn write_qe_report_data_hash(
+        &self,
+        qe_authentication_data: &[u8],
+        output: &mut [u8; 64],
+    ) -> usize {
+        match self {
+            Self::P256(key) => {
+                // Skip the 0x04 prefix
+                hash_into::<Sha256>(
+                    &key.to_encoded_point(false).as_bytes()[1..],
+                    qe_authentication_data,
+                    output,
+                )
+            }
+            #[cfg(feature = "p384")]
+            Self::P384(key) => hash_into::<Sha384>(
+                &key.to_encoded_point(false).as_bytes()[1..],
+                qe_authentication_data,
+                output,
+            ),
+            Self::RawP256(bytes) => hash_into::<Sha256>(bytes, qe_authentication_data, output),
+            #[cfg(feature = "p384")]
+            Self::RawP384(bytes) => hash_into::<Sha384>(bytes, qe_authentication_data, output),
+        }
+    }
+}
+
+/// Write the hash of the encoded key and the QE authentication data to the start of the output,
+/// returning the length of the hash
+fn hash_into<D: Digest>(
+    encoded_key: &[u8],
+    qe_authentication_data: &[u8],
+    output: &mut [u8; 64],
+) -> usize {
+    let mut hasher = D::new();
+    hasher.update(encoded_key);
+    hasher.update(qe_authentication_data);
+    let hash = hasher.finalize();
+    output[..hash.len()].copy_from_slice(&hash);
+    hash.len()
+}
+
+impl From<VerifyingKey> for AttestationKey {
+    fn from(key: VerifyingKey) -> AttestationKey {
+        AttestationKey::P256(key)
+    }
+}
+
+impl PartialEq<VerifyingKey> for AttestationKey {
+    fn eq(&self, other: &VerifyingKey) -> bool {
+        matches!(self, Self::P256(key) if key == other)
+    }
+}
+
+#[cfg(feature = "p384")]
+impl From<p384::ecdsa::VerifyingKey> for AttestationKey {
+    fn from(key: p384::ecdsa::VerifyingKey) -> AttestationKey {
+        AttestationKey::P384(key)
+    }
+}
+
+/// A signature made with an attestation key
+#[non_exhaustive]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QuoteSignature {
+    P256(Signature),
+    #[cfg(feature = "p384")]
+    P384(p384::ecdsa::Signature),
+    /// A P-256 signature which cannot be decoded, such as one which is all zeros, as given in the
+    /// quote
+    RawP256(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))] [u8; 64]),
+    /// A P-384 signature which cannot be decoded, as given in the quote
+    #[cfg(feature = "p384")]
+    RawP384(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))] [u8; 96]),
+}
+
+impl QuoteSignature {
+    /// Decode a signature for the given type of attestation key
+    pub fn from_raw_bytes(
+        attestation_key_type: &AttestionKeyType,
+        input: &[u8],
+    ) -> Result<Self, QuoteParseError> {
+        match attestation_key_type {
+            AttestionKeyType::ECDSA256WithP256 => Ok(Self::P256(
+                Signature::from_slice(input).map_err(|_| QuoteParseError::MalformedSignature)?,
+            )),
+            #[cfg(feature = "p384")]
+            AttestionKeyType::ECDSA384WithP384 => Ok(Self::P384(
+                p384::ecdsa::Signature::from_slice(input)
+                    .map_err(|_| QuoteParseError::MalformedSignature)?,
+            )),
+            #[allow(unreachable_patterns)]
+            _ => Err(QuoteParseError::UnsupportedAttestationKeyType),
+        }
+    }
+
+    /// Decode a signature as with [QuoteSignature::from_raw_bytes], but keep the raw bytes of a
+    /// signature which cannot be decoded
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_raw_bytes_lenient(
+        attestation_key_type: &AttestionKeyType,
+        input: &[u8],
+    ) -> Result<Self, QuoteParseError> {
+        match Self::from_raw_bytes(attestation_key_type, input) {
+            Err(QuoteParseError::MalformedSignature) => match attestation_key_type {
+                AttestionKeyType::ECDSA256WithP256 => Ok(Self::RawP256(
+                    input
+                        .try_into()
+                        .map_err(|_| QuoteParseError::MalformedSignature)?,
+                )),
+                #[cfg(feature = "p384")]
+                AttestionKeyType::ECDSA384WithP384 => Ok(Self::RawP384(
+                    input
+                        .try_into()
+                        .map_err(|_| QuoteParseError::MalformedSignature)?,
+                )),
+                #[allow(unreachable_patterns)]
+                _ => Err(QuoteParseError::UnsupportedAttestationKeyType),
+            },
+            result => result,
+        }
+    }
+
+    /// The decoded P-256 signature, if this is one
+    pub fn as_p256(&self) -> Option<&Signature> {
+        match self {
+            Self::P256(signature) => Some(signature),
+            _ => None,
+        }
+    }
+
+    /// Encode the signature as it is in a quote
+    #[cfg(feature = "alloc")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::P256(signature) => signature.to_bytes().to_vec(),
+            #[cfg(feature = "p384")]
+            Self::P384(signature) => signature.to_bytes().to_vec(),
+            Self::RawP256(bytes) => bytes.to_vec(),
+            #[cfg(feature = "p384")]
+            Self::RawP384(bytes) => bytes.to_vec(),
+        }
+    }
+}
+
+impl From<Signature> for QuoteSignature {
+    fn from(signature: Signature) -> QuoteSignature {
+        QuoteSignature::P256(signature)
+    }
+}
+
+impl PartialEq<Signature> for QuoteSignature {
+    fn eq(&self, other: &Signature) -> bool {
+        matches!(self, Self::P256(signature) if signature == other)
+    }
+}
+
+#[cfg(feature = "p384")]
+impl From<p384::ecdsa::Signature> for QuoteSignature {
+    fn from(signature: p384::ecdsa::Signature) -> QuoteSignature {
+        QuoteSignature::P384(signature)
+    }
+}
+
+impl AttestionKeyType {
+    /// Length of an encoded attestation key, and of a signature made with it
+    pub(crate) fn key_length(&self) -> usize {
+        match self {
+            AttestionKeyType::ECDSA256WithP256 => 64,
+            AttestionKeyType::ECDSA384WithP384 => 96,
+        }
+    }
+
+    /// Whether this type of attestation key is supported with the enabled features
+    pub fn is_supported(&self) -> bool {
+        match self {
+            AttestionKeyType::ECDSA256WithP256 => true,
+            AttestionKeyType::ECDSA384WithP384 => cfg!(feature = "p384"),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Verify many quotes at once, avoiding repeated verification of data shared between quotes from
+//! the same platform
+use crate::{error::QuoteVerificationError, CertificationData, QeReportCertificationData, Quote};
+use alloc::{collections::BTreeMap, vec::Vec};
+use p256::ecdsa::{signature::Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Verifies a batch of quotes, giving a result for each
+///
+/// Quotes from the same platform share a QE report signed by the same provisioning certification
+/// key (PCK), and the same certification data from which the PCK is found. The results of finding
+/// the PCK and of verifying the QE report signature are remembered, keyed by hash, so that each is
+/// only done once. The signature of each quote is always verified.
+///
+/// The PCK is found by calling the given function with the certification data contained in the
+/// QE report certification data, which is typically a PEM encoded PCK certificate chain.
+/// Verifying the certificate chain is up to this function.
+pub struct BatchVerifier<F> {
+    find_pck: F,
+    pcks: BTreeMap<[u8; 32], Option<VerifyingKey>>,
+    qe_reports: BTreeMap<[u8; 32], Result<(), QuoteVerificationError>>,
+}
+
+impl<F: Fn(&[u8]) -> Option<VerifyingKey>> BatchVerifier<F> {
+    /// Create a batch verifier which uses the given function to find the PCK
+    pub fn new(find_pck: F) -> Self {
+        Self {
+            find_pck,
+            pcks: BTreeMap::new(),
+            qe_reports: BTreeMap::new(),
+        }
+    }
+
+    /// Verify each of the given quotes
+    pub fn verify(&mut self, quotes: &[Quote]) -> Vec<Result<(), QuoteVerificationError>> {
+        quotes
+            .iter()
+            .map(|quote| {
+                verify_quote(
+                    quote,
+                    |certification_data| {
+                        *self
+                            .pcks
+                            .entry(hash(&[certification_data]))
+                            .or_insert_with(|| (self.find_pck)(certification_data))
+                    },
+                    |pck, qe_report_certification_data| {
+                        self.qe_reports
+                            .entry(qe_report_key(pck, qe_report_certification_data))
+                            .or_insert_with(|| verify_qe_report(pck, qe_report_certification_data))
+                            .clone()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Verify each of the given quotes, spreading the work across threads
+    #[cfg(feature = "std")]
+    pub fn verify_parallel(&mut self, quotes: &[Quote]) -> Vec<Result<(), QuoteVerificationError>>
+    where
+        F: Sync,
+    {
+        use std::sync::Mutex;
+
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunk_size = quotes.len().div_ceil(threads).max(1);
+        let find_pck = &self.find_pck;
+        let pcks = Mutex::new(core::mem::take(&mut self.pcks));
+        let qe_reports = Mutex::new(core::mem::take(&mut self.qe_reports));
+
+        let results = std::thread::scope(|scope| {
+            let handles: Vec<_> = quotes
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let (pcks, qe_reports) = (&pcks, &qe_reports);
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|quote| {
+                                verify_quote(
+                                    quote,
+                                    |certification_data| {
+                                        let key = hash(&[certification_data]);
+                                        if let Some(pck) = pcks.lock().unwrap().get(&key) {
+                                            return *pck;
+                                        }
+                                        // The lock is not held while finding the PCK
+                                        let pck = find_pck(certification_data);
+                                        pcks.lock().unwrap().insert(key, pck);
+                                        pck
+                                    },
+                                    |pck, qe_report_certification_data| {
+                                        let key = qe_report_key(pck, qe_report_certification_data);
+                                        if let Some(result) = qe_reports.lock().unwrap().get(&key) {
+                                            return result.clone();
+                                        }
+                                        let result =
+                                            verify_qe_report(pck, qe_report_certification_data);
+                                        qe_reports.lock().unwrap().insert(key, result.clone());
+                                        result
+                                    },
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        self.pcks = pcks.into_inner().unwrap();
+        self.qe_reports = qe_reports.into_inner().unwrap();
+        results
+    }
+}
+
+/// Verify a quote, using the given functions to find the PCK and verify the QE report
+fn verify_quote(
+    quote: &Quote,
+    mut find_pck: impl FnMut(&[u8]) -> Option<VerifyingKey>,
+    mut verify_qe_report: impl FnMut(
+        &VerifyingKey,
+        &QeReportCertificationData,
+    ) -> Result<(), QuoteVerificationError>,
+) -> Result<(), QuoteVerificationError> {
+    quote.verify_signature()?;
+    quote.verify_qe_binding()?;
+    let CertificationData::QeReportCertificationData(qe_report_certification_data) =
+        &quote.certification_data
+    else {
+        return Err(QuoteVerificationError::NoQeReportCertificationData);
+    };
+    let pck = find_pck(&qe_report_certification_data.certification_data)
+        .ok_or(QuoteVerificationError::UnknownPck)?;
+    verify_qe_report(&pck, qe_report_certification_data)
+}
+
+/// Verify the signature of a QE report with the given PCK
+fn verify_qe_report(
+    pck: &VerifyingKey,
+    qe_report_certification_data: &QeReportCertificationData,
+) -> Result<(), QuoteVerificationError> {
+    let signature = qe_report_certification_data
+        .signature
+        .as_p256()
+        .ok_or(QuoteVerificationError::BadSignature)?;
+    pck.verify(&qe_report_certification_data.qe_report[..], signature)?;
+    Ok(())
+}
+
+/// The key under which the result of verifying a QE report is remembered
+fn qe_report_key(
+    pck: &VerifyingKey,
+    qe_report_certification_data: &QeReportCertificationData,
+) -> [u8; 32] {
+    hash(&[
+        pck.to_encoded_point(false).as_bytes(),
+        &qe_report_certification_data.qe_report,
+        &qe_report_certification_data.signature.to_bytes(),
+    ])
+}
+
+/// SHA-256 of the concatenated parts
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
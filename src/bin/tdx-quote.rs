@@ -0,0 +1,398 @@
+//! Command line tool for inspecting, verifying, diffing and converting quotes
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use tdx_quote::{
+    JsonLayout, PlatformTcb, QeIdentity, Quote, QuoteLayout, TcbInfo, TcbStatus, VerifyingKey,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "tdx-quote",
+    version,
+    about = "Inspect, verify, diff and convert TDX and SGX quotes"
+)]
+struct Cli {
+    /// Encoding of input quotes. By default this is detected from the input.
+    #[arg(long, global = true, value_enum)]
+    from: Option<InputEncoding>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show every field of a quote
+    Inspect {
+        /// Quote file, or - for standard input
+        quote: PathBuf,
+        /// Show the quote as JSON
+        #[arg(long)]
+        json: bool,
+        /// Layout of the JSON output. Implies --json.
+        #[arg(long, value_enum)]
+        layout: Option<Layout>,
+    },
+    /// Show the bytes of each field of a quote, which may be malformed
+    Hexdump {
+        /// Quote file, or - for standard input
+        quote: PathBuf,
+    },
+    /// Verify the signatures of a quote, and optionally its TCB status and measurements
+    Verify {
+        /// Quote file, or - for standard input
+        quote: PathBuf,
+        /// Provisioning certification key which signed the QE report, as hex encoded SEC1
+        #[arg(long)]
+        pck: Option<String>,
+        /// Directory containing tdx_tcb_info.json and td_qe_identity.json as served by Intel, and
+        /// platform_tcb.json giving the fmspc, pce_id, sgx_tcb_components and pce_svn from the PCK
+        /// certificate
+        #[arg(long)]
+        collateral: Option<PathBuf>,
+        /// JSON policy file. Its `quote` object must match part of the output of `inspect --json`,
+        /// and its `accepted_tcb_statuses` list the TCB statuses accepted besides UpToDate.
+        #[arg(long)]
+        policy: Option<PathBuf>,
+    },
+    /// Show the fields which differ between two quotes
+    Diff {
+        /// Quote file, or - for standard input
+        old: PathBuf,
+        /// Quote file, or - for standard input
+        new: PathBuf,
+    },
+    /// Convert a quote to another encoding
+    Convert {
+        /// Quote file, or - for standard input
+        quote: PathBuf,
+        /// Encoding of the output
+        #[arg(long, value_enum)]
+        to: OutputEncoding,
+        /// Layout of JSON output
+        #[arg(long, value_enum, default_value_t = Layout::TdxQuote)]
+        layout: Layout,
+        /// Output file. By default the output is written to standard output.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum InputEncoding {
+    Raw,
+    Hex,
+    Base64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputEncoding {
+    Raw,
+    Hex,
+    Base64,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Layout {
+    TdxQuote,
+    Intel,
+    GoTdxGuest,
+}
+
+impl From<Layout> for JsonLayout {
+    fn from(layout: Layout) -> JsonLayout {
+        match layout {
+            Layout::TdxQuote => JsonLayout::TdxQuote,
+            Layout::Intel => JsonLayout::Intel,
+            Layout::GoTdxGuest => JsonLayout::GoTdxGuest,
+        }
+    }
+}
+
+/// The TCB of the platform, given alongside the collateral
+#[derive(Deserialize)]
+struct PlatformTcbFile {
+    fmspc: String,
+    pce_id: String,
+    sgx_tcb_components: [u8; 16],
+    pce_svn: u16,
+}
+
+#[derive(Deserialize)]
+struct Policy {
+    #[serde(default)]
+    quote: Value,
+    #[serde(default)]
+    accepted_tcb_statuses: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Run a command, returning whether verification succeeded
+fn run(cli: Cli) -> Result<bool, String> {
+    match cli.command {
+        Command::Inspect {
+            quote,
+            json,
+            layout,
+        } => {
+            let quote = read_quote(&quote, cli.from)?;
+            match layout {
+                Some(layout) => println!("{}", quote.to_json_with_layout(layout.into())),
+                None if json => println!("{}", quote.to_json()),
+                None => print!("{quote}"),
+            }
+            Ok(true)
+        }
+        Command::Hexdump { quote } => {
+            let input = read_input(&quote, cli.from)?;
+            let layout = QuoteLayout::from_bytes(&input);
+            print!("{}", layout.hexdump(&input));
+            Ok(layout.error.is_none())
+        }
+        Command::Verify {
+            quote,
+            pck,
+            collateral,
+            policy,
+        } => verify(
+            &read_quote(&quote, cli.from)?,
+            pck.as_deref(),
+            collateral.as_deref(),
+            policy.as_deref(),
+        ),
+        Command::Diff { old, new } => {
+            let diff = read_quote(&old, cli.from)?.diff(&read_quote(&new, cli.from)?);
+            print!("{diff}");
+            Ok(diff.is_empty())
+        }
+        Command::Convert {
+            quote,
+            to,
+            layout,
+            output,
+        } => {
+            let quote = read_quote(&quote, cli.from)?;
+            let bytes = || quote.to_bytes().map_err(|error| error.to_string());
+            let converted = match to {
+                OutputEncoding::Raw => bytes()?,
+                OutputEncoding::Hex => (hex::encode(bytes()?) + "\n").into_bytes(),
+                OutputEncoding::Base64 => (BASE64.encode(bytes()?) + "\n").into_bytes(),
+                OutputEncoding::Json => {
+                    (quote.to_json_with_layout(layout.into()) + "\n").into_bytes()
+                }
+            };
+            match output {
+                Some(path) => fs::write(&path, converted)
+                    .map_err(|error| format!("Cannot write {}: {error}", path.display()))?,
+                None => io::stdout()
+                    .write_all(&converted)
+                    .map_err(|error| format!("Cannot write output: {error}"))?,
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn verify(
+    quote: &Quote,
+    pck: Option<&str>,
+    collateral: Option<&Path>,
+    policy: Option<&Path>,
+) -> Result<bool, String> {
+    let policy = match policy {
+        Some(path) => serde_json::from_str(&read_to_string(path)?)
+            .map_err(|error| format!("Invalid policy {}: {error}", path.display()))?,
+        None => Policy {
+            quote: Value::Null,
+            accepted_tcb_statuses: Vec::new(),
+        },
+    };
+    let mut verified = true;
+    let mut report = |check: &str, result: Result<String, String>| {
+        match result {
+            Ok(message) => println!("{check}: {message}"),
+            Err(message) => {
+                println!("{check}: FAILED: {message}");
+                verified = false;
+            }
+        };
+    };
+
+    report(
+        "signature",
+        quote
+            .verify_signature()
+            .map(|_| "ok".into())
+            .map_err(|error| error.to_string()),
+    );
+    report(
+        "QE binding",
+        quote
+            .verify_qe_binding()
+            .map(|_| "ok".into())
+            .map_err(|error| error.to_string()),
+    );
+    if let Some(pck) = pck {
+        let pck = hex::decode(pck.trim())
+            .ok()
+            .and_then(|pck| VerifyingKey::from_sec1_bytes(&pck).ok())
+            .ok_or("PCK must be a hex encoded SEC1 public key")?;
+        report(
+            "PCK signature",
+            quote
+                .verify_with_pck(pck)
+                .map(|_| "ok".into())
+                .map_err(|error| error.to_string()),
+        );
+    }
+    if let Some(collateral) = collateral {
+        let (platform_tcb, tcb_info, qe_identity) = read_collateral(collateral)?;
+        report(
+            "TCB status",
+            quote
+                .evaluate_tcb(&platform_tcb, &tcb_info, &qe_identity)
+                .map_err(|error| error.to_string())
+                .and_then(|evaluation| {
+                    let status = evaluation.status();
+                    let mut message = status.as_str().to_string();
+                    if !evaluation.advisory_ids.is_empty() {
+                        message += &format!(" ({})", evaluation.advisory_ids.join(", "));
+                    }
+                    if status == TcbStatus::UpToDate
+                        || policy
+                            .accepted_tcb_statuses
+                            .iter()
+                            .any(|accepted| accepted == status.as_str())
+                    {
+                        Ok(message)
+                    } else {
+                        Err(message)
+                    }
+                }),
+        );
+    }
+    if !policy.quote.is_null() {
+        let mut mismatches = Vec::new();
+        match_policy("", &policy.quote, &json(quote), &mut mismatches);
+        report(
+            "policy",
+            if mismatches.is_empty() {
+                Ok("ok".into())
+            } else {
+                Err(mismatches.join(", "))
+            },
+        );
+    }
+    Ok(verified)
+}
+
+/// Read and parse a quote, detecting its encoding if it is not given
+fn read_quote(path: &Path, encoding: Option<InputEncoding>) -> Result<Quote, String> {
+    Quote::parse_unverified(&read_input(path, encoding)?)
+        .map_err(|error| format!("Cannot parse quote {}: {error}", path.display()))
+}
+
+/// Read a quote without parsing it, detecting its encoding if it is not given
+fn read_input(path: &Path, encoding: Option<InputEncoding>) -> Result<Vec<u8>, String> {
+    let input = if path == Path::new("-") {
+        let mut input = Vec::new();
+        io::stdin()
+            .read_to_end(&mut input)
+            .map_err(|error| format!("Cannot read standard input: {error}"))?;
+        input
+    } else {
+        fs::read(path).map_err(|error| format!("Cannot read {}: {error}", path.display()))?
+    };
+    let text = || String::from_utf8_lossy(&input).trim().to_string();
+    Ok(match encoding {
+        Some(InputEncoding::Raw) => input.clone(),
+        Some(InputEncoding::Hex) => hex::decode(text()).map_err(|error| error.to_string())?,
+        Some(InputEncoding::Base64) => BASE64.decode(text()).map_err(|error| error.to_string())?,
+        None => {
+            if Quote::parse_unverified(&input).is_ok() {
+                input.clone()
+            } else if let Ok(decoded) = hex::decode(text()) {
+                decoded
+            } else if let Ok(decoded) = BASE64.decode(text()) {
+                decoded
+            } else {
+                input.clone()
+            }
+        }
+    })
+}
+
+fn read_to_string(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|error| format!("Cannot read {}: {error}", path.display()))
+}
+
+fn read_collateral(directory: &Path) -> Result<(PlatformTcb, TcbInfo, QeIdentity), String> {
+    let path = directory.join("platform_tcb.json");
+    let platform_tcb: PlatformTcbFile = serde_json::from_str(&read_to_string(&path)?)
+        .map_err(|error| format!("Invalid {}: {error}", path.display()))?;
+    let hex_field = |value: &str| hex::decode(value).ok();
+    let platform_tcb = PlatformTcb {
+        fmspc: hex_field(&platform_tcb.fmspc)
+            .and_then(|fmspc| fmspc.try_into().ok())
+            .ok_or("fmspc in platform_tcb.json must be 6 hex encoded bytes")?,
+        pce_id: hex_field(&platform_tcb.pce_id)
+            .and_then(|pce_id| pce_id.try_into().ok())
+            .ok_or("pce_id in platform_tcb.json must be 2 hex encoded bytes")?,
+        sgx_tcb_components: platform_tcb.sgx_tcb_components,
+        pce_svn: platform_tcb.pce_svn,
+    };
+    let path = directory.join("tdx_tcb_info.json");
+    let tcb_info = TcbInfo::from_json(&read_to_string(&path)?)
+        .map_err(|error| format!("Invalid {}: {error}", path.display()))?;
+    let path = directory.join("td_qe_identity.json");
+    let qe_identity = QeIdentity::from_json(&read_to_string(&path)?)
+        .map_err(|error| format!("Invalid {}: {error}", path.display()))?;
+    Ok((platform_tcb, tcb_info, qe_identity))
+}
+
+fn json(quote: &Quote) -> Value {
+    serde_json::from_str(&quote.to_json()).expect("Quote JSON is valid")
+}
+
+/// List the fields given in a policy which have different values in the quote
+fn match_policy(path: &str, expected: &Value, actual: &Value, mismatches: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected_fields), _) => {
+            for (key, expected_value) in expected_fields {
+                let actual_value = actual.get(key).unwrap_or(&Value::Null);
+                match_policy(&join(path, key), expected_value, actual_value, mismatches);
+            }
+        }
+        _ if expected != actual => {
+            mismatches.push(format!("{path} is {actual}, expected {expected}"))
+        }
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
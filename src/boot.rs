@@ -0,0 +1,337 @@
+//! Predict the runtime measurement registers RTMR1 and RTMR2 for booting a Linux kernel
+//!
+//! TDVF maps TPM PCRs to RTMRs: PCR 1 and 7 to RTMR0, PCR 2-6 to RTMR1 and PCR 8-15 to RTMR2.
+//! Booting a kernel records the following events, in this order:
+//!
+//! - RTMR1: the action "Calling EFI Application from Boot Option", a separator, the Authenticode
+//!   hash of each EFI application loaded (for example shim, grub and then the kernel), and the two
+//!   ExitBootServices actions. The kernel embedded in a Unified Kernel Image is measured here as
+//!   well, as systemd-stub loads it with `LoadImage`, except with older versions of systemd-stub,
+//!   which start it directly (see [LinuxBoot::kernel_image_measured]).
+//! - RTMR2: any events recorded by the bootloader (for example grub's measurements of its
+//!   commands), the sections of a Unified Kernel Image as measured by systemd-stub, and then the
+//!   kernel command line and initrd as measured by the Linux EFI stub.
+use crate::{error::PeError, pe::PeImage, RtmrIndex, RtmrSet};
+use alloc::{string::String, vec::Vec};
+use sha2::{Digest, Sha384};
+
+pub const EV_SEPARATOR: u32 = 0x00000004;
+pub const EV_EVENT_TAG: u32 = 0x00000006;
+pub const EV_IPL: u32 = 0x0000000d;
+pub const EV_EFI_BOOT_SERVICES_APPLICATION: u32 = 0x80000003;
+pub const EV_EFI_ACTION: u32 = 0x80000007;
+
+const CALLING_EFI_APPLICATION: &[u8] = b"Calling EFI Application from Boot Option";
+const EXIT_BOOT_SERVICES_INVOCATION: &[u8] = b"Exit Boot Services Invocation";
+const EXIT_BOOT_SERVICES_SUCCEEDED: &[u8] = b"Exit Boot Services Returned with Success";
+
+/// Sections of a Unified Kernel Image which systemd-stub measures, in the order it measures them
+const UKI_MEASURED_SECTIONS: [&str; 10] = [
+    ".linux", ".osrel", ".cmdline", ".initrd", ".ucode", ".splash", ".dtb", ".uname", ".sbat",
+    ".pcrpkey",
+];
+
+/// An event measured into one of the runtime measurement registers
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MeasuredEvent {
+    /// Which RTMR the event was extended into
+    pub rtmr_index: RtmrIndex,
+    /// TCG event type, for example [EV_EFI_ACTION]
+    pub event_type: u32,
+    /// SHA384 digest extended into the register
+    pub digest: [u8; 48],
+}
+
+/// An ordered log of measured events
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct EventLog {
+    pub events: Vec<MeasuredEvent>,
+}
+
+impl EventLog {
+    /// Create an empty event log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event with a given digest
+    pub fn measure_digest(&mut self, rtmr_index: RtmrIndex, event_type: u32, digest: [u8; 48]) {
+        self.events.push(MeasuredEvent {
+            rtmr_index,
+            event_type,
+            digest,
+        });
+    }
+
+    /// Record an event, with the digest being the SHA384 hash of the given data
+    pub fn measure_data(&mut self, rtmr_index: RtmrIndex, event_type: u32, data: &[u8]) {
+        self.measure_digest(rtmr_index, event_type, Sha384::digest(data).into());
+    }
+
+    /// Record a separator event, which marks the end of the pre-boot environment
+    pub fn measure_separator(&mut self, rtmr_index: RtmrIndex) {
+        self.measure_data(rtmr_index, EV_SEPARATOR, &[0; 4]);
+    }
+
+    /// Extend a set of registers, starting from zero, with each event in the log
+    pub fn replay(&self) -> RtmrSet {
+        let mut rtmrs = RtmrSet::new();
+        for event in self.events.iter() {
+            rtmrs.register_mut(event.rtmr_index).extend(&event.digest);
+        }
+        rtmrs
+    }
+}
+
+/// Describes how a Linux kernel is booted, in order to predict RTMR1 and RTMR2
+#[derive(Debug, Clone)]
+pub struct LinuxBoot<'a> {
+    bootloaders: Vec<&'a [u8]>,
+    bootloader_events: Vec<&'a [u8]>,
+    uki_sections: Vec<(&'static str, &'a [u8])>,
+    kernel: &'a [u8],
+    kernel_image_measured: bool,
+    initrd: Option<&'a [u8]>,
+    cmdline: Option<String>,
+}
+
+impl<'a> LinuxBoot<'a> {
+    /// Boot the given kernel image (a PE/COFF bzImage with an EFI stub) directly from the firmware
+    pub fn new(kernel: &'a [u8]) -> Self {
+        Self {
+            bootloaders: Vec::new(),
+            bootloader_events: Vec::new(),
+            uki_sections: Vec::new(),
+            kernel,
+            kernel_image_measured: true,
+            initrd: None,
+            cmdline: None,
+        }
+    }
+
+    /// Boot a Unified Kernel Image with systemd-stub, which contains the kernel, and optionally
+    /// the command line and the initrd. The UKI is measured as an EFI application, followed by the
+    /// kernel it contains.
+    pub fn unified_kernel_image(uki: &'a [u8]) -> Result<Self, PeError> {
+        let image = PeImage::parse(uki)?;
+        let kernel = image
+            .section(".linux")
+            .ok_or(PeError::MissingSection)?
+            .data();
+        let mut boot = Self::new(kernel).bootloader(uki);
+        for name in UKI_MEASURED_SECTIONS {
+            if let Some(section) = image.section(name) {
+                boot.uki_sections.push((name, section.data()));
+            }
+        }
+        if let Some(cmdline) = image.section(".cmdline") {
+            let cmdline = String::from_utf8_lossy(cmdline.data());
+            boot = boot.cmdline(cmdline.trim_end_matches('\0'));
+        }
+        if let Some(initrd) = image.section(".initrd") {
+            boot = boot.initrd(initrd.data());
+        }
+        Ok(boot)
+    }
+
+    /// Whether the firmware measures the kernel image as an EFI application, which is the default.
+    ///
+    /// This is the case when the kernel is loaded by the firmware, or by a bootloader using
+    /// `LoadImage`, including current versions of systemd-stub. Older versions of systemd-stub
+    /// jump to the kernel embedded in a UKI without loading it as an image, so it is not measured.
+    pub fn kernel_image_measured(mut self, measured: bool) -> Self {
+        self.kernel_image_measured = measured;
+        self
+    }
+
+    /// Add an EFI application which is loaded before the kernel, for example shim or grub
+    pub fn bootloader(mut self, image: &'a [u8]) -> Self {
+        self.bootloaders.push(image);
+        self
+    }
+
+    /// Add an event recorded by the bootloader into RTMR2, for example grub's measurement of a
+    /// command (`grub_cmd: ...`) or of a file it reads
+    pub fn bootloader_event(mut self, data: &'a [u8]) -> Self {
+        self.bootloader_events.push(data);
+        self
+    }
+
+    /// The initrd passed to the kernel
+    pub fn initrd(mut self, initrd: &'a [u8]) -> Self {
+        self.initrd = Some(initrd);
+        self
+    }
+
+    /// The kernel command line, exactly as passed to the kernel's EFI stub
+    ///
+    /// When booting with QEMU's `-kernel` option and an initrd, OVMF appends ` initrd=initrd` to
+    /// the given command line, see [qemu_direct_boot_cmdline]
+    pub fn cmdline(mut self, cmdline: &str) -> Self {
+        self.cmdline = Some(cmdline.into());
+        self
+    }
+
+    /// Get the log of events for RTMR1 and RTMR2
+    pub fn event_log(&self) -> Result<EventLog, PeError> {
+        let mut log = EventLog::new();
+
+        log.measure_data(RtmrIndex::Rtmr1, EV_EFI_ACTION, CALLING_EFI_APPLICATION);
+        log.measure_separator(RtmrIndex::Rtmr1);
+        let kernel = self.kernel_image_measured.then_some(&self.kernel);
+        for image in self.bootloaders.iter().chain(kernel) {
+            let hash = PeImage::parse(image)?.authenticode_sha384()?;
+            log.measure_digest(RtmrIndex::Rtmr1, EV_EFI_BOOT_SERVICES_APPLICATION, hash);
+        }
+        log.measure_data(
+            RtmrIndex::Rtmr1,
+            EV_EFI_ACTION,
+            EXIT_BOOT_SERVICES_INVOCATION,
+        );
+        log.measure_data(
+            RtmrIndex::Rtmr1,
+            EV_EFI_ACTION,
+            EXIT_BOOT_SERVICES_SUCCEEDED,
+        );
+
+        for data in self.bootloader_events.iter() {
+            log.measure_data(RtmrIndex::Rtmr2, EV_IPL, data);
+        }
+        for (name, data) in self.uki_sections.iter() {
+            // The section name is measured including its null terminator
+            let mut name = Vec::from(name.as_bytes());
+            name.push(0);
+            log.measure_data(RtmrIndex::Rtmr2, EV_IPL, &name);
+            log.measure_data(RtmrIndex::Rtmr2, EV_IPL, data);
+        }
+        if let Some(cmdline) = &self.cmdline {
+            // The EFI load options are UTF-16 with a null terminator
+            let load_options: Vec<u8> = cmdline
+                .encode_utf16()
+                .chain([0])
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            log.measure_data(RtmrIndex::Rtmr2, EV_EVENT_TAG, &load_options);
+        }
+        if let Some(initrd) = self.initrd {
+            log.measure_data(RtmrIndex::Rtmr2, EV_EVENT_TAG, initrd);
+        }
+
+        Ok(log)
+    }
+
+    /// Compute the expected RTMR values. Only RTMR1 and RTMR2 are affected by the boot chain,
+    /// RTMR0 and RTMR3 are left zeroed
+    pub fn rtmrs(&self) -> Result<RtmrSet, PeError> {
+        Ok(self.event_log()?.replay())
+    }
+}
+
+/// The command line OVMF passes to the kernel when booting with QEMU's `-kernel` option
+pub fn qemu_direct_boot_cmdline(cmdline: &str, has_initrd: bool) -> String {
+    let mut cmdline = String::from(cmdline);
+    if has_initrd {
+        cmdline.push_str(" initrd=initrd");
+    }
+    cmdline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pe::{test_image, tests::SIGNED_IMAGE},
+        rtmr::tests::from_hex,
+        Rtmr,
+    };
+
+    #[test]
+    fn test_direct_boot() {
+        let kernel = test_image(&[(".text", &[1; 0x100])]);
+        let rtmrs = LinuxBoot::new(&kernel)
+            .initrd(b"initrd")
+            .cmdline("console=ttyS0")
+            .rtmrs()
+            .unwrap();
+
+        let mut rtmr1 = Rtmr::new();
+        rtmr1.extend_data(CALLING_EFI_APPLICATION);
+        rtmr1.extend_data(&[0; 4]);
+        rtmr1.extend(
+            &PeImage::parse(&kernel)
+                .unwrap()
+                .authenticode_sha384()
+                .unwrap(),
+        );
+        rtmr1.extend_data(EXIT_BOOT_SERVICES_INVOCATION);
+        rtmr1.extend_data(EXIT_BOOT_SERVICES_SUCCEEDED);
+        assert_eq!(rtmrs.rtmr1, rtmr1);
+
+        let mut rtmr2 = Rtmr::new();
+        rtmr2.extend_data(b"c\0o\0n\0s\0o\0l\0e\0=\0t\0t\0y\0S\x000\0\0\0");
+        rtmr2.extend_data(b"initrd");
+        assert_eq!(rtmrs.rtmr2, rtmr2);
+        assert_eq!(rtmrs.rtmr0, Rtmr::new());
+    }
+
+    #[test]
+    fn test_direct_boot_known_answer() {
+        // The values were computed independently of this crate, by hashing the events described
+        // in the module documentation. The image is a signed PE executable standing in for a
+        // kernel, whose Authenticode hash is checked against its signature in the pe module.
+        let cmdline = qemu_direct_boot_cmdline("console=ttyS0", true);
+        let rtmrs = LinuxBoot::new(SIGNED_IMAGE)
+            .initrd(b"initrd")
+            .cmdline(&cmdline)
+            .rtmrs()
+            .unwrap();
+        assert_eq!(
+            rtmrs.rtmr1.value(),
+            from_hex("fa7dfc66905a5ccee23e9221b291c5a223f4e304ff766263044cebbbb2176d1b7294bc9fe611e604f7100a34ae1ff7aa")
+        );
+        assert_eq!(
+            rtmrs.rtmr2.value(),
+            from_hex("8df12e8852dbefc4990441727587784d458eaebd424a7449df4cac5ff5c5697136089e5d23371935abdd67d6e7cc1bb7")
+        );
+    }
+
+    #[test]
+    fn test_unified_kernel_image() {
+        let kernel = test_image(&[(".text", &[1; 0x100])]);
+        let uki = test_image(&[
+            (".cmdline", b"quiet\0"),
+            (".initrd", b"initrd"),
+            (".linux", &kernel),
+        ]);
+        let log = LinuxBoot::unified_kernel_image(&uki)
+            .unwrap()
+            .event_log()
+            .unwrap();
+        // Action, separator, UKI, kernel, 2 exit boot services actions, 3 sections with their
+        // names, command line and initrd
+        assert_eq!(log.events.len(), 14);
+        let rtmr1_events = log
+            .events
+            .iter()
+            .filter(|e| e.rtmr_index == RtmrIndex::Rtmr1)
+            .count();
+        assert_eq!(rtmr1_events, 6);
+
+        // Older versions of systemd-stub do not load the kernel as an image
+        let log = LinuxBoot::unified_kernel_image(&uki)
+            .unwrap()
+            .kernel_image_measured(false)
+            .event_log()
+            .unwrap();
+        let applications: Vec<_> = log
+            .events
+            .iter()
+            .filter(|e| e.event_type == EV_EFI_BOOT_SERVICES_APPLICATION)
+            .map(|e| e.digest)
+            .collect();
+        assert_eq!(
+            applications,
+            [PeImage::parse(&uki).unwrap().authenticode_sha384().unwrap()]
+        );
+    }
+}
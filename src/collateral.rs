@@ -0,0 +1,633 @@
+//! Intel collateral (TCB Info and QE Identity) in a compact binary form, and evaluation of the
+//! TCB status of a quote against it
+//!
+//! The compact form contains everything needed for TCB evaluation, so that verifiers without a
+//! JSON parser can use it. It begins with a magic number and a format version. Integers are little
+//! endian, and dates are seconds since the Unix epoch. Lists are prefixed with their length.
+use crate::{error::CollateralError, Quote};
+use alloc::{string::String, vec::Vec};
+use nom::{
+    bytes::complete::take,
+    combinator::{all_consuming, map_res},
+    multi::length_count,
+    number::complete::{le_u16, le_u32, le_u64, u8},
+    IResult,
+};
+
+/// The version of the compact encoding produced by this crate
+pub const COMPACT_COLLATERAL_VERSION: u8 = 1;
+
+const TCB_INFO_MAGIC: &[u8; 4] = b"TCBI";
+const QE_IDENTITY_MAGIC: &[u8; 4] = b"QEID";
+
+/// The TCB status of a platform, TDX module or quoting enclave. These are ordered from best to
+/// worst.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TcbStatus {
+    UpToDate,
+    SWHardeningNeeded,
+    ConfigurationNeeded,
+    ConfigurationAndSWHardeningNeeded,
+    OutOfDate,
+    OutOfDateConfigurationNeeded,
+    Revoked,
+}
+
+impl TcbStatus {
+    /// The status as it is written in Intel's collateral
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TcbStatus::UpToDate => "UpToDate",
+            TcbStatus::SWHardeningNeeded => "SWHardeningNeeded",
+            TcbStatus::ConfigurationNeeded => "ConfigurationNeeded",
+            TcbStatus::ConfigurationAndSWHardeningNeeded => "ConfigurationAndSWHardeningNeeded",
+            TcbStatus::OutOfDate => "OutOfDate",
+            TcbStatus::OutOfDateConfigurationNeeded => "OutOfDateConfigurationNeeded",
+            TcbStatus::Revoked => "Revoked",
+        }
+    }
+}
+
+impl TryFrom<u8> for TcbStatus {
+    type Error = CollateralError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TcbStatus::UpToDate),
+            1 => Ok(TcbStatus::SWHardeningNeeded),
+            2 => Ok(TcbStatus::ConfigurationNeeded),
+            3 => Ok(TcbStatus::ConfigurationAndSWHardeningNeeded),
+            4 => Ok(TcbStatus::OutOfDate),
+            5 => Ok(TcbStatus::OutOfDateConfigurationNeeded),
+            6 => Ok(TcbStatus::Revoked),
+            _ => Err(CollateralError::Malformed),
+        }
+    }
+}
+
+/// Refers to the signed JSON which a compact encoding was converted from, so that it can be
+/// audited. The JSON itself is not kept.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SignatureReference {
+    /// Signature by the Intel TCB signing key
+    pub signature: [u8; 64],
+    /// SHA-256 of the signed part of the JSON (the `tcbInfo` or `enclaveIdentity` object)
+    pub signed_data_hash: [u8; 32],
+}
+
+/// The TDX module expected by a TCB Info
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TdxModule {
+    pub mrsigner: [u8; 48],
+    pub attributes: [u8; 8],
+    pub attributes_mask: [u8; 8],
+}
+
+/// A TDX module identity and its TCB levels, for a given TDX module major version
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TdxModuleIdentity {
+    /// The major version, as in the identity `TDX_01`
+    pub major_version: u8,
+    pub module: TdxModule,
+    pub tcb_levels: Vec<TdxModuleTcbLevel>,
+}
+
+/// The status of TDX modules with at least the given SVN
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TdxModuleTcbLevel {
+    pub isv_svn: u8,
+    pub tcb_date: u64,
+    pub status: TcbStatus,
+}
+
+/// The status of platforms with at least the given SVNs
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TcbLevel {
+    pub sgx_tcb_components: [u8; 16],
+    pub pce_svn: u16,
+    /// All zero for SGX TCB Info
+    pub tdx_tcb_components: [u8; 16],
+    pub tcb_date: u64,
+    pub status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+/// TCB Info for a platform family (FMSPC)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TcbInfo {
+    /// The version of the TCB Info JSON
+    pub version: u8,
+    /// Whether this is TDX rather than SGX TCB Info
+    pub tdx: bool,
+    pub issue_date: u64,
+    pub next_update: u64,
+    pub fmspc: [u8; 6],
+    pub pce_id: [u8; 2],
+    pub tcb_type: u8,
+    pub tcb_evaluation_data_number: u32,
+    pub tdx_module: Option<TdxModule>,
+    pub tdx_module_identities: Vec<TdxModuleIdentity>,
+    /// Ordered from the highest TCB level to the lowest
+    pub tcb_levels: Vec<TcbLevel>,
+    pub signature_reference: SignatureReference,
+}
+
+/// The enclave which a QE Identity describes
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EnclaveId {
+    Qe,
+    Qve,
+    TdQe,
+}
+
+impl TryFrom<u8> for EnclaveId {
+    type Error = CollateralError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(EnclaveId::Qe),
+            1 => Ok(EnclaveId::Qve),
+            2 => Ok(EnclaveId::TdQe),
+            _ => Err(CollateralError::Malformed),
+        }
+    }
+}
+
+/// The status of quoting enclaves with at least the given SVN
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QeTcbLevel {
+    pub isv_svn: u16,
+    pub tcb_date: u64,
+    pub status: TcbStatus,
+}
+
+/// The identity of Intel's quoting enclave
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QeIdentity {
+    /// The version of the QE Identity JSON
+    pub version: u8,
+    pub id: EnclaveId,
+    pub issue_date: u64,
+    pub next_update: u64,
+    pub tcb_evaluation_data_number: u32,
+    pub misc_select: u32,
+    pub misc_select_mask: u32,
+    pub attributes: [u8; 16],
+    pub attributes_mask: [u8; 16],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    /// Ordered from the highest TCB level to the lowest
+    pub tcb_levels: Vec<QeTcbLevel>,
+    pub signature_reference: SignatureReference,
+}
+
+/// The TCB of a platform, as given in the extensions of its PCK certificate
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlatformTcb {
+    pub fmspc: [u8; 6],
+    pub pce_id: [u8; 2],
+    pub sgx_tcb_components: [u8; 16],
+    pub pce_svn: u16,
+}
+
+/// The result of evaluating the TCB status of a quote
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TcbEvaluation {
+    /// Status of the platform (the SGX and TDX TCB components)
+    pub platform: TcbStatus,
+    /// Status of the TDX module, if the TCB Info has TDX module identities
+    pub tdx_module: Option<TcbStatus>,
+    /// Status of the quoting enclave
+    pub qe: TcbStatus,
+    /// Security advisories which apply to the platform TCB level
+    pub advisory_ids: Vec<String>,
+}
+
+impl TcbEvaluation {
+    /// The worst of the platform, TDX module and quoting enclave statuses
+    pub fn status(&self) -> TcbStatus {
+        self.platform
+            .max(self.tdx_module.unwrap_or(TcbStatus::UpToDate))
+            .max(self.qe)
+    }
+}
+
+impl TcbInfo {
+    /// Decode TCB Info from its compact encoding
+    pub fn from_compact_bytes(input: &[u8]) -> Result<Self, CollateralError> {
+        let input = compact_header(TCB_INFO_MAGIC, input)?;
+        let (_, tcb_info) = all_consuming(tcb_info_parser)(input)?;
+        Ok(tcb_info)
+    }
+
+    /// Encode as compact bytes. This fails if a list or advisory ID is too long for the length
+    /// prefix of the compact encoding.
+    pub fn to_compact_bytes(&self) -> Result<Vec<u8>, CollateralError> {
+        let mut output = compact_header_bytes(TCB_INFO_MAGIC);
+        output.push(self.version);
+        output.push(self.tdx as u8);
+        output.extend(self.issue_date.to_le_bytes());
+        output.extend(self.next_update.to_le_bytes());
+        output.extend(self.fmspc);
+        output.extend(self.pce_id);
+        output.push(self.tcb_type);
+        output.extend(self.tcb_evaluation_data_number.to_le_bytes());
+        output.extend(self.signature_reference.signature);
+        output.extend(self.signature_reference.signed_data_hash);
+        match &self.tdx_module {
+            Some(tdx_module) => {
+                output.push(1);
+                write_tdx_module(&mut output, tdx_module);
+            }
+            None => output.push(0),
+        }
+        output.push(length(
+            self.tdx_module_identities.len(),
+            "TDX module identities",
+        )?);
+        for identity in &self.tdx_module_identities {
+            output.push(identity.major_version);
+            write_tdx_module(&mut output, &identity.module);
+            output.push(length(identity.tcb_levels.len(), "TDX module TCB levels")?);
+            for tcb_level in &identity.tcb_levels {
+                output.push(tcb_level.isv_svn);
+                output.extend(tcb_level.tcb_date.to_le_bytes());
+                output.push(tcb_level.status as u8);
+            }
+        }
+        output.extend(length::<u16>(self.tcb_levels.len(), "TCB levels")?.to_le_bytes());
+        for tcb_level in &self.tcb_levels {
+            output.extend(tcb_level.sgx_tcb_components);
+            output.extend(tcb_level.pce_svn.to_le_bytes());
+            output.extend(tcb_level.tdx_tcb_components);
+            output.extend(tcb_level.tcb_date.to_le_bytes());
+            output.push(tcb_level.status as u8);
+            output.push(length(tcb_level.advisory_ids.len(), "advisory IDs")?);
+            for advisory_id in &tcb_level.advisory_ids {
+                output.push(length(advisory_id.len(), "advisory ID")?);
+                output.extend(advisory_id.as_bytes());
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl QeIdentity {
+    /// Decode a QE Identity from its compact encoding
+    pub fn from_compact_bytes(input: &[u8]) -> Result<Self, CollateralError> {
+        let input = compact_header(QE_IDENTITY_MAGIC, input)?;
+        let (_, qe_identity) = all_consuming(qe_identity_parser)(input)?;
+        Ok(qe_identity)
+    }
+
+    /// Encode as compact bytes. This fails if there are too many TCB levels for the length prefix
+    /// of the compact encoding.
+    pub fn to_compact_bytes(&self) -> Result<Vec<u8>, CollateralError> {
+        let mut output = compact_header_bytes(QE_IDENTITY_MAGIC);
+        output.push(self.version);
+        output.push(self.id as u8);
+        output.extend(self.issue_date.to_le_bytes());
+        output.extend(self.next_update.to_le_bytes());
+        output.extend(self.tcb_evaluation_data_number.to_le_bytes());
+        output.extend(self.signature_reference.signature);
+        output.extend(self.signature_reference.signed_data_hash);
+        output.extend(self.misc_select.to_le_bytes());
+        output.extend(self.misc_select_mask.to_le_bytes());
+        output.extend(self.attributes);
+        output.extend(self.attributes_mask);
+        output.extend(self.mr_signer);
+        output.extend(self.isv_prod_id.to_le_bytes());
+        output.push(length(self.tcb_levels.len(), "QE TCB levels")?);
+        for tcb_level in &self.tcb_levels {
+            output.extend(tcb_level.isv_svn.to_le_bytes());
+            output.extend(tcb_level.tcb_date.to_le_bytes());
+            output.push(tcb_level.status as u8);
+        }
+        Ok(output)
+    }
+}
+
+/// Evaluate the TCB status of a TDX quote, following Intel's TDX quote verification
+pub(crate) fn evaluate_tcb(
+    quote: &Quote,
+    platform: &PlatformTcb,
+    tcb_info: &TcbInfo,
+    qe_identity: &QeIdentity,
+) -> Result<TcbEvaluation, CollateralError> {
+    let body = quote.td_body().ok_or(CollateralError::NotTdQuote)?;
+    if !tcb_info.tdx {
+        return Err(CollateralError::NotTdxTcbInfo);
+    }
+    if qe_identity.id != EnclaveId::TdQe {
+        return Err(CollateralError::NotTdQeIdentity);
+    }
+    if platform.fmspc != tcb_info.fmspc {
+        return Err(CollateralError::FmspcMismatch);
+    }
+    if platform.pce_id != tcb_info.pce_id {
+        return Err(CollateralError::PceIdMismatch);
+    }
+
+    // The TDX module. If the major version is not zero, there is an identity for that version
+    // which gives the status of the module, and the first two TDX TCB components are not compared.
+    let [module_svn, module_major_version, ..] = body.tee_tcb_svn;
+    if let Some(tdx_module) = &tcb_info.tdx_module {
+        check_tdx_module(tdx_module, &body.mrsignerseam, &body.seamattributes)?;
+    }
+    let (tdx_module, first_tdx_component) =
+        if module_major_version > 0 && !tcb_info.tdx_module_identities.is_empty() {
+            let identity = tcb_info
+                .tdx_module_identities
+                .iter()
+                .find(|identity| identity.major_version == module_major_version)
+                .ok_or(CollateralError::NoTdxModuleIdentity(module_major_version))?;
+            check_tdx_module(&identity.module, &body.mrsignerseam, &body.seamattributes)?;
+            let tcb_level = identity
+                .tcb_levels
+                .iter()
+                .find(|tcb_level| tcb_level.isv_svn <= module_svn)
+                .ok_or(CollateralError::NoMatchingTcbLevel)?;
+            (Some(tcb_level.status), 2)
+        } else {
+            (None, 0)
+        };
+
+    // The platform
+    let tcb_level = tcb_info
+        .tcb_levels
+        .iter()
+        .find(|tcb_level| {
+            at_least(&platform.sgx_tcb_components, &tcb_level.sgx_tcb_components)
+                && platform.pce_svn >= tcb_level.pce_svn
+                && at_least(
+                    &body.tee_tcb_svn[first_tdx_component..],
+                    &tcb_level.tdx_tcb_components[first_tdx_component..],
+                )
+        })
+        .ok_or(CollateralError::NoMatchingTcbLevel)?;
+
+    // The quoting enclave
+    let qe_report_body = quote
+        .qe_report_certification_data()
+        .ok_or(CollateralError::NoQeReportCertificationData)?
+        .qe_report_body()?;
+    if qe_report_body.mr_signer != qe_identity.mr_signer
+        || qe_report_body.isv_prod_id != qe_identity.isv_prod_id
+        || qe_report_body.misc_select & qe_identity.misc_select_mask
+            != qe_identity.misc_select & qe_identity.misc_select_mask
+        || !masked_equal(
+            &qe_report_body.attributes,
+            &qe_identity.attributes,
+            &qe_identity.attributes_mask,
+        )
+    {
+        return Err(CollateralError::QeIdentityMismatch);
+    }
+    let qe_tcb_level = qe_identity
+        .tcb_levels
+        .iter()
+        .find(|tcb_level| tcb_level.isv_svn <= qe_report_body.isv_svn)
+        .ok_or(CollateralError::NoMatchingTcbLevel)?;
+
+    Ok(TcbEvaluation {
+        platform: tcb_level.status,
+        tdx_module,
+        qe: qe_tcb_level.status,
+        advisory_ids: tcb_level.advisory_ids.clone(),
+    })
+}
+
+/// Check that the TDX module signer and attributes match those expected
+fn check_tdx_module(
+    tdx_module: &TdxModule,
+    mrsignerseam: &[u8; 48],
+    seamattributes: &[u8; 8],
+) -> Result<(), CollateralError> {
+    if *mrsignerseam != tdx_module.mrsigner
+        || !masked_equal(
+            seamattributes,
+            &tdx_module.attributes,
+            &tdx_module.attributes_mask,
+        )
+    {
+        return Err(CollateralError::TdxModuleMismatch);
+    }
+    Ok(())
+}
+
+/// Whether each SVN is at least that of the corresponding component of the TCB level
+fn at_least(svns: &[u8], tcb_level_svns: &[u8]) -> bool {
+    svns.iter()
+        .zip(tcb_level_svns)
+        .all(|(svn, tcb_level_svn)| svn >= tcb_level_svn)
+}
+
+/// Whether the value, with the mask applied, equals the expected value with the mask applied
+fn masked_equal(value: &[u8], expected: &[u8], mask: &[u8]) -> bool {
+    value
+        .iter()
+        .zip(expected)
+        .zip(mask)
+        .all(|((value, expected), mask)| value & mask == expected & mask)
+}
+
+/// Convert a length to the type of its prefix in the compact encoding
+fn length<T: TryFrom<usize>>(length: usize, field: &'static str) -> Result<T, CollateralError> {
+    T::try_from(length).map_err(|_| CollateralError::TooLong(field))
+}
+
+/// The magic number followed by the format version
+fn compact_header_bytes(magic: &[u8; 4]) -> Vec<u8> {
+    let mut output = magic.to_vec();
+    output.push(COMPACT_COLLATERAL_VERSION);
+    output
+}
+
+/// Check the magic number and format version, returning the rest of the input
+fn compact_header<'a>(magic: &[u8; 4], input: &'a [u8]) -> Result<&'a [u8], CollateralError> {
+    let (format_version, input) = input
+        .strip_prefix(&magic[..])
+        .and_then(|input| input.split_first())
+        .ok_or(CollateralError::Malformed)?;
+    if *format_version != COMPACT_COLLATERAL_VERSION {
+        return Err(CollateralError::UnsupportedFormatVersion(*format_version));
+    }
+    Ok(input)
+}
+
+/// The compact encoding of TCB Info, following the header
+fn tcb_info_parser(input: &[u8]) -> IResult<&[u8], TcbInfo> {
+    let (input, version) = u8(input)?;
+    let (input, tdx) = u8(input)?;
+    let (input, issue_date) = le_u64(input)?;
+    let (input, next_update) = le_u64(input)?;
+    let (input, fmspc) = array(input)?;
+    let (input, pce_id) = array(input)?;
+    let (input, tcb_type) = u8(input)?;
+    let (input, tcb_evaluation_data_number) = le_u32(input)?;
+    let (input, signature_reference) = signature_reference_parser(input)?;
+    let (input, has_tdx_module) = u8(input)?;
+    let (input, tdx_module) = match has_tdx_module {
+        0 => (input, None),
+        _ => {
+            let (input, tdx_module) = tdx_module_parser(input)?;
+            (input, Some(tdx_module))
+        }
+    };
+    let (input, tdx_module_identities) = length_count(u8, tdx_module_identity_parser)(input)?;
+    let (input, tcb_levels) = length_count(le_u16, tcb_level_parser)(input)?;
+    Ok((
+        input,
+        TcbInfo {
+            version,
+            tdx: tdx != 0,
+            issue_date,
+            next_update,
+            fmspc,
+            pce_id,
+            tcb_type,
+            tcb_evaluation_data_number,
+            tdx_module,
+            tdx_module_identities,
+            tcb_levels,
+            signature_reference,
+        },
+    ))
+}
+
+/// The compact encoding of a QE Identity, following the header
+fn qe_identity_parser(input: &[u8]) -> IResult<&[u8], QeIdentity> {
+    let (input, version) = u8(input)?;
+    let (input, id) = map_res(u8, EnclaveId::try_from)(input)?;
+    let (input, issue_date) = le_u64(input)?;
+    let (input, next_update) = le_u64(input)?;
+    let (input, tcb_evaluation_data_number) = le_u32(input)?;
+    let (input, signature_reference) = signature_reference_parser(input)?;
+    let (input, misc_select) = le_u32(input)?;
+    let (input, misc_select_mask) = le_u32(input)?;
+    let (input, attributes) = array(input)?;
+    let (input, attributes_mask) = array(input)?;
+    let (input, mr_signer) = array(input)?;
+    let (input, isv_prod_id) = le_u16(input)?;
+    let (input, tcb_levels) = length_count(u8, qe_tcb_level_parser)(input)?;
+    Ok((
+        input,
+        QeIdentity {
+            version,
+            id,
+            issue_date,
+            next_update,
+            tcb_evaluation_data_number,
+            misc_select,
+            misc_select_mask,
+            attributes,
+            attributes_mask,
+            mr_signer,
+            isv_prod_id,
+            tcb_levels,
+            signature_reference,
+        },
+    ))
+}
+
+fn write_tdx_module(output: &mut Vec<u8>, tdx_module: &TdxModule) {
+    output.extend(tdx_module.mrsigner);
+    output.extend(tdx_module.attributes);
+    output.extend(tdx_module.attributes_mask);
+}
+
+fn array<const N: usize>(input: &[u8]) -> IResult<&[u8], [u8; N]> {
+    let (input, bytes) = take(N)(input)?;
+    Ok((input, bytes.try_into().unwrap()))
+}
+
+fn signature_reference_parser(input: &[u8]) -> IResult<&[u8], SignatureReference> {
+    let (input, signature) = array(input)?;
+    let (input, signed_data_hash) = array(input)?;
+    Ok((
+        input,
+        SignatureReference {
+            signature,
+            signed_data_hash,
+        },
+    ))
+}
+
+fn tdx_module_parser(input: &[u8]) -> IResult<&[u8], TdxModule> {
+    let (input, mrsigner) = array(input)?;
+    let (input, attributes) = array(input)?;
+    let (input, attributes_mask) = array(input)?;
+    Ok((
+        input,
+        TdxModule {
+            mrsigner,
+            attributes,
+            attributes_mask,
+        },
+    ))
+}
+
+fn tcb_status_parser(input: &[u8]) -> IResult<&[u8], TcbStatus> {
+    map_res(u8, TcbStatus::try_from)(input)
+}
+
+fn tdx_module_identity_parser(input: &[u8]) -> IResult<&[u8], TdxModuleIdentity> {
+    let (input, major_version) = u8(input)?;
+    let (input, module) = tdx_module_parser(input)?;
+    let (input, tcb_levels) = length_count(u8, |input| {
+        let (input, isv_svn) = u8(input)?;
+        let (input, tcb_date) = le_u64(input)?;
+        let (input, status) = tcb_status_parser(input)?;
+        Ok((
+            input,
+            TdxModuleTcbLevel {
+                isv_svn,
+                tcb_date,
+                status,
+            },
+        ))
+    })(input)?;
+    Ok((
+        input,
+        TdxModuleIdentity {
+            major_version,
+            module,
+            tcb_levels,
+        },
+    ))
+}
+
+fn tcb_level_parser(input: &[u8]) -> IResult<&[u8], TcbLevel> {
+    let (input, sgx_tcb_components) = array(input)?;
+    let (input, pce_svn) = le_u16(input)?;
+    let (input, tdx_tcb_components) = array(input)?;
+    let (input, tcb_date) = le_u64(input)?;
+    let (input, status) = tcb_status_parser(input)?;
+    let (input, advisory_ids) =
+        length_count(u8, map_res(length_count(u8, u8), String::from_utf8))(input)?;
+    Ok((
+        input,
+        TcbLevel {
+            sgx_tcb_components,
+            pce_svn,
+            tdx_tcb_components,
+            tcb_date,
+            status,
+            advisory_ids,
+        },
+    ))
+}
+
+fn qe_tcb_level_parser(input: &[u8]) -> IResult<&[u8], QeTcbLevel> {
+    let (input, isv_svn) = le_u16(input)?;
+    let (input, tcb_date) = le_u64(input)?;
+    let (input, status) = tcb_status_parser(input)?;
+    Ok((
+        input,
+        QeTcbLevel {
+            isv_svn,
+            tcb_date,
+            status,
+        },
+    ))
+}
@@ -0,0 +1,359 @@
+//! Conversion from the signed TCB Info and QE Identity JSON served by Intel's provisioning
+//! certification service to the compact form
+use crate::{
+    error::CollateralError, EnclaveId, QeIdentity, QeTcbLevel, SignatureReference, TcbInfo,
+    TcbLevel, TcbStatus, TdxModule, TdxModuleIdentity, TdxModuleTcbLevel,
+};
+use alloc::{string::String, vec::Vec};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
+
+impl TcbInfo {
+    /// Convert signed TCB Info JSON, as served by Intel, to its compact form. The signature is not
+    /// verified.
+    pub fn from_json(json: &str) -> Result<Self, CollateralError> {
+        let signed: SignedTcbInfo = from_str(json)?;
+        let signature_reference = signature_reference(signed.tcb_info, &signed.signature)?;
+        let tcb_info: TcbInfoJson = from_str(signed.tcb_info.get())?;
+        Ok(TcbInfo {
+            version: tcb_info.version,
+            tdx: match tcb_info.id.as_deref() {
+                None | Some("SGX") => false,
+                Some("TDX") => true,
+                Some(_) => return Err(CollateralError::InvalidJsonField("id")),
+            },
+            issue_date: date(&tcb_info.issue_date, "issueDate")?,
+            next_update: date(&tcb_info.next_update, "nextUpdate")?,
+            fmspc: hex(&tcb_info.fmspc, "fmspc")?,
+            pce_id: hex(&tcb_info.pce_id, "pceId")?,
+            tcb_type: tcb_info.tcb_type,
+            tcb_evaluation_data_number: tcb_info.tcb_evaluation_data_number,
+            tdx_module: tcb_info
+                .tdx_module
+                .map(|tdx_module| tdx_module.try_into())
+                .transpose()?,
+            tdx_module_identities: tcb_info
+                .tdx_module_identities
+                .into_iter()
+                .map(|identity| identity.try_into())
+                .collect::<Result<_, _>>()?,
+            tcb_levels: tcb_info
+                .tcb_levels
+                .into_iter()
+                .map(|tcb_level| tcb_level.try_into())
+                .collect::<Result<_, _>>()?,
+            signature_reference,
+        })
+    }
+}
+
+impl QeIdentity {
+    /// Convert signed QE Identity JSON, as served by Intel, to its compact form. The signature is
+    /// not verified.
+    pub fn from_json(json: &str) -> Result<Self, CollateralError> {
+        let signed: SignedQeIdentity = from_str(json)?;
+        let signature_reference = signature_reference(signed.enclave_identity, &signed.signature)?;
+        let identity: QeIdentityJson = from_str(signed.enclave_identity.get())?;
+        Ok(QeIdentity {
+            version: identity.version,
+            id: match identity.id.as_str() {
+                "QE" => EnclaveId::Qe,
+                "QVE" => EnclaveId::Qve,
+                "TD_QE" => EnclaveId::TdQe,
+                _ => return Err(CollateralError::InvalidJsonField("id")),
+            },
+            issue_date: date(&identity.issue_date, "issueDate")?,
+            next_update: date(&identity.next_update, "nextUpdate")?,
+            tcb_evaluation_data_number: identity.tcb_evaluation_data_number,
+            misc_select: u32::from_be_bytes(hex(&identity.miscselect, "miscselect")?),
+            misc_select_mask: u32::from_be_bytes(hex(&identity.miscselect_mask, "miscselectMask")?),
+            attributes: hex(&identity.attributes, "attributes")?,
+            attributes_mask: hex(&identity.attributes_mask, "attributesMask")?,
+            mr_signer: hex(&identity.mrsigner, "mrsigner")?,
+            isv_prod_id: identity.isvprodid,
+            tcb_levels: identity
+                .tcb_levels
+                .into_iter()
+                .map(|tcb_level| tcb_level.try_into())
+                .collect::<Result<_, _>>()?,
+            signature_reference,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedTcbInfo<'a> {
+    #[serde(borrow)]
+    tcb_info: &'a RawValue,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbInfoJson {
+    id: Option<String>,
+    version: u8,
+    issue_date: String,
+    next_update: String,
+    fmspc: String,
+    pce_id: String,
+    #[serde(default)]
+    tcb_type: u8,
+    tcb_evaluation_data_number: u32,
+    tdx_module: Option<TdxModuleJson>,
+    #[serde(default)]
+    tdx_module_identities: Vec<TdxModuleIdentityJson>,
+    tcb_levels: Vec<TcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModuleJson {
+    mrsigner: String,
+    attributes: String,
+    attributes_mask: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TdxModuleIdentityJson {
+    id: String,
+    #[serde(flatten)]
+    module: TdxModuleJson,
+    tcb_levels: Vec<IsvSvnTcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbLevelJson {
+    tcb: TcbJson,
+    tcb_date: String,
+    tcb_status: String,
+    #[serde(default, rename = "advisoryIDs")]
+    advisory_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TcbJson {
+    sgxtcbcomponents: Vec<TcbComponentJson>,
+    pcesvn: u16,
+    #[serde(default)]
+    tdxtcbcomponents: Vec<TcbComponentJson>,
+}
+
+#[derive(Deserialize)]
+struct TcbComponentJson {
+    svn: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedQeIdentity<'a> {
+    #[serde(borrow)]
+    enclave_identity: &'a RawValue,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QeIdentityJson {
+    id: String,
+    version: u8,
+    issue_date: String,
+    next_update: String,
+    tcb_evaluation_data_number: u32,
+    miscselect: String,
+    miscselect_mask: String,
+    attributes: String,
+    attributes_mask: String,
+    mrsigner: String,
+    isvprodid: u16,
+    tcb_levels: Vec<IsvSvnTcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IsvSvnTcbLevelJson {
+    tcb: IsvSvnJson,
+    tcb_date: String,
+    tcb_status: String,
+}
+
+#[derive(Deserialize)]
+struct IsvSvnJson {
+    isvsvn: u16,
+}
+
+impl TryFrom<TdxModuleJson> for TdxModule {
+    type Error = CollateralError;
+
+    fn try_from(tdx_module: TdxModuleJson) -> Result<Self, Self::Error> {
+        Ok(TdxModule {
+            mrsigner: hex(&tdx_module.mrsigner, "mrsigner")?,
+            attributes: hex(&tdx_module.attributes, "attributes")?,
+            attributes_mask: hex(&tdx_module.attributes_mask, "attributesMask")?,
+        })
+    }
+}
+
+impl TryFrom<TdxModuleIdentityJson> for TdxModuleIdentity {
+    type Error = CollateralError;
+
+    fn try_from(identity: TdxModuleIdentityJson) -> Result<Self, Self::Error> {
+        // The ID is the major version in hex, such as TDX_01
+        let major_version = identity
+            .id
+            .strip_prefix("TDX_")
+            .and_then(|version| u8::from_str_radix(version, 16).ok())
+            .ok_or(CollateralError::InvalidJsonField("id"))?;
+        Ok(TdxModuleIdentity {
+            major_version,
+            module: identity.module.try_into()?,
+            tcb_levels: identity
+                .tcb_levels
+                .into_iter()
+                .map(|tcb_level| tcb_level.try_into())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<IsvSvnTcbLevelJson> for TdxModuleTcbLevel {
+    type Error = CollateralError;
+
+    fn try_from(tcb_level: IsvSvnTcbLevelJson) -> Result<Self, Self::Error> {
+        Ok(TdxModuleTcbLevel {
+            isv_svn: u8::try_from(tcb_level.tcb.isvsvn)
+                .map_err(|_| CollateralError::InvalidJsonField("isvsvn"))?,
+            tcb_date: date(&tcb_level.tcb_date, "tcbDate")?,
+            status: status(&tcb_level.tcb_status)?,
+        })
+    }
+}
+
+impl TryFrom<IsvSvnTcbLevelJson> for QeTcbLevel {
+    type Error = CollateralError;
+
+    fn try_from(tcb_level: IsvSvnTcbLevelJson) -> Result<Self, Self::Error> {
+        Ok(QeTcbLevel {
+            isv_svn: tcb_level.tcb.isvsvn,
+            tcb_date: date(&tcb_level.tcb_date, "tcbDate")?,
+            status: status(&tcb_level.tcb_status)?,
+        })
+    }
+}
+
+impl TryFrom<TcbLevelJson> for TcbLevel {
+    type Error = CollateralError;
+
+    fn try_from(tcb_level: TcbLevelJson) -> Result<Self, Self::Error> {
+        Ok(TcbLevel {
+            sgx_tcb_components: components(&tcb_level.tcb.sgxtcbcomponents, "sgxtcbcomponents")?,
+            pce_svn: tcb_level.tcb.pcesvn,
+            tdx_tcb_components: if tcb_level.tcb.tdxtcbcomponents.is_empty() {
+                [0; 16]
+            } else {
+                components(&tcb_level.tcb.tdxtcbcomponents, "tdxtcbcomponents")?
+            },
+            tcb_date: date(&tcb_level.tcb_date, "tcbDate")?,
+            status: status(&tcb_level.tcb_status)?,
+            advisory_ids: tcb_level.advisory_ids,
+        })
+    }
+}
+
+fn from_str<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, CollateralError> {
+    serde_json::from_str(json).map_err(|_| CollateralError::InvalidJson)
+}
+
+/// The signature, and the hash of the exact bytes which were signed
+fn signature_reference(
+    signed_data: &RawValue,
+    signature: &str,
+) -> Result<SignatureReference, CollateralError> {
+    Ok(SignatureReference {
+        signature: hex(signature, "signature")?,
+        signed_data_hash: Sha256::digest(signed_data.get().as_bytes()).into(),
+    })
+}
+
+fn components(
+    components: &[TcbComponentJson],
+    field: &'static str,
+) -> Result<[u8; 16], CollateralError> {
+    let mut svns = [0; 16];
+    if components.len() != svns.len() {
+        return Err(CollateralError::InvalidJsonField(field));
+    }
+    for (svn, component) in svns.iter_mut().zip(components) {
+        *svn = component.svn;
+    }
+    Ok(svns)
+}
+
+fn status(status: &str) -> Result<TcbStatus, CollateralError> {
+    [
+        TcbStatus::UpToDate,
+        TcbStatus::SWHardeningNeeded,
+        TcbStatus::ConfigurationNeeded,
+        TcbStatus::ConfigurationAndSWHardeningNeeded,
+        TcbStatus::OutOfDate,
+        TcbStatus::OutOfDateConfigurationNeeded,
+        TcbStatus::Revoked,
+    ]
+    .into_iter()
+    .find(|candidate| candidate.as_str() == status)
+    .ok_or(CollateralError::InvalidJsonField("tcbStatus"))
+}
+
+/// Decode a hex string of exactly N bytes
+fn hex<const N: usize>(input: &str, field: &'static str) -> Result<[u8; N], CollateralError> {
+    let error = || CollateralError::InvalidJsonField(field);
+    let input = input.as_bytes();
+    if input.len() != 2 * N {
+        return Err(error());
+    }
+    let mut output = [0; N];
+    for (byte, pair) in output.iter_mut().zip(input.chunks(2)) {
+        let pair = core::str::from_utf8(pair).map_err(|_| error())?;
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| error())?;
+    }
+    Ok(output)
+}
+
+/// Convert a date such as `2024-03-13T00:00:00Z` to seconds since the Unix epoch
+fn date(input: &str, field: &'static str) -> Result<u64, CollateralError> {
+    let error = || CollateralError::InvalidJsonField(field);
+    let number = |range: core::ops::Range<usize>| -> Result<u64, CollateralError> {
+        input
+            .get(range)
+            .filter(|digits| digits.bytes().all(|digit| digit.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(error)
+    };
+    let separators = [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')];
+    if !separators
+        .iter()
+        .all(|(index, separator)| input.as_bytes().get(*index) == Some(separator))
+        || !input.ends_with('Z')
+    {
+        return Err(error());
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hours, minutes, seconds) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1970..10000).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(error());
+    }
+
+    // Days since the epoch, counting years from March so that the leap day is last
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let days =
+        365 * year + year / 4 - year / 100 + year / 400 + (153 * month + 2) / 5 + day - 1 - 719_468;
+    Ok(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
@@ -0,0 +1,200 @@
+//! A compact form of a quote, with the PCK certificate chain replaced by an identifier for the PCK
+use crate::{
+    body_parser, check_v5_body_type, encode_verifying_key,
+    error::{QuoteField, QuoteSerializeError, QuoteVerificationError, VerifyingKeyError},
+    quote_header_parser,
+    serialize::{check_body, signed_data_serializer},
+    take_n::{take384, take64},
+    AttestationKey, CertificationData, Quote, QuoteHeader, QuoteParseError, QuoteSignature,
+    ReportBody,
+};
+use alloc::vec::Vec;
+use nom::{
+    bytes::complete::take,
+    number::complete::{le_u16, u8},
+};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Identifies the provisioning certification key (PCK) which signed a QE report
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PckId {
+    /// The PCK itself, in the compressed form given by [encode_verifying_key]
+    Key(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))] [u8; 33]),
+    /// SHA-256 of the certification data from which the PCK is found, typically a PEM encoded
+    /// certificate chain
+    CertificationDataHash(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))] [u8; 32],
+    ),
+}
+
+impl PckId {
+    /// Identify a PCK by the key itself
+    pub fn from_pck(pck: &VerifyingKey) -> Result<Self, VerifyingKeyError> {
+        Ok(PckId::Key(encode_verifying_key(pck)?))
+    }
+
+    /// Identify a PCK by the hash of the certification data from which it is found
+    pub fn from_certification_data(certification_data: &[u8]) -> Self {
+        PckId::CertificationDataHash(Sha256::digest(certification_data).into())
+    }
+}
+
+/// A quote with the certification data of the QE report replaced by a [PckId]
+///
+/// Real quotes are around 5 KB, most of which is the PCK certificate chain. The compact form keeps
+/// everything needed to verify the quote given the PCK.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactQuote {
+    pub header: QuoteHeader,
+    pub body: ReportBody,
+    pub signature: QuoteSignature,
+    pub attestation_key: AttestationKey,
+    /// The QE report, which binds the attestation key
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub qe_report: [u8; 384],
+    /// Signature of the QE report made using the PCK
+    pub qe_report_signature: Signature,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))]
+    pub qe_authentication_data: Vec<u8>,
+    pub pck_id: PckId,
+}
+
+impl CompactQuote {
+    /// Make a compact quote from a quote with QE report certification data, identifying the PCK
+    /// with the given ID
+    pub fn new(quote: Quote, pck_id: PckId) -> Result<Self, QuoteVerificationError> {
+        let CertificationData::QeReportCertificationData(qe_report_certification_data) =
+            quote.certification_data
+        else {
+            return Err(QuoteVerificationError::NoQeReportCertificationData);
+        };
+        Ok(Self {
+            header: quote.header,
+            body: quote.body,
+            signature: quote.signature,
+            attestation_key: quote.attestation_key,
+            qe_report: qe_report_certification_data.qe_report,
+            qe_report_signature: *qe_report_certification_data
+                .signature
+                .as_p256()
+                .ok_or(QuoteVerificationError::BadSignature)?,
+            qe_authentication_data: qe_report_certification_data.qe_authentication_data,
+            pck_id,
+        })
+    }
+
+    /// Verify the quote signature, the binding of the attestation key to the QE report, and the
+    /// QE report signature. The PCK is found by calling the given function with the PCK ID, which
+    /// would typically look it up in a registry of known PCKs.
+    pub fn verify(
+        &self,
+        find_pck: impl FnOnce(&PckId) -> Option<VerifyingKey>,
+    ) -> Result<(), QuoteVerificationError> {
+        self.attestation_key.verify(
+            &signed_data_serializer(&self.header, &self.body),
+            &self.signature,
+        )?;
+        if !self
+            .attestation_key
+            .matches_qe_report_data(&self.qe_authentication_data, &self.qe_report[320..])
+        {
+            return Err(QuoteVerificationError::AttestationKeyDoesNotMatch);
+        }
+        let pck = find_pck(&self.pck_id).ok_or(QuoteVerificationError::UnknownPck)?;
+        pck.verify(&self.qe_report, &self.qe_report_signature)?;
+        Ok(())
+    }
+
+    /// Serialize the compact quote. This begins with the header and body as in a quote, followed
+    /// by the signature, attestation key, QE report, QE report signature, QE authentication data
+    /// prefixed with its length, and PCK ID prefixed with its type (0 for a key, 1 for a hash).
+    ///
+    /// This fails if the body does not match the header, or the QE authentication data is too long
+    /// for its length.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, QuoteSerializeError> {
+        check_body(&self.header, &self.body)?;
+        let qe_authentication_data_length = u16::try_from(self.qe_authentication_data.len())
+            .map_err(|_| QuoteSerializeError::TooLong("QE authentication data"))?;
+        let mut output = signed_data_serializer(&self.header, &self.body);
+        output.extend_from_slice(&self.signature.to_bytes());
+        output.extend_from_slice(&self.attestation_key.to_raw_bytes());
+        output.extend_from_slice(&self.qe_report);
+        output.extend_from_slice(&self.qe_report_signature.to_bytes());
+        output.extend_from_slice(&qe_authentication_data_length.to_le_bytes());
+        output.extend_from_slice(&self.qe_authentication_data);
+        match &self.pck_id {
+            PckId::Key(key) => {
+                output.push(0);
+                output.extend_from_slice(key);
+            }
+            PckId::CertificationDataHash(hash) => {
+                output.push(1);
+                output.extend_from_slice(hash);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Parse a compact quote serialized with [CompactQuote::to_bytes], without verifying it
+    pub fn from_bytes(original_input: &[u8]) -> Result<Self, QuoteParseError> {
+        let locate = |field| QuoteParseError::locate(original_input, field);
+
+        let (input, header) =
+            quote_header_parser(original_input).map_err(locate(QuoteField::Header))?;
+        if !header.attestation_key_type.is_supported() {
+            return Err(QuoteParseError::UnsupportedAttestationKeyType);
+        };
+        if !(3..=5).contains(&header.version) {
+            return Err(QuoteParseError::UnknownQuoteVersion(header.version));
+        }
+        check_v5_body_type(&header, input)?;
+        let (input, body) = body_parser(input, &header).map_err(locate(QuoteField::Body))?;
+        let body = (&body).into();
+
+        let key_length = header.attestation_key_type.key_length();
+        let (input, signature) =
+            take(key_length)(input).map_err(locate(QuoteField::SignatureSection))?;
+        let signature = QuoteSignature::from_raw_bytes(&header.attestation_key_type, signature)?;
+        let (input, attestation_key) =
+            take(key_length)(input).map_err(locate(QuoteField::SignatureSection))?;
+        let attestation_key =
+            AttestationKey::from_raw_bytes(&header.attestation_key_type, attestation_key)?;
+
+        let (input, qe_report) = take384(input).map_err(locate(QuoteField::QeReport))?;
+        let (input, qe_report_signature) = take64(input).map_err(locate(QuoteField::QeReport))?;
+        let qe_report_signature = Signature::from_slice(qe_report_signature)
+            .map_err(|_| QuoteParseError::MalformedSignature)?;
+        let (input, qe_authentication_data_size) =
+            le_u16(input).map_err(locate(QuoteField::QeReport))?;
+        let (input, qe_authentication_data) =
+            take(qe_authentication_data_size)(input).map_err(locate(QuoteField::QeReport))?;
+
+        let pck_id_offset = original_input.len() - input.len();
+        let pck_id_error = QuoteParseError::Parse {
+            field: QuoteField::PckId,
+            offset: pck_id_offset,
+        };
+        let pck_id = match u8::<_, nom::error::Error<&[u8]>>(input) {
+            Ok((&[], _)) | Err(_) => return Err(pck_id_error),
+            Ok((key, 0)) => PckId::Key(key.try_into().map_err(|_| pck_id_error)?),
+            Ok((hash, 1)) => {
+                PckId::CertificationDataHash(hash.try_into().map_err(|_| pck_id_error)?)
+            }
+            Ok(_) => return Err(pck_id_error),
+        };
+
+        Ok(Self {
+            header,
+            body,
+            signature,
+            attestation_key,
+            qe_report: *qe_report,
+            qe_report_signature,
+            qe_authentication_data: qe_authentication_data.to_vec(),
+            pck_id,
+        })
+    }
+}
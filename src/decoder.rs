@@ -0,0 +1,156 @@
+//! Incremental decoding of quotes from input which arrives in chunks, such as from a stream with
+//! no framing
+use crate::{
+    check_body_type, error::QuoteField, quote_header_parser, ParseOptions, Quote, QuoteParseError,
+    TEEType, QUOTE_HEADER_LENGTH, V4_QUOTE_BODY_LENGTH,
+};
+use alloc::vec::Vec;
+use nom::number::complete::{le_i32, le_u16};
+
+/// Length of an SGX enclave report body
+const SGX_REPORT_BODY_LENGTH: usize = 384;
+
+/// Decodes a quote from chunks of input, reading the length fields to find out how many more
+/// bytes are needed
+///
+/// Only the bytes belonging to the quote are consumed, so input following the quote is left for
+/// the caller. Once a quote has been decoded, or after an error, the decoder can be used again.
+#[derive(Debug, Clone, Default)]
+pub struct QuoteDecoder {
+    buffer: Vec<u8>,
+    options: ParseOptions,
+}
+
+impl QuoteDecoder {
+    /// Create a decoder which parses quotes with the default options. These do not limit the
+    /// length of the certification data, so input from untrusted sources should be decoded with
+    /// [ParseOptions::strict].
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create a decoder which parses quotes with the given options
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            buffer: Vec::new(),
+            options,
+        }
+    }
+
+    /// The number of bytes which have been buffered so far
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// How many more bytes are needed, either to complete the quote or to read the next length
+    /// field. More may be needed once that length field has been read.
+    pub fn bytes_needed(&self) -> Result<usize, QuoteParseError> {
+        Ok(required_length(&self.buffer, &self.options)? - self.buffer.len())
+    }
+
+    /// Give the decoder some input, advancing the input past the bytes consumed. If this completes
+    /// a quote, it is parsed and verified, and returned.
+    pub fn push(&mut self, input: &mut &[u8]) -> Result<Option<Quote>, QuoteParseError> {
+        loop {
+            let needed = match self.bytes_needed() {
+                Ok(needed) => needed,
+                Err(error) => {
+                    self.buffer.clear();
+                    return Err(error);
+                }
+            };
+            if needed == 0 {
+                let quote = Quote::from_bytes_with_options(&self.buffer, &self.options);
+                self.buffer.clear();
+                return quote.map(Some);
+            }
+            if input.is_empty() {
+                return Ok(None);
+            }
+            let (chunk, rest) = input.split_at(needed.min(input.len()));
+            self.buffer.extend_from_slice(chunk);
+            *input = rest;
+        }
+    }
+}
+
+/// The length the input must have to make progress decoding a quote. If the input is at least
+/// this long, this is the length of the quote.
+fn required_length(input: &[u8], options: &ParseOptions) -> Result<usize, QuoteParseError> {
+    let locate = |field| QuoteParseError::locate(input, field);
+
+    // Header
+    if input.len() < QUOTE_HEADER_LENGTH {
+        return Ok(QUOTE_HEADER_LENGTH);
+    }
+    let (_, header) = quote_header_parser(input).map_err(locate(QuoteField::Header))?;
+    if !header.attestation_key_type.is_supported() {
+        return Err(QuoteParseError::UnsupportedAttestationKeyType);
+    };
+
+    // Body - for version 5 quotes, the length depends on the body type
+    let body_length = |tee_type| match tee_type {
+        TEEType::SGX => SGX_REPORT_BODY_LENGTH,
+        TEEType::TDX => V4_QUOTE_BODY_LENGTH,
+    };
+    let signed_data_length = match header.version {
+        3 | 4 => QUOTE_HEADER_LENGTH + body_length(header.tee_type),
+        5 => {
+            if input.len() < QUOTE_HEADER_LENGTH + 6 {
+                return Ok(QUOTE_HEADER_LENGTH + 6);
+            }
+            let (_, body_type) =
+                le_u16(&input[QUOTE_HEADER_LENGTH..]).map_err(locate(QuoteField::Body))?;
+            check_body_type(&header.tee_type, body_type)?;
+            QUOTE_HEADER_LENGTH
+                + 6
+                + match (header.tee_type, body_type) {
+                    (TEEType::SGX, 1) => SGX_REPORT_BODY_LENGTH,
+                    (TEEType::TDX, 2) => V4_QUOTE_BODY_LENGTH,
+                    (TEEType::TDX, 3) => V4_QUOTE_BODY_LENGTH + 64,
+                    _ => return Err(QuoteParseError::UnknownBodyType(body_type)),
+                }
+        }
+        version => return Err(QuoteParseError::UnknownQuoteVersion(version)),
+    };
+
+    // Signature section length
+    if input.len() < signed_data_length + 4 {
+        return Ok(signed_data_length + 4);
+    }
+    let (_, signature_section_length) =
+        le_i32(&input[signed_data_length..]).map_err(locate(QuoteField::SignatureSection))?;
+    let signature_section_length = usize::try_from(signature_section_length)?;
+    let key_length = header.attestation_key_type.key_length();
+
+    // Version 3 quotes have no certification data length, so the signature section length is used
+    if header.version == 3 {
+        let certification_data_length = signature_section_length
+            .checked_sub(2 * key_length)
+            .ok_or(QuoteParseError::Parse {
+                field: QuoteField::SignatureSection,
+                offset: signed_data_length,
+            })?;
+        if certification_data_length > options.max_certification_data_len {
+            return Err(QuoteParseError::CertificationDataTooLong(
+                certification_data_length,
+            ));
+        }
+        return Ok(signed_data_length + 4 + signature_section_length);
+    }
+
+    // Signature, attestation key, and certification data type and length
+    let certification_data_start = signed_data_length + 4 + 2 * key_length + 2 + 4;
+    if input.len() < certification_data_start {
+        return Ok(certification_data_start);
+    }
+    let (_, certification_data_length) = le_i32(&input[certification_data_start - 4..])
+        .map_err(locate(QuoteField::SignatureSection))?;
+    let certification_data_length = usize::try_from(certification_data_length)?;
+    if certification_data_length > options.max_certification_data_len {
+        return Err(QuoteParseError::CertificationDataTooLong(
+            certification_data_length,
+        ));
+    }
+    Ok(certification_data_start + certification_data_length)
+}
@@ -0,0 +1,292 @@
+//! Field-by-field comparison of quotes
+use crate::{
+    display::Hex, serialize::certification_data_serializer, CertificationData, Quote, QuoteBody,
+    QuoteHeader, ReportBody, SgxReportBody,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+/// The value of a quote field, as given in a [FieldDiff]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FieldValue {
+    /// An integer field
+    Number(u64),
+    /// A byte array field, such as a measurement register
+    Bytes(Vec<u8>),
+    /// An enumerated field, by name
+    Text(String),
+    /// A field which is not present, such as TDX 1.5 fields in a TDX 1.0 quote
+    None,
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Number(number) => write!(f, "{number}"),
+            FieldValue::Bytes(bytes) => write!(f, "{}", Hex(bytes)),
+            FieldValue::Text(text) => f.write_str(text),
+            FieldValue::None => f.write_str("none"),
+        }
+    }
+}
+
+/// A field which differs between two quotes
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldDiff {
+    /// Path to the field, for example `body.rtmr3` or `certification_data.qe_report.isv_svn`
+    pub field: String,
+    pub old: FieldValue,
+    pub new: FieldValue,
+}
+
+/// The fields which differ between two quotes, given by [Quote::diff]
+///
+/// This displays as one line per field, in the form `field: old -> new`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct QuoteDiff {
+    pub fields: Vec<FieldDiff>,
+}
+
+impl QuoteDiff {
+    /// Whether the quotes are the same
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Find the difference in a particular field
+    pub fn get(&self, field: &str) -> Option<&FieldDiff> {
+        self.fields.iter().find(|diff| diff.field == field)
+    }
+
+    fn push(&mut self, field: &str, old: FieldValue, new: FieldValue) {
+        if old != new {
+            self.fields.push(FieldDiff {
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+
+    fn bytes(&mut self, field: &str, old: &[u8], new: &[u8]) {
+        self.push(field, bytes(old), bytes(new));
+    }
+
+    fn number(&mut self, field: &str, old: impl Into<u64>, new: impl Into<u64>) {
+        self.push(
+            field,
+            FieldValue::Number(old.into()),
+            FieldValue::Number(new.into()),
+        );
+    }
+
+    fn text(&mut self, field: &str, old: impl fmt::Debug, new: impl fmt::Debug) {
+        self.push(
+            field,
+            FieldValue::Text(format!("{old:?}")),
+            FieldValue::Text(format!("{new:?}")),
+        );
+    }
+
+    fn header(&mut self, old: &QuoteHeader, new: &QuoteHeader) {
+        self.number("header.version", old.version, new.version);
+        self.text(
+            "header.attestation_key_type",
+            &old.attestation_key_type,
+            &new.attestation_key_type,
+        );
+        self.text("header.tee_type", &old.tee_type, &new.tee_type);
+        self.bytes("header.reserved1", &old.reserved1, &new.reserved1);
+        self.bytes("header.reserved2", &old.reserved2, &new.reserved2);
+        self.bytes("header.qe_vendor_id", &old.qe_vendor_id, &new.qe_vendor_id);
+        self.bytes("header.user_data", &old.user_data, &new.user_data);
+    }
+
+    fn td_report_body(&mut self, old: &QuoteBody, new: &QuoteBody) {
+        self.text("body.tdx_version", &old.tdx_version, &new.tdx_version);
+        self.bytes("body.tee_tcb_svn", &old.tee_tcb_svn, &new.tee_tcb_svn);
+        self.bytes("body.mrseam", &old.mrseam, &new.mrseam);
+        self.bytes("body.mrsignerseam", &old.mrsignerseam, &new.mrsignerseam);
+        self.bytes(
+            "body.seamattributes",
+            &old.seamattributes,
+            &new.seamattributes,
+        );
+        self.bytes("body.tdattributes", &old.tdattributes, &new.tdattributes);
+        self.bytes("body.xfam", &old.xfam, &new.xfam);
+        self.bytes("body.mrtd", &old.mrtd, &new.mrtd);
+        self.bytes("body.mrconfigid", &old.mrconfigid, &new.mrconfigid);
+        self.bytes("body.mrowner", &old.mrowner, &new.mrowner);
+        self.bytes("body.mrownerconfig", &old.mrownerconfig, &new.mrownerconfig);
+        self.bytes("body.rtmr0", &old.rtmr0, &new.rtmr0);
+        self.bytes("body.rtmr1", &old.rtmr1, &new.rtmr1);
+        self.bytes("body.rtmr2", &old.rtmr2, &new.rtmr2);
+        self.bytes("body.rtmr3", &old.rtmr3, &new.rtmr3);
+        self.bytes("body.reportdata", &old.reportdata, &new.reportdata);
+        self.push(
+            "body.tee_tcb_svn_2",
+            optional_bytes(old.tee_tcb_svn_2.as_ref()),
+            optional_bytes(new.tee_tcb_svn_2.as_ref()),
+        );
+        self.push(
+            "body.mrservicetd",
+            optional_bytes(old.mrservicetd.as_ref()),
+            optional_bytes(new.mrservicetd.as_ref()),
+        );
+    }
+
+    fn sgx_report_body(&mut self, prefix: &str, old: &SgxReportBody, new: &SgxReportBody) {
+        let field = |name| format!("{prefix}.{name}");
+        self.bytes(&field("cpu_svn"), &old.cpu_svn, &new.cpu_svn);
+        self.number(&field("misc_select"), old.misc_select, new.misc_select);
+        self.bytes(&field("reserved1"), &old.reserved1, &new.reserved1);
+        self.bytes(
+            &field("isv_ext_prod_id"),
+            &old.isv_ext_prod_id,
+            &new.isv_ext_prod_id,
+        );
+        self.bytes(&field("attributes"), &old.attributes, &new.attributes);
+        self.bytes(&field("mr_enclave"), &old.mr_enclave, &new.mr_enclave);
+        self.bytes(&field("reserved2"), &old.reserved2, &new.reserved2);
+        self.bytes(&field("mr_signer"), &old.mr_signer, &new.mr_signer);
+        self.bytes(&field("reserved3"), &old.reserved3, &new.reserved3);
+        self.bytes(&field("config_id"), &old.config_id, &new.config_id);
+        self.number(&field("isv_prod_id"), old.isv_prod_id, new.isv_prod_id);
+        self.number(&field("isv_svn"), old.isv_svn, new.isv_svn);
+        self.number(&field("config_svn"), old.config_svn, new.config_svn);
+        self.bytes(&field("reserved4"), &old.reserved4, &new.reserved4);
+        self.bytes(
+            &field("isv_family_id"),
+            &old.isv_family_id,
+            &new.isv_family_id,
+        );
+        self.bytes(&field("report_data"), &old.report_data, &new.report_data);
+    }
+
+    fn certification_data(&mut self, old: &CertificationData, new: &CertificationData) {
+        let (
+            CertificationData::QeReportCertificationData(old),
+            CertificationData::QeReportCertificationData(new),
+        ) = (old, new)
+        else {
+            self.number(
+                "certification_data.type",
+                old.certification_data_type() as u16,
+                new.certification_data_type() as u16,
+            );
+            self.push(
+                "certification_data.data",
+                serialized_certification_data(old),
+                serialized_certification_data(new),
+            );
+            return;
+        };
+
+        match (old.qe_report_body(), new.qe_report_body()) {
+            (Ok(old_report), Ok(new_report)) => {
+                self.sgx_report_body("certification_data.qe_report", &old_report, &new_report)
+            }
+            _ => self.bytes(
+                "certification_data.qe_report",
+                &old.qe_report,
+                &new.qe_report,
+            ),
+        }
+        self.bytes(
+            "certification_data.signature",
+            &old.signature.to_bytes(),
+            &new.signature.to_bytes(),
+        );
+        self.bytes(
+            "certification_data.qe_authentication_data",
+            &old.qe_authentication_data,
+            &new.qe_authentication_data,
+        );
+        // The nested certification data begins with its type and length
+        match (
+            old.certification_data.split_at_checked(6),
+            new.certification_data.split_at_checked(6),
+        ) {
+            (Some((old_header, old_data)), Some((new_header, new_data))) => {
+                self.number(
+                    "certification_data.certification_data.type",
+                    u16::from_le_bytes([old_header[0], old_header[1]]),
+                    u16::from_le_bytes([new_header[0], new_header[1]]),
+                );
+                self.bytes(
+                    "certification_data.certification_data.data",
+                    old_data,
+                    new_data,
+                );
+            }
+            _ => self.bytes(
+                "certification_data.certification_data",
+                &old.certification_data,
+                &new.certification_data,
+            ),
+        }
+    }
+}
+
+impl fmt::Display for QuoteDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for FieldDiff { field, old, new } in &self.fields {
+            writeln!(f, "{field}: {old} -> {new}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Quote {
+    /// List the fields which differ between this quote and another, with the values from each.
+    /// This covers the header, body, signature, attestation key and certification data, including
+    /// the fields of the QE report.
+    pub fn diff(&self, other: &Quote) -> QuoteDiff {
+        let mut diff = QuoteDiff::default();
+        diff.header(&self.header, &other.header);
+        match (&self.body, &other.body) {
+            (ReportBody::Td(old), ReportBody::Td(new)) => diff.td_report_body(old, new),
+            (ReportBody::Sgx(old), ReportBody::Sgx(new)) => diff.sgx_report_body("body", old, new),
+            (old, new) => diff.push("body", body_type(old), body_type(new)),
+        }
+        diff.bytes(
+            "signature",
+            &self.signature.to_bytes(),
+            &other.signature.to_bytes(),
+        );
+        diff.bytes(
+            "attestation_key",
+            &self.attestation_key.to_raw_bytes(),
+            &other.attestation_key.to_raw_bytes(),
+        );
+        diff.certification_data(&self.certification_data, &other.certification_data);
+        diff
+    }
+}
+
+fn bytes(bytes: &[u8]) -> FieldValue {
+    FieldValue::Bytes(bytes.to_vec())
+}
+
+/// Certification data as it is in the quote, or none if it cannot be serialized
+fn serialized_certification_data(certification_data: &CertificationData) -> FieldValue {
+    certification_data_serializer(certification_data).map_or(FieldValue::None, |data| bytes(&data))
+}
+
+fn optional_bytes<const N: usize>(input: Option<&[u8; N]>) -> FieldValue {
+    input.map_or(FieldValue::None, |input| bytes(input))
+}
+
+fn body_type(body: &ReportBody) -> FieldValue {
+    FieldValue::Text(
+        match body {
+            ReportBody::Td(_) => "TD report",
+            ReportBody::Sgx(_) => "SGX report",
+        }
+        .to_string(),
+    )
+}
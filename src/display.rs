@@ -0,0 +1,274 @@
+//! Human readable display of quotes, with one labelled field per line and byte arrays as hex
+#[cfg(feature = "alloc")]
+use crate::{serialize::certification_data_length, CertificationData, Quote};
+use crate::{
+    AttestionKeyType, QuoteBody, QuoteHeader, ReportBody, SgxReportBody, TDXVersion, TEEType,
+};
+use core::fmt::{self, Display};
+
+impl Display for AttestionKeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AttestionKeyType::ECDSA256WithP256 => "ECDSA-256-with-P-256",
+            AttestionKeyType::ECDSA384WithP384 => "ECDSA-384-with-P-384",
+        })
+    }
+}
+
+impl Display for TEEType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TEEType::SGX => "SGX",
+            TEEType::TDX => "TDX",
+        })
+    }
+}
+
+impl Display for TDXVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TDXVersion::One => "TDX 1.0",
+            TDXVersion::OnePointFive => "TDX 1.5",
+        })
+    }
+}
+
+impl Display for QuoteHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_header(f, self, "")
+    }
+}
+
+impl Display for QuoteBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_td_body(f, self, "")
+    }
+}
+
+impl Display for SgxReportBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_sgx_body(f, self, "")
+    }
+}
+
+impl Display for ReportBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportBody::Td(body) => body.fmt(f),
+            ReportBody::Sgx(body) => body.fmt(f),
+        }
+    }
+}
+
+/// The certification data is summarized by its type and length, rather than shown in full, as it
+/// is typically a PEM encoded certificate chain of several kilobytes.
+#[cfg(feature = "alloc")]
+impl Display for Quote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Header:")?;
+        write_header(f, &self.header, "  ")?;
+        match &self.body {
+            ReportBody::Td(body) => {
+                writeln!(f, "Body:")?;
+                write_td_body(f, body, "  ")?;
+            }
+            ReportBody::Sgx(body) => {
+                writeln!(f, "Body: SGX")?;
+                write_sgx_body(f, body, "  ")?;
+            }
+        }
+        write_hex(f, "", "Signature", &self.signature.to_bytes())?;
+        write_hex(
+            f,
+            "",
+            "Attestation key",
+            &self.attestation_key.to_raw_bytes(),
+        )?;
+        let certification_data_type = self.certification_data.certification_data_type();
+        let CertificationData::QeReportCertificationData(qe_report_certification_data) =
+            &self.certification_data
+        else {
+            let length = certification_data_length(&self.certification_data);
+            return write_certification_data_type(f, "", certification_data_type, Some(length));
+        };
+        write_certification_data_type(f, "", certification_data_type, None)?;
+        match qe_report_certification_data.qe_report_body() {
+            Ok(qe_report) => {
+                writeln!(f, "  QE report:")?;
+                write_sgx_body(f, &qe_report, "    ")?;
+            }
+            Err(_) => write_hex(
+                f,
+                "  ",
+                "QE report",
+                &qe_report_certification_data.qe_report,
+            )?,
+        }
+        write_hex(
+            f,
+            "  ",
+            "QE report signature",
+            &qe_report_certification_data.signature.to_bytes(),
+        )?;
+        write_hex(
+            f,
+            "  ",
+            "QE authentication data",
+            &qe_report_certification_data.qe_authentication_data,
+        )?;
+        // The nested certification data begins with its type and length
+        match qe_report_certification_data.certification_data.as_slice() {
+            [] => Ok(()),
+            [type_low, type_high, _, _, _, _, data @ ..] => write_certification_data_type(
+                f,
+                "  ",
+                i16::from_le_bytes([*type_low, *type_high]),
+                Some(data.len()),
+            ),
+            data => write_hex(f, "  ", "Certification data", data),
+        }
+    }
+}
+
+fn write_header(f: &mut fmt::Formatter<'_>, header: &QuoteHeader, indent: &str) -> fmt::Result {
+    writeln!(f, "{indent}Version: {}", header.version)?;
+    writeln!(
+        f,
+        "{indent}Attestation key type: {}",
+        header.attestation_key_type
+    )?;
+    writeln!(f, "{indent}TEE type: {}", header.tee_type)?;
+    if header.version == 3 {
+        // Version 3 quotes use the reserved fields for security version numbers
+        writeln!(
+            f,
+            "{indent}QE SVN: {}",
+            u16::from_le_bytes(header.reserved1)
+        )?;
+        writeln!(
+            f,
+            "{indent}PCE SVN: {}",
+            u16::from_le_bytes(header.reserved2)
+        )?;
+    } else {
+        write_hex(f, indent, "Reserved 1", &header.reserved1)?;
+        write_hex(f, indent, "Reserved 2", &header.reserved2)?;
+    }
+    writeln!(f, "{indent}QE vendor ID: {}", Uuid(&header.qe_vendor_id))?;
+    write_hex(f, indent, "User data", &header.user_data)
+}
+
+fn write_td_body(f: &mut fmt::Formatter<'_>, body: &QuoteBody, indent: &str) -> fmt::Result {
+    writeln!(f, "{indent}TDX version: {}", body.tdx_version)?;
+    for (label, bytes) in [
+        ("TEE TCB SVN", &body.tee_tcb_svn[..]),
+        ("MRSEAM", &body.mrseam),
+        ("MRSIGNERSEAM", &body.mrsignerseam),
+        ("SEAM attributes", &body.seamattributes),
+        ("TD attributes", &body.tdattributes),
+        ("XFAM", &body.xfam),
+        ("MRTD", &body.mrtd),
+        ("MRCONFIGID", &body.mrconfigid),
+        ("MROWNER", &body.mrowner),
+        ("MROWNERCONFIG", &body.mrownerconfig),
+        ("RTMR0", &body.rtmr0),
+        ("RTMR1", &body.rtmr1),
+        ("RTMR2", &body.rtmr2),
+        ("RTMR3", &body.rtmr3),
+        ("Report data", &body.reportdata),
+    ] {
+        write_hex(f, indent, label, bytes)?;
+    }
+    if let Some(tee_tcb_svn_2) = &body.tee_tcb_svn_2 {
+        write_hex(f, indent, "TEE TCB SVN 2", tee_tcb_svn_2)?;
+    }
+    if let Some(mrservicetd) = &body.mrservicetd {
+        write_hex(f, indent, "MRSERVICETD", mrservicetd)?;
+    }
+    Ok(())
+}
+
+fn write_sgx_body(f: &mut fmt::Formatter<'_>, body: &SgxReportBody, indent: &str) -> fmt::Result {
+    write_hex(f, indent, "CPU SVN", &body.cpu_svn)?;
+    writeln!(f, "{indent}MISCSELECT: {:#010x}", body.misc_select)?;
+    write_hex(f, indent, "ISV extended product ID", &body.isv_ext_prod_id)?;
+    write_hex(f, indent, "Attributes", &body.attributes)?;
+    write_hex(f, indent, "MRENCLAVE", &body.mr_enclave)?;
+    write_hex(f, indent, "MRSIGNER", &body.mr_signer)?;
+    write_hex(f, indent, "Config ID", &body.config_id)?;
+    writeln!(f, "{indent}ISV product ID: {}", body.isv_prod_id)?;
+    writeln!(f, "{indent}ISV SVN: {}", body.isv_svn)?;
+    writeln!(f, "{indent}Config SVN: {}", body.config_svn)?;
+    write_hex(f, indent, "ISV family ID", &body.isv_family_id)?;
+    write_hex(f, indent, "Report data", &body.report_data)
+}
+
+#[cfg(feature = "alloc")]
+fn write_certification_data_type(
+    f: &mut fmt::Formatter<'_>,
+    indent: &str,
+    certification_data_type: i16,
+    length: Option<usize>,
+) -> fmt::Result {
+    write!(
+        f,
+        "{indent}Certification data: {} ({certification_data_type})",
+        certification_data_type_name(certification_data_type)
+    )?;
+    if let Some(length) = length {
+        write!(f, ", {length} bytes")?;
+    }
+    writeln!(f)
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, indent: &str, label: &str, bytes: &[u8]) -> fmt::Result {
+    writeln!(f, "{indent}{label}: {}", Hex(bytes))
+}
+
+/// Displays bytes as lower case hex. The alternate form, `{:#}`, separates the bytes with spaces.
+pub(crate) struct Hex<'a>(pub(crate) &'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 && f.alternate() {
+                f.write_str(" ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays a 16 byte identifier, such as a QE vendor ID, as a hyphenated UUID
+pub(crate) struct Uuid<'a>(pub(crate) &'a [u8; 16]);
+
+impl Display for Uuid<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.0;
+        write!(
+            f,
+            "{}-{}-{}-{}-{}",
+            Hex(&id[0..4]),
+            Hex(&id[4..6]),
+            Hex(&id[6..8]),
+            Hex(&id[8..10]),
+            Hex(&id[10..16])
+        )
+    }
+}
+
+/// Name of a certification data type, as given in the quote
+#[cfg(feature = "alloc")]
+pub(crate) fn certification_data_type_name(certification_data_type: i16) -> &'static str {
+    match certification_data_type {
+        1 => "PCK ID, PPID in plaintext, CPUSVN and PCESVN",
+        2 => "PCK ID, PPID encrypted with RSA-2048-OAEP, CPUSVN and PCESVN",
+        3 => "PCK ID, PPID encrypted with RSA-3072-OAEP, CPUSVN and PCESVN",
+        4 => "PCK leaf certificate",
+        5 => "PCK certificate chain",
+        6 => "QE report certification data",
+        7 => "Platform manifest",
+        _ => "Unknown",
+    }
+}
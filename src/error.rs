@@ -4,24 +4,105 @@ use core::num::TryFromIntError;
 /// An error when parsing a quote
 #[derive(Debug, Eq, PartialEq)]
 pub enum QuoteParseError {
-    Parse,
+    /// The given part of the quote could not be parsed. The offset is from the start of the input.
+    Parse {
+        field: QuoteField,
+        offset: usize,
+    },
+    /// The signature made with the attestation key is invalid
     Verification,
-    UnknownCertificationDataType,
-    UnknownQuoteVersion,
+    /// The attestation key is not a valid point on the curve
+    MalformedAttestationKey,
+    /// A signature could not be decoded
+    MalformedSignature,
+    UnknownCertificationDataType(i16),
+    UnknownQuoteVersion(u16),
+    /// The TD report version (the body type of a version 5 quote) is not known
+    UnknownBodyType(u16),
+    /// The body type of a version 5 quote is known, but is not for the TEE type in the header
+    BodyTypeMismatch {
+        tee_type: crate::TEEType,
+        body_type: u16,
+    },
     IntConversionError,
     UnsupportedAttestationKeyType,
     AttestationKeyDoesNotMatch,
+    /// In strict mode, a length field does not match the length of the data it describes
+    InconsistentLength(QuoteField),
+    /// In strict mode, there are bytes following the quote, beginning at the given offset
+    TrailingBytes {
+        offset: usize,
+    },
+    /// In strict mode, a reserved field at the given offset is not zero
+    NonZeroReserved {
+        offset: usize,
+    },
+    /// The certification data is longer than the maximum allowed length
+    CertificationDataTooLong(usize),
+}
+
+impl QuoteParseError {
+    /// Returns a function which converts a nom error to a parse error for the given field,
+    /// located by the position of the error within the given input
+    pub(crate) fn locate<'a>(
+        input: &'a [u8],
+        field: QuoteField,
+    ) -> impl Fn(nom::Err<nom::error::Error<&'a [u8]>>) -> QuoteParseError + 'a {
+        move |error| {
+            let offset = match error {
+                nom::Err::Error(error) | nom::Err::Failure(error) => {
+                    (error.input.as_ptr() as usize).saturating_sub(input.as_ptr() as usize)
+                }
+                nom::Err::Incomplete(_) => input.len(),
+            };
+            QuoteParseError::Parse { field, offset }
+        }
+    }
+
+    /// Move the location of a parse error, for errors from parsing part of the input
+    pub(crate) fn offset_by(self, base: usize) -> QuoteParseError {
+        match self {
+            QuoteParseError::Parse { field, offset } => QuoteParseError::Parse {
+                field,
+                offset: base + offset,
+            },
+            error => error,
+        }
+    }
 }
 
 impl Display for QuoteParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            QuoteParseError::Parse => f.write_str("Cannot parse quote"),
+            QuoteParseError::Parse { field, offset } => {
+                write!(f, "Cannot parse quote {field} at byte offset {offset}")
+            }
             QuoteParseError::Verification => f.write_str("Signature is invalid"),
-            QuoteParseError::UnknownCertificationDataType => {
-                f.write_str("Unknown certification data type")
+            QuoteParseError::MalformedAttestationKey => {
+                f.write_str("Attestation key cannot be decoded")
+            }
+            QuoteParseError::MalformedSignature => f.write_str("Signature cannot be decoded"),
+            QuoteParseError::UnknownCertificationDataType(certification_data_type) => {
+                write!(
+                    f,
+                    "Unknown certification data type {certification_data_type}"
+                )
+            }
+            QuoteParseError::UnknownQuoteVersion(version) => {
+                write!(f, "Unknown quote version {version}")
+            }
+            QuoteParseError::UnknownBodyType(body_type) => {
+                write!(f, "Unknown quote body type {body_type}")
+            }
+            QuoteParseError::BodyTypeMismatch {
+                tee_type,
+                body_type,
+            } => {
+                write!(
+                    f,
+                    "Quote body type {body_type} is not for TEE type {tee_type}"
+                )
             }
-            QuoteParseError::UnknownQuoteVersion => f.write_str("Unknown quote version"),
             QuoteParseError::IntConversionError => f.write_str("Integer conversion error"),
             QuoteParseError::UnsupportedAttestationKeyType => {
                 f.write_str("Unsupported attestion key type")
@@ -29,13 +110,61 @@ impl Display for QuoteParseError {
             QuoteParseError::AttestationKeyDoesNotMatch => {
                 f.write_str("Attestation key does not match hash in QE report")
             }
+            QuoteParseError::InconsistentLength(field) => {
+                write!(f, "Length of quote {field} is inconsistent")
+            }
+            QuoteParseError::TrailingBytes { offset } => {
+                write!(f, "Trailing bytes after quote at byte offset {offset}")
+            }
+            QuoteParseError::NonZeroReserved { offset } => {
+                write!(f, "Reserved field at byte offset {offset} is not zero")
+            }
+            QuoteParseError::CertificationDataTooLong(length) => {
+                write!(f, "Certification data length {length} exceeds maximum")
+            }
         }
     }
 }
 
-impl From<nom::Err<nom::error::Error<&[u8]>>> for QuoteParseError {
-    fn from(_: nom::Err<nom::error::Error<&[u8]>>) -> QuoteParseError {
-        QuoteParseError::Parse
+impl core::error::Error for QuoteParseError {}
+
+/// The part of a quote in which a parse error occurred
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
+pub enum QuoteField {
+    Header,
+    Body,
+    /// The signature section length, signature, attestation key, and the type and length of the
+    /// certification data
+    SignatureSection,
+    /// Certification data of the given type
+    CertificationData(i16),
+    /// The QE report, within QE report certification data
+    QeReport,
+    /// The PCK identifier, within a compact quote
+    PckId,
+}
+
+impl Display for QuoteField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteField::Header => f.write_str("header"),
+            QuoteField::Body => f.write_str("body"),
+            QuoteField::SignatureSection => f.write_str("signature section"),
+            QuoteField::CertificationData(certification_data_type) => {
+                write!(f, "certification data type {certification_data_type}")
+            }
+            QuoteField::QeReport => f.write_str("QE report"),
+            QuoteField::PckId => f.write_str("PCK ID"),
+        }
     }
 }
 
@@ -51,21 +180,244 @@ impl From<TryFromIntError> for QuoteParseError {
     }
 }
 
-/// An error when verifying a quote with a provisioning certification key (PCK)
-#[derive(Debug, Eq, PartialEq)]
+/// An error when verifying a quote, or verifying it with a provisioning certification key (PCK)
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub enum QuoteVerificationError {
     NoQeReportCertificationData,
     BadSignature,
+    ReportDataMismatch,
+    BadAttestationKey,
+    BadAttestationKeySignature,
+    AttestationKeyDoesNotMatch,
+    /// No PCK could be found for the quote's certification data
+    UnknownPck,
+}
+
+impl From<QuoteVerificationError> for QuoteParseError {
+    fn from(error: QuoteVerificationError) -> QuoteParseError {
+        match error {
+            QuoteVerificationError::AttestationKeyDoesNotMatch => {
+                QuoteParseError::AttestationKeyDoesNotMatch
+            }
+            QuoteVerificationError::BadAttestationKey => QuoteParseError::MalformedAttestationKey,
+            _ => QuoteParseError::Verification,
+        }
+    }
 }
 
+impl Display for QuoteVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteVerificationError::NoQeReportCertificationData => {
+                f.write_str("Quote has no QE report certification data")
+            }
+            QuoteVerificationError::BadSignature => {
+                f.write_str("QE report signature from the PCK is invalid")
+            }
+            QuoteVerificationError::ReportDataMismatch => {
+                f.write_str("Report data does not match expected value")
+            }
+            QuoteVerificationError::BadAttestationKey => {
+                f.write_str("Attestation key cannot be decoded")
+            }
+            QuoteVerificationError::BadAttestationKeySignature => {
+                f.write_str("Signature from the attestation key is invalid")
+            }
+            QuoteVerificationError::AttestationKeyDoesNotMatch => {
+                f.write_str("Attestation key does not match hash in QE report")
+            }
+            QuoteVerificationError::UnknownPck => {
+                f.write_str("No PCK found for certification data")
+            }
+        }
+    }
+}
+
+impl core::error::Error for QuoteVerificationError {}
+
 impl From<p256::ecdsa::Error> for QuoteVerificationError {
     fn from(_: p256::ecdsa::Error) -> QuoteVerificationError {
         QuoteVerificationError::BadSignature
     }
 }
 
+/// An error when decoding collateral or evaluating the TCB status of a quote
+#[derive(Debug, Eq, PartialEq)]
+pub enum CollateralError {
+    /// The compact encoding could not be decoded
+    Malformed,
+    /// A list or string is too long for the length prefix of the compact encoding
+    TooLong(&'static str),
+    UnsupportedFormatVersion(u8),
+    /// The collateral JSON could not be parsed
+    InvalidJson,
+    /// A field of the collateral JSON has an invalid value
+    InvalidJsonField(&'static str),
+    /// TCB status can only be evaluated for quotes with a TD report body
+    NotTdQuote,
+    NoQeReportCertificationData,
+    /// The QE report could not be parsed
+    QuoteParse(QuoteParseError),
+    /// The TCB Info is for SGX rather than TDX
+    NotTdxTcbInfo,
+    /// The QE Identity is not for the TD quoting enclave
+    NotTdQeIdentity,
+    /// The platform is not from the family the TCB Info is for
+    FmspcMismatch,
+    PceIdMismatch,
+    /// The TDX module signer or attributes differ from those in the TCB Info
+    TdxModuleMismatch,
+    /// The TCB Info has no identity for the given TDX module major version
+    NoTdxModuleIdentity(u8),
+    /// The QE report signer, product ID, MISCSELECT or attributes differ from those in the QE
+    /// Identity
+    QeIdentityMismatch,
+    /// The SVNs are lower than those of every TCB level
+    NoMatchingTcbLevel,
+}
+
+impl Display for CollateralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollateralError::Malformed => f.write_str("Cannot decode compact collateral"),
+            CollateralError::TooLong(field) => {
+                write!(f, "Length of {field} is too large for compact collateral")
+            }
+            CollateralError::UnsupportedFormatVersion(version) => {
+                write!(f, "Unsupported compact collateral format version {version}")
+            }
+            CollateralError::InvalidJson => f.write_str("Cannot parse collateral JSON"),
+            CollateralError::InvalidJsonField(field) => {
+                write!(f, "Invalid value for {field} in collateral JSON")
+            }
+            CollateralError::NotTdQuote => f.write_str("Quote does not have a TD report body"),
+            CollateralError::NoQeReportCertificationData => {
+                f.write_str("Quote has no QE report certification data")
+            }
+            CollateralError::QuoteParse(error) => write!(f, "{error}"),
+            CollateralError::NotTdxTcbInfo => f.write_str("TCB Info is not for TDX"),
+            CollateralError::NotTdQeIdentity => {
+                f.write_str("QE Identity is not for the TD quoting enclave")
+            }
+            CollateralError::FmspcMismatch => f.write_str("TCB Info is for a different FMSPC"),
+            CollateralError::PceIdMismatch => f.write_str("TCB Info is for a different PCE ID"),
+            CollateralError::TdxModuleMismatch => f.write_str("TDX module does not match TCB Info"),
+            CollateralError::NoTdxModuleIdentity(version) => {
+                write!(
+                    f,
+                    "TCB Info has no identity for TDX module major version {version}"
+                )
+            }
+            CollateralError::QeIdentityMismatch => {
+                f.write_str("Quoting enclave does not match QE Identity")
+            }
+            CollateralError::NoMatchingTcbLevel => f.write_str("No TCB level matches"),
+        }
+    }
+}
+
+impl core::error::Error for CollateralError {}
+
+impl From<QuoteParseError> for CollateralError {
+    fn from(error: QuoteParseError) -> CollateralError {
+        CollateralError::QuoteParse(error)
+    }
+}
+
+impl From<nom::Err<nom::error::Error<&[u8]>>> for CollateralError {
+    fn from(_: nom::Err<nom::error::Error<&[u8]>>) -> CollateralError {
+        CollateralError::Malformed
+    }
+}
+
+/// An error when serializing a quote
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum QuoteSerializeError {
+    /// A variable length field is too long for its length to be given in the quote
+    TooLong(&'static str),
+    /// The body cannot be given in a quote with this header, for example a TDX 1.5 body in a
+    /// version 4 quote, or a TD report in an SGX quote
+    BodyDoesNotMatchHeader,
+    /// The attestation key is not of the type given in the header
+    AttestationKeyDoesNotMatchHeader,
+    /// Version 3 quotes can only contain QE report certification data
+    CertificationDataDoesNotMatchVersion,
+}
+
+impl Display for QuoteSerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteSerializeError::TooLong(field) => write!(f, "{field} is too long to serialize"),
+            QuoteSerializeError::BodyDoesNotMatchHeader => {
+                f.write_str("Quote body does not match the version and TEE type in the header")
+            }
+            QuoteSerializeError::AttestationKeyDoesNotMatchHeader => {
+                f.write_str("Attestation key does not match the key type in the header")
+            }
+            QuoteSerializeError::CertificationDataDoesNotMatchVersion => {
+                f.write_str("Version 3 quotes can only contain QE report certification data")
+            }
+        }
+    }
+}
+
+impl core::error::Error for QuoteSerializeError {}
+
+/// An error when converting a quote to or from go-tdx-guest's protobuf representation
+#[derive(Debug, Eq, PartialEq)]
+pub enum ProtobufError {
+    /// Only version 4 TDX 1.0 quotes with QE report certification data have a protobuf
+    /// representation
+    Unsupported,
+    /// The protobuf encoding could not be decoded
+    Decode,
+    /// A message field is not present
+    MissingField(&'static str),
+    /// A message field has an invalid length or value
+    InvalidField(&'static str),
+    /// The message has bytes following the quote, which are only kept by
+    /// [Quote::from_protobuf_with_extra_bytes](crate::Quote::from_protobuf_with_extra_bytes)
+    ExtraBytes,
+}
+
+impl Display for ProtobufError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtobufError::Unsupported => f.write_str("Quote has no protobuf representation"),
+            ProtobufError::Decode => f.write_str("Cannot decode protobuf message"),
+            ProtobufError::MissingField(field) => write!(f, "Missing {field} in protobuf message"),
+            ProtobufError::InvalidField(field) => {
+                write!(f, "Invalid value for {field} in protobuf message")
+            }
+            ProtobufError::ExtraBytes => {
+                f.write_str("Protobuf message has bytes following the quote")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ProtobufError {}
+
 /// An error when handling a verifying key
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub enum VerifyingKeyError {
     DecodeEncodedPoint,
     EncodedPointToVerifyingKey,
@@ -85,3 +437,93 @@ impl Display for VerifyingKeyError {
         }
     }
 }
+
+impl core::error::Error for VerifyingKeyError {}
+
+/// An error when reading the TDX metadata of a TDVF firmware image
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
+pub enum TdvfError {
+    NoOvmfTable,
+    NoMetadataOffset,
+    BadMetadataGuid,
+    BadSignature,
+    BadLength,
+    UnsupportedVersion(u32),
+    UnknownSectionType(u32),
+    MisalignedSection,
+    OutOfBounds,
+    IntConversionError,
+}
+
+impl Display for TdvfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TdvfError::NoOvmfTable => f.write_str("Firmware image has no OVMF GUIDed table"),
+            TdvfError::NoMetadataOffset => {
+                f.write_str("OVMF GUIDed table has no TDX metadata offset entry")
+            }
+            TdvfError::BadMetadataGuid => f.write_str("TDX metadata GUID not found"),
+            TdvfError::BadSignature => f.write_str("TDVF descriptor has bad signature"),
+            TdvfError::BadLength => {
+                f.write_str("TDVF descriptor length does not match number of sections")
+            }
+            TdvfError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported TDVF metadata version {version}")
+            }
+            TdvfError::UnknownSectionType(section_type) => {
+                write!(f, "Unknown TDVF section type {section_type}")
+            }
+            TdvfError::MisalignedSection => f.write_str("TDVF section is not page aligned"),
+            TdvfError::OutOfBounds => f.write_str("TDVF metadata points outside firmware image"),
+            TdvfError::IntConversionError => f.write_str("Integer conversion error"),
+        }
+    }
+}
+
+impl core::error::Error for TdvfError {}
+
+impl From<TryFromIntError> for TdvfError {
+    fn from(_: TryFromIntError) -> TdvfError {
+        TdvfError::IntConversionError
+    }
+}
+
+/// An error when parsing a PE/COFF image
+#[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
+pub enum PeError {
+    NotPe,
+    UnknownOptionalHeaderMagic,
+    MissingSection,
+    OutOfBounds,
+}
+
+impl Display for PeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeError::NotPe => f.write_str("Not a PE/COFF image"),
+            PeError::UnknownOptionalHeaderMagic => f.write_str("Unknown optional header magic"),
+            PeError::MissingSection => f.write_str("Image is missing a required section"),
+            PeError::OutOfBounds => f.write_str("PE/COFF header points outside image"),
+        }
+    }
+}
+
+impl core::error::Error for PeError {}
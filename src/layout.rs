@@ -0,0 +1,344 @@
+//! Byte ranges of the fields of a quote, and an annotated hexdump using them
+use crate::{
+    check_body_type, display::Hex, error::QuoteField, AttestionKeyType, QuoteParseError, TEEType,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Write, ops::Range};
+
+/// A field of a quote and the bytes it occupies
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldLayout {
+    /// Path to the field, for example `header.version` or `body.rtmr0`. The names match those
+    /// given by [Quote::diff](crate::Quote::diff), with the addition of length and type fields.
+    pub field: String,
+    pub range: Range<usize>,
+}
+
+/// The byte ranges of every field of a quote
+///
+/// This is found by walking the quote structure without validating it, so it can be used to
+/// debug malformed quotes. Fields containing other fields, such as `body`, come before the fields
+/// they contain.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct QuoteLayout {
+    pub fields: Vec<FieldLayout>,
+    /// The error which stopped the walk, for a quote which cannot be parsed
+    pub error: Option<QuoteParseError>,
+}
+
+impl QuoteLayout {
+    /// Find the byte range of every field of a quote
+    pub fn from_bytes(input: &[u8]) -> Self {
+        let mut builder = LayoutBuilder {
+            input,
+            offset: 0,
+            end: input.len(),
+            open: Vec::new(),
+            section: QuoteField::Header,
+            layout: QuoteLayout::default(),
+        };
+        if let Err(error) = builder.quote() {
+            builder.layout.error = Some(error);
+        }
+        while !builder.open.is_empty() {
+            builder.close();
+        }
+        builder.layout
+    }
+
+    /// The byte range of the given field
+    pub fn get(&self, field: &str) -> Option<Range<usize>> {
+        self.fields
+            .iter()
+            .find(|layout| layout.field == field)
+            .map(|layout| layout.range.clone())
+    }
+
+    /// Render the quote as a hexdump, with each field labelled and indented by its nesting. Fields
+    /// of up to 16 bytes are shown on one line, longer fields in rows of 16 bytes with their
+    /// printable characters. If the input is shorter than the layout, the dump stops where it ends.
+    pub fn hexdump(&self, input: &[u8]) -> String {
+        let mut output = String::new();
+        for (index, FieldLayout { field, range }) in self.fields.iter().enumerate() {
+            let depth = field.matches('.').count();
+            let indent = "  ".repeat(depth);
+            let label = field.rsplit('.').next().unwrap_or(field);
+            let Some(bytes) = input.get(range.clone()) else {
+                let _ = writeln!(output, "{:06x}  truncated: input ends", input.len());
+                break;
+            };
+            let is_container = self
+                .fields
+                .get(index + 1)
+                .is_some_and(|next| next.field.starts_with(&format!("{field}.")));
+            if bytes.len() <= 16 && !is_container {
+                let _ = writeln!(
+                    output,
+                    "{:06x}  {indent}{label}: {:#}",
+                    range.start,
+                    Hex(bytes)
+                );
+                continue;
+            }
+            let _ = writeln!(
+                output,
+                "{:06x}  {indent}{label} ({} bytes)",
+                range.start,
+                bytes.len()
+            );
+            if is_container {
+                continue;
+            }
+            for (row, chunk) in bytes.chunks(16).enumerate() {
+                let printable: String = chunk
+                    .iter()
+                    .map(|byte| match byte {
+                        0x20..=0x7e => char::from(*byte),
+                        _ => '.',
+                    })
+                    .collect();
+                let _ = writeln!(
+                    output,
+                    "{:06x}  {indent}  {:<47}  |{printable}|",
+                    range.start + 16 * row,
+                    format!("{:#}", Hex(chunk))
+                );
+            }
+        }
+        if let Some(error) = &self.error {
+            let _ = writeln!(output, "error: {error}");
+        }
+        output
+    }
+}
+
+/// Walks a quote, recording the range of each field
+struct LayoutBuilder<'a> {
+    input: &'a [u8],
+    offset: usize,
+    /// The end of the innermost field with a given length
+    end: usize,
+    /// Indices of the fields containing the current position, with the end to restore on leaving
+    open: Vec<(usize, usize)>,
+    /// The part of the quote being walked, for errors
+    section: QuoteField,
+    layout: QuoteLayout,
+}
+
+impl<'a> LayoutBuilder<'a> {
+    fn take(&mut self, field: &str, length: usize) -> Result<&'a [u8], QuoteParseError> {
+        let start = self.offset;
+        let bytes = start
+            .checked_add(length)
+            .filter(|end| *end <= self.end)
+            .and_then(|end| self.input.get(start..end))
+            .ok_or(QuoteParseError::Parse {
+                field: self.section,
+                offset: start,
+            })?;
+        self.offset += length;
+        self.layout.fields.push(FieldLayout {
+            field: field.to_string(),
+            range: start..self.offset,
+        });
+        Ok(bytes)
+    }
+
+    fn u16(&mut self, field: &str) -> Result<u16, QuoteParseError> {
+        let bytes = self.take(field, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self, field: &str) -> Result<u32, QuoteParseError> {
+        let bytes = self.take(field, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Begin a field containing other fields, optionally with a known length
+    fn open(&mut self, field: &str, length: Option<usize>) {
+        self.open.push((self.layout.fields.len(), self.end));
+        self.layout.fields.push(FieldLayout {
+            field: field.to_string(),
+            range: self.offset..self.offset,
+        });
+        if let Some(length) = length {
+            self.end = self.end.min(self.offset.saturating_add(length));
+        }
+    }
+
+    /// End the innermost field containing other fields
+    fn close(&mut self) {
+        if let Some((index, end)) = self.open.pop() {
+            self.layout.fields[index].range.end = self.offset;
+            self.end = end;
+        }
+    }
+
+    fn quote(&mut self) -> Result<(), QuoteParseError> {
+        self.open("header", None);
+        let version = self.u16("header.version")?;
+        let attestation_key_type = self.u16("header.attestation_key_type")?;
+        let tee_type = self.u32("header.tee_type")?;
+        self.take("header.reserved1", 2)?;
+        self.take("header.reserved2", 2)?;
+        self.take("header.qe_vendor_id", 16)?;
+        self.take("header.user_data", 20)?;
+        self.close();
+
+        self.section = QuoteField::Body;
+        self.open("body", None);
+        let body_type = match (version, tee_type) {
+            (3, 0) | (4, 0) => 1,
+            (4, 0x81) => 2,
+            (5, _) => {
+                let body_type = self.u16("body.type")?;
+                self.u32("body.size")?;
+                body_type
+            }
+            // The TEE type is not known
+            (3 | 4, _) => {
+                return Err(QuoteParseError::Parse {
+                    field: QuoteField::Header,
+                    offset: 4,
+                })
+            }
+            _ => return Err(QuoteParseError::UnknownQuoteVersion(version)),
+        };
+        // The body type must agree with the TEE type given in the header
+        let tee_type = TEEType::try_from(tee_type).map_err(|_| QuoteParseError::Parse {
+            field: QuoteField::Header,
+            offset: 4,
+        })?;
+        check_body_type(&tee_type, body_type)?;
+        if body_type == 1 {
+            self.sgx_report_body("body")?;
+        } else {
+            self.td_report_body(body_type == 3)?;
+        }
+        self.close();
+
+        self.section = QuoteField::SignatureSection;
+        let key_length = AttestionKeyType::try_from(attestation_key_type)
+            .map_err(|_| QuoteParseError::UnsupportedAttestationKeyType)?
+            .key_length();
+        let signature_section_length = self.u32("signature_section_length")? as usize;
+        self.take("signature", key_length)?;
+        self.take("attestation_key", key_length)?;
+
+        if version == 3 {
+            // Version 3 quotes always contain QE report certification data, which is not preceded
+            // by a type and length
+            self.section = QuoteField::CertificationData(6);
+            let length = signature_section_length.saturating_sub(2 * key_length);
+            self.open("certification_data", Some(length));
+            self.qe_report_certification_data()?;
+            self.close();
+        } else {
+            self.open("certification_data", None);
+            let certification_data_type = self.u16("certification_data.type")? as i16;
+            let length = self.u32("certification_data.size")? as usize;
+            self.section = QuoteField::CertificationData(certification_data_type);
+            if certification_data_type == 6 {
+                self.end = self.end.min(self.offset.saturating_add(length));
+                self.qe_report_certification_data()?;
+            } else {
+                self.take("certification_data.data", length)?;
+            }
+            self.close();
+        }
+
+        if self.offset < self.input.len() {
+            self.take("trailing_bytes", self.input.len() - self.offset)?;
+        }
+        Ok(())
+    }
+
+    fn td_report_body(&mut self, tdx_1_5: bool) -> Result<(), QuoteParseError> {
+        for (field, length) in [
+            ("body.tee_tcb_svn", 16),
+            ("body.mrseam", 48),
+            ("body.mrsignerseam", 48),
+            ("body.seamattributes", 8),
+            ("body.tdattributes", 8),
+            ("body.xfam", 8),
+            ("body.mrtd", 48),
+            ("body.mrconfigid", 48),
+            ("body.mrowner", 48),
+            ("body.mrownerconfig", 48),
+            ("body.rtmr0", 48),
+            ("body.rtmr1", 48),
+            ("body.rtmr2", 48),
+            ("body.rtmr3", 48),
+            ("body.reportdata", 64),
+        ] {
+            self.take(field, length)?;
+        }
+        if tdx_1_5 {
+            self.take("body.tee_tcb_svn_2", 16)?;
+            self.take("body.mrservicetd", 48)?;
+        }
+        Ok(())
+    }
+
+    fn sgx_report_body(&mut self, prefix: &str) -> Result<(), QuoteParseError> {
+        for (field, length) in [
+            ("cpu_svn", 16),
+            ("misc_select", 4),
+            ("reserved1", 12),
+            ("isv_ext_prod_id", 16),
+            ("attributes", 16),
+            ("mr_enclave", 32),
+            ("reserved2", 32),
+            ("mr_signer", 32),
+            ("reserved3", 32),
+            ("config_id", 64),
+            ("isv_prod_id", 2),
+            ("isv_svn", 2),
+            ("config_svn", 2),
+            ("reserved4", 42),
+            ("isv_family_id", 16),
+            ("report_data", 64),
+        ] {
+            self.take(&format!("{prefix}.{field}"), length)?;
+        }
+        Ok(())
+    }
+
+    fn qe_report_certification_data(&mut self) -> Result<(), QuoteParseError> {
+        let section = self.section;
+        self.section = QuoteField::QeReport;
+        self.open("certification_data.qe_report", Some(384));
+        self.sgx_report_body("certification_data.qe_report")?;
+        self.close();
+        self.section = section;
+        self.take("certification_data.signature", 64)?;
+        let qe_authentication_data_size =
+            self.u16("certification_data.qe_authentication_data_size")?;
+        self.take(
+            "certification_data.qe_authentication_data",
+            qe_authentication_data_size.into(),
+        )?;
+
+        // The nested certification data, typically the PCK certificate chain, which may be empty
+        if self.offset == self.end {
+            return Ok(());
+        }
+        self.open("certification_data.certification_data", None);
+        let certification_data_type = self.u16("certification_data.certification_data.type")?;
+        let length = self.u32("certification_data.certification_data.size")? as usize;
+        self.section = QuoteField::CertificationData(certification_data_type as i16);
+        self.take("certification_data.certification_data.data", length)?;
+        self.close();
+
+        // Any bytes following the nested certification data, up to the given certification data
+        // length
+        if self.offset < self.end {
+            self.take("certification_data.padding", self.end - self.offset)?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
 //! Parses and verifies Intel TDX quotes (v4 and v5)
 //!
-//! This crate is `no_std`.
+//! SGX quotes (v3, and v4 and v5 with an enclave report body) are also supported, so that SGX
+//! enclaves and TDs can be verified in the same way.
+//!
+//! This crate is `no_std`. The `alloc` feature flag, which is enabled by default, gives owned types
+//! such as `Quote`, serialization and the measurement calculators. Without it, quotes can still be
+//! parsed with [QuoteRef] and verified without an allocator.
 //!
 //! This is inspired by [tdx-quote-parser](https://github.com/MoeMahhouk/tdx-quote-parser) for the types
 //! and [sgx-quote](https://docs.rs/sgx-quote) for the no-std parsing using [nom](https://docs.rs/nom).
@@ -11,111 +16,270 @@
 //! The `mock` feature flag allows generating mock quotes, which this library can parse and verify. This
 //! is used for testing attestation features on without needing TDX hardware.
 //!
+//! The `p384` feature flag adds support for quotes made with a P-384 attestation key.
+//!
+//! The `serde` feature flag implements `Serialize` and `Deserialize` for [Quote] and its parts. Byte
+//! fields are hex encoded in human readable formats such as JSON, and left as bytes otherwise.
+//!
+//! The `scale` feature flag implements the SCALE codec traits and `TypeInfo` for [Quote], its parts
+//! and the error types, for use in a Substrate runtime. Certification data is limited to 64 KiB.
+//!
+//! [BatchVerifier] verifies many quotes at once, only verifying shared QE reports once. With the `std`
+//! feature flag, it can spread the work across threads.
+//!
+//! [QuoteRef] parses a quote without copying, borrowing its fields from the input. It can be
+//! converted to an owned [Quote] when needed.
+//!
+//! By default, parsing is lenient. [ParseOptions] gives a strict mode for quotes from untrusted
+//! sources, which only accepts quotes in canonical form and limits certification data to 64 KiB.
+//!
+//! [QuoteDecoder] decodes quotes which arrive in chunks, such as from a stream with no framing.
+//!
+//! [CompactQuote] replaces the PCK certificate chain of a quote with an identifier for the PCK, which makes it
+//! much smaller. It is verified against a registry of known PCKs.
+//!
+//! [TcbInfo] and [QeIdentity] hold Intel's collateral in a compact, versioned binary form, from which
+//! [Quote::evaluate_tcb] finds the TCB status of a quote without parsing JSON. The `json` feature flag
+//! adds conversion from the signed JSON served by Intel.
+//!
+//! [Quote], [QuoteHeader] and [QuoteBody] implement `Display`, giving one labelled field per
+//! line with byte arrays as hex, so that quotes can be logged readably.
+//!
+//! With the `json` feature flag, `Quote::to_json` renders every field of a quote as JSON, with hex
+//! measurements, decoded attributes and PEM certificates. Layouts matching Intel's quote parser and
+//! go-tdx-guest are also available.
+//!
+//! [Quote::diff] lists the fields which differ between two quotes with the values from each, for
+//! example to find which measurement registers changed after an image update.
+//!
+//! [QuoteLayout] gives the byte range of every field of a quote, and renders an annotated hexdump. It
+//! does not validate the quote, so it can be used to debug quotes which fail to parse.
+//!
+//! The `protobuf` feature flag gives lossless conversion between [Quote] and the `QuoteV4` protobuf
+//! message used by [go-tdx-guest](https://github.com/google/go-tdx-guest). Bytes following the
+//! quote in the message are given separately by [Quote::from_protobuf_with_extra_bytes].
+//!
+//! The `cli` feature flag builds the `tdx-quote` command line tool, which inspects, hexdumps,
+//! converts and diffs quotes, and verifies them against a PCK, Intel collateral and a policy.
+//!
+//! Expected measurement values can be computed ahead of time, for comparison with those in a quote:
+//! [compute_mrtd] gives the build-time measurement from a TDVF firmware image, [VmConfiguration] and
+//! [LinuxBoot] give the runtime measurements from the virtual machine configuration and from booting
+//! a kernel, and [RtmrSet] simulates the runtime measurement registers.
+//!
 //! Warning: This is in early stages of development and has not been audited.
 //!
 //! For quote generation, see [`configfs-tsm`](https://crates.io/crates/configfs-tsm).
 #![no_std]
+mod attestation_key;
+#[cfg(feature = "alloc")]
+mod batch;
+#[cfg(feature = "alloc")]
+mod boot;
+#[cfg(feature = "alloc")]
+mod collateral;
+#[cfg(feature = "json")]
+mod collateral_json;
+#[cfg(feature = "alloc")]
+mod compact_quote;
+#[cfg(feature = "alloc")]
+mod decoder;
+#[cfg(feature = "alloc")]
+mod diff;
+mod display;
 mod error;
+#[cfg(feature = "alloc")]
+mod layout;
 #[cfg(feature = "mock")]
 mod mock;
+#[cfg(feature = "alloc")]
+mod mrtd;
+mod parse_options;
+#[cfg(feature = "alloc")]
+mod pe;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "json")]
+mod quote_json;
+mod quote_ref;
+mod report_data;
+mod rtmr;
+#[cfg(feature = "scale")]
+mod scale_codec;
+#[cfg(feature = "serde")]
+mod serde_hex;
+#[cfg(feature = "alloc")]
+mod serialize;
 mod take_n;
-
-pub use error::QuoteParseError;
-use error::{QuoteVerificationError, VerifyingKeyError};
+#[cfg(feature = "alloc")]
+mod vm_config;
+
+pub use attestation_key::{AttestationKey, QuoteSignature};
+#[cfg(feature = "alloc")]
+pub use batch::BatchVerifier;
+#[cfg(feature = "alloc")]
+pub use boot::{
+    qemu_direct_boot_cmdline, EventLog, LinuxBoot, MeasuredEvent, EV_EFI_ACTION,
+    EV_EFI_BOOT_SERVICES_APPLICATION, EV_EVENT_TAG, EV_IPL, EV_SEPARATOR,
+};
+#[cfg(feature = "alloc")]
+pub use collateral::{
+    EnclaveId, PlatformTcb, QeIdentity, QeTcbLevel, SignatureReference, TcbEvaluation, TcbInfo,
+    TcbLevel, TcbStatus, TdxModule, TdxModuleIdentity, TdxModuleTcbLevel,
+    COMPACT_COLLATERAL_VERSION,
+};
+#[cfg(feature = "alloc")]
+pub use compact_quote::{CompactQuote, PckId};
+#[cfg(feature = "alloc")]
+pub use decoder::QuoteDecoder;
+#[cfg(feature = "alloc")]
+pub use diff::{FieldDiff, FieldValue, QuoteDiff};
+pub use error::{
+    CollateralError, PeError, ProtobufError, QuoteField, QuoteParseError, QuoteSerializeError,
+    QuoteVerificationError, TdvfError, VerifyingKeyError,
+};
+#[cfg(feature = "alloc")]
+pub use layout::{FieldLayout, QuoteLayout};
+#[cfg(feature = "alloc")]
+pub use mrtd::{
+    compute_mrtd, TdvfMetadata, TdvfSection, TdvfSectionType, TDVF_ATTRIBUTE_MR_EXTEND,
+    TDVF_ATTRIBUTE_PAGE_AUG,
+};
 use p256::EncodedPoint;
-use take_n::{take16, take2, take20, take384, take48, take64, take8};
+pub use parse_options::ParseOptions;
+#[cfg(feature = "alloc")]
+pub use pe::{PeImage, PeSection};
+#[cfg(feature = "json")]
+pub use quote_json::JsonLayout;
+pub use quote_ref::{
+    CertificationDataRef, QeReportCertificationDataRef, QuoteBodyRef, QuoteRef, ReportBodyRef,
+    SgxReportBodyRef,
+};
+pub use report_data::ReportDataBinding;
+pub use rtmr::{Rtmr, RtmrIndex, RtmrSet};
+use take_n::{take12, take16, take2, take20, take32, take384, take42, take48, take64, take8};
+#[cfg(feature = "alloc")]
+pub use vm_config::{
+    AcpiTables, EfiVariable, SecureBootVariables, VmConfiguration, EFI_GLOBAL_VARIABLE_GUID,
+    EFI_IMAGE_SECURITY_DATABASE_GUID, EV_EFI_HANDOFF_TABLES2, EV_EFI_PLATFORM_FIRMWARE_BLOB2,
+    EV_EFI_VARIABLE_BOOT, EV_EFI_VARIABLE_DRIVER_CONFIG, EV_PLATFORM_CONFIG_FLAGS,
+};
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
 use nom::{
     bytes::complete::take,
     combinator::{map, map_res},
-    number::complete::{le_i16, le_i32, le_u16, le_u32},
+    number::complete::{le_u16, le_u32},
     sequence::tuple,
     IResult,
 };
 #[cfg(feature = "mock")]
 pub use p256::ecdsa::SigningKey;
 pub use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
-use sha2::{Digest, Sha256};
 
 const QUOTE_HEADER_LENGTH: usize = 48;
+#[cfg(feature = "alloc")]
 const V4_QUOTE_BODY_LENGTH: usize = 584;
-const V5_QUOTE_BODY_LENGTH: usize = V4_QUOTE_BODY_LENGTH + 64;
 
-/// A TDX Quote
+#[cfg(feature = "alloc")]
+/// A TDX or SGX Quote
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub struct Quote {
     pub header: QuoteHeader,
-    pub body: QuoteBody,
-    pub signature: Signature,
-    pub attestation_key: VerifyingKey,
+    pub body: ReportBody,
+    /// Signature of the header and body. [QuoteSignature::as_p256] gives the decoded P-256
+    /// signature.
+    pub signature: QuoteSignature,
+    /// The key which made the signature. [AttestationKey::as_p256] gives the decoded P-256 key.
+    pub attestation_key: AttestationKey,
     pub certification_data: CertificationData,
 }
 
+#[cfg(feature = "alloc")]
 impl Quote {
-    /// Parse and validate a TDX quote
-    pub fn from_bytes(original_input: &[u8]) -> Result<Self, QuoteParseError> {
-        // Parse header
-        let (input, header) = quote_header_parser(original_input)?;
-        if header.attestation_key_type != AttestionKeyType::ECDSA256WithP256 {
-            return Err(QuoteParseError::UnsupportedAttestationKeyType);
-        };
-        let body_length = match header.version {
-            4 => V4_QUOTE_BODY_LENGTH,
-            5 => V5_QUOTE_BODY_LENGTH,
-            _ => return Err(QuoteParseError::UnknownQuoteVersion),
-        };
-
-        // Get signed data
-        let signed_data = &original_input[..QUOTE_HEADER_LENGTH + body_length];
-
-        // Parse body
-        let (input, body) = body_parser(input, header.version)?;
-
-        // Signature
-        let (input, _signature_section_length) = le_i32(input)?;
-        let (input, signature) = take(64u8)(input)?;
-        let signature = Signature::from_bytes(signature.into())?;
-
-        // Attestation key
-        let (input, attestation_key) = take(64u8)(input)?;
-        let attestation_key_bytes = attestation_key;
-        let attestation_key = [&[4], attestation_key].concat(); // 0x04 means uncompressed
-        let attestation_key = VerifyingKey::from_sec1_bytes(&attestation_key)?;
-
-        // Verify signature
-        attestation_key.verify(signed_data, &signature)?;
-
-        // Certification data
-        let (input, certification_data_type) = le_i16(input)?;
-        let (input, certification_dat_len) = le_i32(input)?;
-        let certification_dat_len: usize = certification_dat_len.try_into()?;
-        let (_input, certification_data) = take(certification_dat_len)(input)?;
-        let certification_data = CertificationData::new(
-            certification_data_type,
-            certification_data.to_vec(),
-            attestation_key_bytes.to_vec(),
-        )?;
+    /// Parse and validate a TDX or SGX quote
+    pub fn from_bytes(input: &[u8]) -> Result<Self, QuoteParseError> {
+        QuoteRef::from_bytes(input)?.to_quote()
+    }
 
-        Ok(Quote {
-            header,
-            body,
-            signature,
-            attestation_key,
-            certification_data,
-        })
+    /// Parse and validate a TDX or SGX quote with the given options
+    pub fn from_bytes_with_options(
+        input: &[u8],
+        options: &ParseOptions,
+    ) -> Result<Self, QuoteParseError> {
+        QuoteRef::from_bytes_with_options(input, options)?.to_quote()
+    }
+
+    /// Parse a TDX or SGX quote without verifying the signature or the QE report binding, so that
+    /// broken or tampered quotes can be inspected. [Quote::verify_signature] and
+    /// [Quote::verify_qe_binding] should be used before trusting its contents.
+    ///
+    /// Signatures and attestation keys which cannot be decoded, such as a signature which is all
+    /// zeros, are kept as raw bytes, and fail verification.
+    pub fn parse_unverified(input: &[u8]) -> Result<Self, QuoteParseError> {
+        QuoteRef::parse_unverified(input)?.to_quote()
+    }
+
+    /// Verify that the header and body are signed by the attestation key
+    pub fn verify_signature(&self) -> Result<(), QuoteVerificationError> {
+        self.attestation_key
+            .verify(&self.signed_data(), &self.signature)
+    }
+
+    /// Verify that the QE report contains the hash of the attestation key, so that a valid
+    /// signature of the QE report from the PCK also certifies the attestation key
+    pub fn verify_qe_binding(&self) -> Result<(), QuoteVerificationError> {
+        match &self.certification_data {
+            CertificationData::QeReportCertificationData(qe_report_certification_data) => {
+                qe_report_certification_data.check_attestation_key(&self.attestation_key)
+            }
+            _ => Err(QuoteVerificationError::NoQeReportCertificationData),
+        }
     }
 
     /// Returns the report data
     pub fn report_input_data(&self) -> [u8; 64] {
-        self.body.reportdata
+        match &self.body {
+            ReportBody::Td(body) => body.reportdata,
+            ReportBody::Sgx(body) => body.report_data,
+        }
+    }
+
+    /// Returns the build-time measurement register, if this is a TDX quote
+    pub fn mrtd(&self) -> Option<[u8; 48]> {
+        self.td_body().map(|body| body.mrtd)
     }
 
-    /// Returns the build-time measurement register
-    pub fn mrtd(&self) -> [u8; 48] {
-        self.body.mrtd
+    /// Returns the TD report, if this is a TDX quote
+    pub fn td_body(&self) -> Option<&QuoteBody> {
+        match &self.body {
+            ReportBody::Td(body) => Some(body),
+            ReportBody::Sgx(_) => None,
+        }
+    }
+
+    /// Returns the enclave report, if this is an SGX quote
+    pub fn sgx_body(&self) -> Option<&SgxReportBody> {
+        match &self.body {
+            ReportBody::Sgx(body) => Some(body),
+            ReportBody::Td(_) => None,
+        }
     }
 
     /// Returns the QeReportCertificationData if present
@@ -134,16 +298,41 @@ impl Quote {
         let qe_report_certification_data = self
             .qe_report_certification_data()
             .ok_or(QuoteVerificationError::NoQeReportCertificationData)?;
-        pck.verify(
-            &qe_report_certification_data.qe_report[..],
-            &qe_report_certification_data.signature,
-        )?;
+        let signature = qe_report_certification_data
+            .signature
+            .as_p256()
+            .ok_or(QuoteVerificationError::BadSignature)?;
+        pck.verify(&qe_report_certification_data.qe_report[..], signature)?;
         Ok(())
     }
+
+    /// Evaluate the TCB status of a TDX quote using TCB Info and QE Identity collateral, given the
+    /// TCB of the platform from its PCK certificate
+    ///
+    /// This does not verify the quote, the PCK certificate chain or the signatures on the
+    /// collateral, nor check that the collateral is current.
+    pub fn evaluate_tcb(
+        &self,
+        platform: &PlatformTcb,
+        tcb_info: &TcbInfo,
+        qe_identity: &QeIdentity,
+    ) -> Result<TcbEvaluation, CollateralError> {
+        collateral::evaluate_tcb(self, platform, tcb_info, qe_identity)
+    }
 }
 
 /// Type of TEE used
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub enum TEEType {
     SGX = 0x00000000,
     TDX = 0x00000081,
@@ -164,9 +353,19 @@ impl TryFrom<u32> for TEEType {
 /// Type of the Attestation Key used by the Quoting Enclave
 #[non_exhaustive]
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub enum AttestionKeyType {
     ECDSA256WithP256 = 2,
-    /// Not yet supported by TDX
+    /// Not yet supported by TDX. Supported by this crate with the `p384` feature.
     ECDSA384WithP384 = 3,
 }
 
@@ -182,26 +381,50 @@ impl TryFrom<u16> for AttestionKeyType {
     }
 }
 
-/// A TDX quote header
-#[derive(Debug, Eq, PartialEq)]
+/// A quote header
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub struct QuoteHeader {
-    /// Quote version (4 or 5)
+    /// Quote version (3, 4 or 5)
     pub version: u16,
     /// Type of the Attestation Key used by the Quoting Enclave
     pub attestation_key_type: AttestionKeyType,
     /// Type of TEE used
     pub tee_type: TEEType,
-    /// Currently unused
+    /// Currently unused. For version 3 quotes this is the QE security version number
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub reserved1: [u8; 2],
-    /// Currently unused
+    /// Currently unused. For version 3 quotes this is the PCE security version number
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub reserved2: [u8; 2],
     /// UUID for the quoting enclave vendor
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub qe_vendor_id: [u8; 16], // Could use Uuid crate
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub user_data: [u8; 20],
 }
 
 /// Version of TDX used to create the quote
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub enum TDXVersion {
     /// TDX v1
     One,
@@ -216,59 +439,159 @@ impl TryFrom<u16> for TDXVersion {
         match value {
             2 => Ok(TDXVersion::One),
             3 => Ok(TDXVersion::OnePointFive),
-            _ => Err(QuoteParseError::Parse),
+            _ => Err(QuoteParseError::UnknownBodyType(value)),
         }
     }
 }
 
 /// A TDX quote body
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
 pub struct QuoteBody {
     pub tdx_version: TDXVersion,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub tee_tcb_svn: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub mrseam: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub mrsignerseam: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub seamattributes: [u8; 8],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub tdattributes: [u8; 8],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub xfam: [u8; 8],
     /// Build-time measurement
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub mrtd: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub mrconfigid: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub mrowner: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub mrownerconfig: [u8; 48],
     /// Runtime extendable measurement register
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub rtmr0: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub rtmr1: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub rtmr2: [u8; 48],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub rtmr3: [u8; 48],
     /// User defined input data
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub reportdata: [u8; 64],
     /// Optional as only for TDX 1.5
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_array"))]
     pub tee_tcb_svn_2: Option<[u8; 16]>,
     /// Optional as only for TDX 1.5
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_array"))]
     pub mrservicetd: Option<[u8; 48]>,
 }
 
+/// The body of a quote
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
+pub enum ReportBody {
+    /// A TD report, from a TDX quote
+    Td(QuoteBody),
+    /// An enclave report, from an SGX quote
+    Sgx(SgxReportBody),
+}
+
+/// An SGX enclave report body. This is also the format of the QE report.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "scale",
+    derive(
+        codec::Encode,
+        codec::Decode,
+        codec::MaxEncodedLen,
+        scale_info::TypeInfo
+    )
+)]
+pub struct SgxReportBody {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub cpu_svn: [u8; 16],
+    pub misc_select: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub reserved1: [u8; 12],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub isv_ext_prod_id: [u8; 16],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub attributes: [u8; 16],
+    /// Measurement of the enclave
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub mr_enclave: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub reserved2: [u8; 32],
+    /// Hash of the key used to sign the enclave
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub mr_signer: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub reserved3: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub config_id: [u8; 64],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub config_svn: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub reserved4: [u8; 42],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub isv_family_id: [u8; 16],
+    /// User defined input data
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
+    pub report_data: [u8; 64],
+}
+
 /// Data related to certifying the QE Report
+#[cfg(feature = "alloc")]
 #[non_exhaustive]
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq, Eq)]
 #[repr(i16)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CertificationData {
-    PckIdPpidPlainCpusvnPcesvn(Vec<u8>) = 1,
-    PckIdPpidRSA2048CpusvnPcesvn(Vec<u8>) = 2,
-    PckIdPpidRSA3072CpusvnPcesvn(Vec<u8>) = 3,
-    PckLeafCert(Vec<u8>) = 4,
-    PckCertChain(Vec<u8>) = 5,
+    PckIdPpidPlainCpusvnPcesvn(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))] Vec<u8>,
+    ) = 1,
+    PckIdPpidRSA2048CpusvnPcesvn(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))] Vec<u8>,
+    ) = 2,
+    PckIdPpidRSA3072CpusvnPcesvn(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))] Vec<u8>,
+    ) = 3,
+    PckLeafCert(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))] Vec<u8>) = 4,
+    PckCertChain(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))] Vec<u8>) = 5,
     QeReportCertificationData(QeReportCertificationData) = 6,
-    PlatformManifest(Vec<u8>) = 7,
+    PlatformManifest(#[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))] Vec<u8>) =
+        7,
 }
 
+#[cfg(feature = "alloc")]
 impl CertificationData {
-    pub fn new(
-        certification_data_type: i16,
-        data: Vec<u8>,
-        attestation_key: Vec<u8>,
-    ) -> Result<Self, QuoteParseError> {
+    pub fn new(certification_data_type: i16, data: Vec<u8>) -> Result<Self, QuoteParseError> {
         match certification_data_type {
             1 => Ok(Self::PckIdPpidPlainCpusvnPcesvn(data)),
             2 => Ok(Self::PckIdPpidRSA2048CpusvnPcesvn(data)),
@@ -276,70 +599,76 @@ impl CertificationData {
             4 => Ok(Self::PckLeafCert(data)),
             5 => Ok(Self::PckCertChain(data)),
             6 => Ok(Self::QeReportCertificationData(
-                QeReportCertificationData::new(data, attestation_key)?,
+                QeReportCertificationData::new(data)?,
             )),
             7 => Ok(Self::PlatformManifest(data)),
-            _ => Err(QuoteParseError::UnknownCertificationDataType),
+            _ => Err(QuoteParseError::UnknownCertificationDataType(
+                certification_data_type,
+            )),
         }
     }
 }
 
 /// Certification data which contains a signature from the PCK
+#[cfg(feature = "alloc")]
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QeReportCertificationData {
-    /// This should contain SHA256(attestation_public_key || QE authentication data) || 32 null from_bytes
+    /// The report data of this should contain SHA256(attestation_public_key || QE authentication data)
+    /// followed by null bytes. For P-384 attestation keys, SHA384 is used.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::array"))]
     pub qe_report: [u8; 384],
     /// Signature of the qe_report field made using the PCK key
-    pub signature: Signature,
+    pub signature: QuoteSignature,
     /// Authentication data used by the quoting enclave to provide additional context
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))]
     pub qe_authentication_data: Vec<u8>,
     /// Data required to verify the QE report signature
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::vec"))]
     pub certification_data: Vec<u8>,
 }
 
+#[cfg(feature = "alloc")]
 impl QeReportCertificationData {
-    /// Parse QeReportCertificationData from given input, checking the hash contains the given
-    /// attestation key
-    fn new(input: Vec<u8>, attestation_key: Vec<u8>) -> Result<Self, QuoteParseError> {
-        let (input, qe_report) = take384(&input)?;
-        // The last part of the qe_report is the hash of the attestation key and authentication
-        // data, followed by 32 null bytes (which we ignore)
-        let expected_hash = &qe_report[384 - 64..384 - 32];
-
-        let (input, signature) = take64(&input)?;
-        let signature = Signature::from_bytes((&signature).into())?;
-        let (input, qe_authentication_data_size) = le_i16(input)?;
-        let qe_authentication_data_size: usize = qe_authentication_data_size.try_into()?;
-        let (certification_data, qe_authentication_data) =
-            take(qe_authentication_data_size)(input)?;
-
-        // Check the hash in the qe_report
-        let hash = {
-            let mut hasher = Sha256::new();
-            hasher.update(&attestation_key);
-            hasher.update(&qe_authentication_data);
-            hasher.finalize()
-        };
-        if hash[..] != *expected_hash {
-            return Err(QuoteParseError::AttestationKeyDoesNotMatch);
-        }
+    /// Parse QeReportCertificationData from given input
+    fn new(input: Vec<u8>) -> Result<Self, QuoteParseError> {
+        let (_input, qe_report_certification_data) = qe_report_certification_data_parser(&input)
+            .map_err(QuoteParseError::locate(
+                &input,
+                QuoteField::CertificationData(6),
+            ))?;
+        (&qe_report_certification_data).try_into()
+    }
 
-        Ok(Self {
-            qe_report,
-            signature,
-            qe_authentication_data: qe_authentication_data.to_vec(),
-            certification_data: certification_data.to_vec(),
-        })
+    /// Decode the QE report, which has the format of an SGX enclave report
+    pub fn qe_report_body(&self) -> Result<SgxReportBody, QuoteParseError> {
+        let (_input, body) = sgx_report_body_parser(&self.qe_report).map_err(
+            QuoteParseError::locate(&self.qe_report, QuoteField::QeReport),
+        )?;
+        Ok((&body).into())
+    }
+
+    /// Check that the QE report data contains the hash of the given attestation key
+    fn check_attestation_key(
+        &self,
+        attestation_key: &AttestationKey,
+    ) -> Result<(), QuoteVerificationError> {
+        if !attestation_key
+            .matches_qe_report_data(&self.qe_authentication_data, &self.qe_report[384 - 64..])
+        {
+            return Err(QuoteVerificationError::AttestationKeyDoesNotMatch);
+        }
+        Ok(())
     }
 }
 
 /// Helper function to encode a public key as bytes
 pub fn encode_verifying_key(input: &VerifyingKey) -> Result<[u8; 33], VerifyingKeyError> {
-    Ok(input
+    input
         .to_encoded_point(true)
         .as_bytes()
         .try_into()
-        .map_err(|_| VerifyingKeyError::BadSize)?)
+        .map_err(|_| VerifyingKeyError::BadSize)
 }
 
 /// Helper function to decode bytes to a public key
@@ -369,30 +698,69 @@ fn quote_header_parser(input: &[u8]) -> IResult<&[u8], QuoteHeader> {
                 version,
                 attestation_key_type: attestation_key_type.try_into()?,
                 tee_type: tee_type.try_into()?,
-                reserved1,
-                reserved2,
-                qe_vendor_id,
-                user_data,
+                reserved1: *reserved1,
+                reserved2: *reserved2,
+                qe_vendor_id: *qe_vendor_id,
+                user_data: *user_data,
             })
         },
     )(input)
 }
 
+/// Check that the body type of a version 5 quote is known, and is for the TEE type in the header
+pub(crate) fn check_body_type(tee_type: &TEEType, body_type: u16) -> Result<(), QuoteParseError> {
+    match (tee_type, body_type) {
+        (TEEType::SGX, 1) | (TEEType::TDX, 2 | 3) => Ok(()),
+        (_, 1..=3) => Err(QuoteParseError::BodyTypeMismatch {
+            tee_type: tee_type.clone(),
+            body_type,
+        }),
+        _ => Err(QuoteParseError::UnknownBodyType(body_type)),
+    }
+}
+
+/// For a version 5 quote, check the body type at the start of the input following the header. If
+/// the input is too short, this is left for [body_parser] to report.
+fn check_v5_body_type(header: &QuoteHeader, input: &[u8]) -> Result<(), QuoteParseError> {
+    match input.get(..2) {
+        Some(&[low, high]) if header.version == 5 => {
+            check_body_type(&header.tee_type, u16::from_le_bytes([low, high]))
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Parser for a quote body
-fn body_parser(input: &[u8], version: u16) -> IResult<&[u8], QuoteBody> {
-    let (input, tdx_version) = match version {
-        // For a version 4 quote format, we know its TDX v1
-        4 => (input, TDXVersion::One),
-        // For version 5 quote format, read the TDX version from the quote
-        5 => {
+fn body_parser<'a>(input: &'a [u8], header: &QuoteHeader) -> IResult<&'a [u8], ReportBodyRef<'a>> {
+    let (input, tdx_version) = match (header.version, &header.tee_type) {
+        // Version 3 quotes are always SGX, version 4 quotes may be either
+        (3, TEEType::SGX) | (4, TEEType::SGX) => {
+            let (input, body) = sgx_report_body_parser(input)?;
+            return Ok((input, ReportBodyRef::Sgx(body)));
+        }
+        // For a version 4 TDX quote, we know its TDX v1
+        (4, TEEType::TDX) => (input, TDXVersion::One),
+        // For version 5 quote format, read the body type from the quote. This must agree with the
+        // TEE type given in the header.
+        (5, tee_type) => {
             let (input, body_type) = le_u16(input)?;
             let (input, _body_size) = le_u32(input)?;
-            (
-                input,
-                body_type.try_into().map_err(|_| {
-                    nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Fail))
-                })?,
-            )
+            // Callers check the body type with [check_v5_body_type] first, to give a specific
+            // error
+            match (tee_type, body_type) {
+                (TEEType::SGX, 1) => {
+                    let (input, body) = sgx_report_body_parser(input)?;
+                    return Ok((input, ReportBodyRef::Sgx(body)));
+                }
+                (TEEType::TDX, 2) => (input, TDXVersion::One),
+                (TEEType::TDX, 3) => (input, TDXVersion::OnePointFive),
+                _ => {
+                    return Err(nom::Err::Failure(nom::error::Error::new(
+                        input,
+                        nom::error::ErrorKind::Fail,
+                    )))
+                }
+            }
         }
         _ => {
             return Err(nom::Err::Failure(nom::error::Error::new(
@@ -413,11 +781,56 @@ fn body_parser(input: &[u8], version: u16) -> IResult<&[u8], QuoteBody> {
     } else {
         input
     };
-    Ok((input, body))
+    Ok((input, ReportBodyRef::Td(body)))
+}
+
+/// Parser for an SGX enclave report body
+fn sgx_report_body_parser(input: &[u8]) -> IResult<&[u8], SgxReportBodyRef<'_>> {
+    map(
+        tuple((
+            take16, le_u32, take12, take16, take16, take32, take32, take32, take32, take64, le_u16,
+            le_u16, le_u16, take42, take16, take64,
+        )),
+        |(
+            cpu_svn,
+            misc_select,
+            reserved1,
+            isv_ext_prod_id,
+            attributes,
+            mr_enclave,
+            reserved2,
+            mr_signer,
+            reserved3,
+            config_id,
+            isv_prod_id,
+            isv_svn,
+            config_svn,
+            reserved4,
+            isv_family_id,
+            report_data,
+        )| SgxReportBodyRef {
+            cpu_svn,
+            misc_select,
+            reserved1,
+            isv_ext_prod_id,
+            attributes,
+            mr_enclave,
+            reserved2,
+            mr_signer,
+            reserved3,
+            config_id,
+            isv_prod_id,
+            isv_svn,
+            config_svn,
+            reserved4,
+            isv_family_id,
+            report_data,
+        },
+    )(input)
 }
 
 /// Parser for a quote body - omitting optional extra fields for TDX 1.5
-fn basic_body_parser(input: &[u8]) -> IResult<&[u8], QuoteBody> {
+fn basic_body_parser(input: &[u8]) -> IResult<&[u8], QuoteBodyRef<'_>> {
     map(
         tuple((
             take16, take48, take48, take8, take8, take8, take48, take48, take48, take48, take48,
@@ -439,7 +852,7 @@ fn basic_body_parser(input: &[u8]) -> IResult<&[u8], QuoteBody> {
             rtmr2,
             rtmr3,
             reportdata,
-        )| QuoteBody {
+        )| QuoteBodyRef {
             tdx_version: TDXVersion::One,
             tee_tcb_svn,
             mrseam,
@@ -461,3 +874,22 @@ fn basic_body_parser(input: &[u8]) -> IResult<&[u8], QuoteBody> {
         },
     )(input)
 }
+
+/// Parser for QE report certification data
+fn qe_report_certification_data_parser(
+    input: &[u8],
+) -> IResult<&[u8], QeReportCertificationDataRef<'_>> {
+    let (input, qe_report) = take384(input)?;
+    let (input, signature) = take64(input)?;
+    let (input, qe_authentication_data_size) = le_u16(input)?;
+    let (certification_data, qe_authentication_data) = take(qe_authentication_data_size)(input)?;
+    Ok((
+        &[],
+        QeReportCertificationDataRef {
+            qe_report,
+            signature,
+            qe_authentication_data,
+            certification_data,
+        },
+    ))
+}
@@ -0,0 +1,432 @@
+//! Compute the expected build-time measurement (MRTD) of a TD from its TDVF (OVMF) firmware image
+//!
+//! The TDVF metadata describes which sections of the firmware are loaded into the TD and which of
+//! them are measured. The TDX module builds MRTD by hashing a record of every TDH.MEM.PAGE.ADD
+//! and TDH.MR.EXTEND call made while the TD is being built, which we emulate here.
+//!
+//! This is based on the [TDX Virtual Firmware Design Guide](https://cdrdv2.intel.com/v1/dl/getContent/733585),
+//! section 11.
+use crate::error::TdvfError;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha384};
+
+/// Size of a TD private memory page
+const PAGE_SIZE: u64 = 0x1000;
+/// Number of bytes measured by a single TDH.MR.EXTEND call
+const MR_EXTEND_CHUNK_SIZE: usize = 256;
+/// Length of a serialized TDVF section descriptor
+const TDVF_SECTION_LENGTH: usize = 32;
+/// Length of a TDVF descriptor, not including its sections
+const TDVF_DESCRIPTOR_LENGTH: usize = 16;
+
+/// 'TDVF' as a little endian u32
+const TDVF_SIGNATURE: u32 = 0x46564454;
+/// 96b582de-1fb2-45f7-baea-a366c55a082d - marks the end of the OVMF GUIDed table
+const OVMF_TABLE_FOOTER_GUID: [u8; 16] = [
+    0xde, 0x82, 0xb5, 0x96, 0xb2, 0x1f, 0xf7, 0x45, 0xba, 0xea, 0xa3, 0x66, 0xc5, 0x5a, 0x08, 0x2d,
+];
+/// e47a6535-984a-4798-865e-4685a7bf8ec2 - OVMF table entry giving the TDX metadata offset
+const TDX_METADATA_OFFSET_GUID: [u8; 16] = [
+    0x35, 0x65, 0x7a, 0xe4, 0x4a, 0x98, 0x98, 0x47, 0x86, 0x5e, 0x46, 0x85, 0xa7, 0xbf, 0x8e, 0xc2,
+];
+/// e9eaf9f3-168e-44d5-a8eb-7f4d8738f6ae - immediately precedes the TDVF descriptor
+const TDX_METADATA_GUID: [u8; 16] = [
+    0xf3, 0xf9, 0xea, 0xe9, 0x8e, 0x16, 0xd5, 0x44, 0xa8, 0xeb, 0x7f, 0x4d, 0x87, 0x38, 0xf6, 0xae,
+];
+
+/// Section attribute indicating that the section contents are measured into MRTD
+pub const TDVF_ATTRIBUTE_MR_EXTEND: u32 = 0x00000001;
+/// Section attribute indicating that the pages are added after the TD is built (with TDH.MEM.PAGE.AUG)
+pub const TDVF_ATTRIBUTE_PAGE_AUG: u32 = 0x00000002;
+
+/// Type of a TDVF metadata section
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TdvfSectionType {
+    /// Boot firmware volume
+    Bfv = 0,
+    /// Configuration firmware volume
+    Cfv = 1,
+    /// TD hand-off block, generated by the VMM
+    TdHob = 2,
+    /// Temporary memory used by the firmware during early boot
+    TempMem = 3,
+    /// Permanent memory, accepted at runtime
+    PermMem = 4,
+    /// Payload such as a kernel, when loaded by the VMM
+    Payload = 5,
+    /// Parameters for the payload, such as a command line
+    PayloadParam = 6,
+}
+
+impl TryFrom<u32> for TdvfSectionType {
+    type Error = TdvfError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Bfv),
+            1 => Ok(Self::Cfv),
+            2 => Ok(Self::TdHob),
+            3 => Ok(Self::TempMem),
+            4 => Ok(Self::PermMem),
+            5 => Ok(Self::Payload),
+            6 => Ok(Self::PayloadParam),
+            _ => Err(TdvfError::UnknownSectionType(value)),
+        }
+    }
+}
+
+/// A section descriptor from the TDVF metadata
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TdvfSection {
+    /// Offset of the section data in the firmware image
+    pub data_offset: u32,
+    /// Size of the section data in the firmware image
+    pub raw_data_size: u32,
+    /// Guest physical address at which the section is loaded
+    pub memory_address: u64,
+    /// Size of the section in guest memory
+    pub memory_data_size: u64,
+    pub section_type: TdvfSectionType,
+    /// Bitmask of `TDVF_ATTRIBUTE_*`
+    pub attributes: u32,
+}
+
+impl TdvfSection {
+    /// Whether the contents of this section are measured with TDH.MR.EXTEND
+    pub fn is_extended(&self) -> bool {
+        self.attributes & TDVF_ATTRIBUTE_MR_EXTEND != 0
+    }
+
+    /// Whether the pages of this section are added with TDH.MEM.PAGE.ADD while building the TD,
+    /// and so are included in MRTD
+    pub fn is_added(&self) -> bool {
+        self.section_type != TdvfSectionType::PermMem
+            && self.attributes & TDVF_ATTRIBUTE_PAGE_AUG == 0
+    }
+
+    /// Parse a section descriptor
+    fn from_bytes(input: &[u8]) -> Result<Self, TdvfError> {
+        Ok(Self {
+            data_offset: read_u32(input, 0)?,
+            raw_data_size: read_u32(input, 4)?,
+            memory_address: read_u64(input, 8)?,
+            memory_data_size: read_u64(input, 16)?,
+            section_type: read_u32(input, 24)?.try_into()?,
+            attributes: read_u32(input, 28)?,
+        })
+    }
+}
+
+/// The TDX metadata of a TDVF firmware image
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TdvfMetadata {
+    /// Metadata format version
+    pub version: u32,
+    pub sections: Vec<TdvfSection>,
+}
+
+impl TdvfMetadata {
+    /// Locate and parse the TDX metadata in a firmware image, using the OVMF GUIDed table
+    pub fn from_firmware(firmware: &[u8]) -> Result<Self, TdvfError> {
+        let offset = metadata_offset(firmware)?;
+        let descriptor_start = firmware
+            .len()
+            .checked_sub(offset)
+            .ok_or(TdvfError::OutOfBounds)?;
+        let guid_start = descriptor_start
+            .checked_sub(TDX_METADATA_GUID.len())
+            .ok_or(TdvfError::OutOfBounds)?;
+        if firmware[guid_start..descriptor_start] != TDX_METADATA_GUID {
+            return Err(TdvfError::BadMetadataGuid);
+        }
+
+        let descriptor = &firmware[descriptor_start..];
+        if read_u32(descriptor, 0)? != TDVF_SIGNATURE {
+            return Err(TdvfError::BadSignature);
+        }
+        let length: usize = read_u32(descriptor, 4)?.try_into()?;
+        let version = read_u32(descriptor, 8)?;
+        if version != 1 {
+            return Err(TdvfError::UnsupportedVersion(version));
+        }
+        let number_of_sections: usize = read_u32(descriptor, 12)?.try_into()?;
+        if length
+            != number_of_sections
+                .checked_mul(TDVF_SECTION_LENGTH)
+                .and_then(|len| len.checked_add(TDVF_DESCRIPTOR_LENGTH))
+                .ok_or(TdvfError::OutOfBounds)?
+        {
+            return Err(TdvfError::BadLength);
+        }
+
+        let sections = descriptor
+            .get(TDVF_DESCRIPTOR_LENGTH..length)
+            .ok_or(TdvfError::OutOfBounds)?
+            .chunks_exact(TDVF_SECTION_LENGTH)
+            .map(TdvfSection::from_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { version, sections })
+    }
+
+    /// Compute MRTD by emulating the way the VMM builds the TD from the given firmware image,
+    /// adding each page of each section in order, and extending MRTD with the contents of
+    /// measured sections.
+    pub fn mrtd(&self, firmware: &[u8]) -> Result<[u8; 48], TdvfError> {
+        let mut hasher = Sha384::new();
+        for section in self.sections.iter().filter(|section| section.is_added()) {
+            if section.memory_address % PAGE_SIZE != 0 || section.memory_data_size % PAGE_SIZE != 0
+            {
+                return Err(TdvfError::MisalignedSection);
+            }
+            let data = if section.is_extended() {
+                let start: usize = section.data_offset.try_into()?;
+                let end = start
+                    .checked_add(section.raw_data_size.try_into()?)
+                    .ok_or(TdvfError::OutOfBounds)?;
+                firmware.get(start..end).ok_or(TdvfError::OutOfBounds)?
+            } else {
+                &[]
+            };
+
+            let mut page = [0u8; PAGE_SIZE as usize];
+            for page_index in 0..section.memory_data_size / PAGE_SIZE {
+                let page_offset = page_index
+                    .checked_mul(PAGE_SIZE)
+                    .ok_or(TdvfError::OutOfBounds)?;
+                let gpa = section
+                    .memory_address
+                    .checked_add(page_offset)
+                    .filter(|gpa| gpa.checked_add(PAGE_SIZE - 1).is_some())
+                    .ok_or(TdvfError::OutOfBounds)?;
+                mem_page_add(&mut hasher, gpa);
+                if section.is_extended() {
+                    // Data beyond the end of the raw section is zero
+                    let page_data = usize::try_from(page_offset)
+                        .ok()
+                        .and_then(|page_start| data.get(page_start..))
+                        .unwrap_or(&[]);
+                    let available = page_data.len().min(page.len());
+                    page.fill(0);
+                    page[..available].copy_from_slice(&page_data[..available]);
+
+                    for (chunk_index, chunk) in page.chunks_exact(MR_EXTEND_CHUNK_SIZE).enumerate()
+                    {
+                        mr_extend(
+                            &mut hasher,
+                            gpa + (chunk_index * MR_EXTEND_CHUNK_SIZE) as u64,
+                            chunk,
+                        );
+                    }
+                }
+            }
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Compute the expected MRTD of a TD booted with the given TDVF firmware image
+pub fn compute_mrtd(firmware: &[u8]) -> Result<[u8; 48], TdvfError> {
+    TdvfMetadata::from_firmware(firmware)?.mrtd(firmware)
+}
+
+/// Record a TDH.MEM.PAGE.ADD of the page at the given guest physical address
+fn mem_page_add(hasher: &mut Sha384, gpa: u64) {
+    let mut buffer = [0u8; 128];
+    buffer[..12].copy_from_slice(b"MEM.PAGE.ADD");
+    buffer[16..24].copy_from_slice(&gpa.to_le_bytes());
+    hasher.update(buffer);
+}
+
+/// Record a TDH.MR.EXTEND of a 256 byte chunk at the given guest physical address
+fn mr_extend(hasher: &mut Sha384, gpa: u64, chunk: &[u8]) {
+    let mut buffer = [0u8; 128];
+    buffer[..9].copy_from_slice(b"MR.EXTEND");
+    buffer[16..24].copy_from_slice(&gpa.to_le_bytes());
+    hasher.update(buffer);
+    hasher.update(chunk);
+}
+
+/// Find the TDX metadata offset, which is given relative to the end of the firmware image, by
+/// walking the OVMF GUIDed table backwards from its footer
+fn metadata_offset(firmware: &[u8]) -> Result<usize, TdvfError> {
+    // The footer GUID is found 32 bytes before the end of the image, after the reset vector,
+    // preceded by the total length of the table
+    let footer_start = firmware
+        .len()
+        .checked_sub(0x30)
+        .ok_or(TdvfError::NoOvmfTable)?;
+    if firmware[footer_start..footer_start + 16] != OVMF_TABLE_FOOTER_GUID {
+        return Err(TdvfError::NoOvmfTable);
+    }
+    let table_end = footer_start.checked_sub(2).ok_or(TdvfError::NoOvmfTable)?;
+    let table_length: usize = read_u16(firmware, table_end)?.into();
+    let table_start = (footer_start + 16)
+        .checked_sub(table_length)
+        .ok_or(TdvfError::OutOfBounds)?;
+    let table = firmware
+        .get(table_start..table_end)
+        .ok_or(TdvfError::OutOfBounds)?;
+
+    // Each entry is made up of its data, then its length as a u16, then its GUID
+    let mut entry_end = table.len();
+    while entry_end >= 18 {
+        let guid = &table[entry_end - 16..entry_end];
+        let entry_length: usize = read_u16(table, entry_end - 18)?.into();
+        if entry_length < 18 || entry_length > entry_end {
+            return Err(TdvfError::OutOfBounds);
+        }
+        if guid == TDX_METADATA_OFFSET_GUID {
+            let data = &table[entry_end - entry_length..entry_end - 18];
+            return Ok(read_u32(data, 0)?.try_into()?);
+        }
+        entry_end -= entry_length;
+    }
+    Err(TdvfError::NoMetadataOffset)
+}
+
+fn read_u16(input: &[u8], offset: usize) -> Result<u16, TdvfError> {
+    input
+        .get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+        .ok_or(TdvfError::OutOfBounds)
+}
+
+fn read_u32(input: &[u8], offset: usize) -> Result<u32, TdvfError> {
+    input
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(TdvfError::OutOfBounds)
+}
+
+fn read_u64(input: &[u8], offset: usize) -> Result<u64, TdvfError> {
+    input
+        .get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(TdvfError::OutOfBounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtmr::tests::from_hex;
+    use alloc::vec;
+
+    /// Build a minimal firmware image with a BFV which is measured, and a TD HOB which is not
+    fn test_firmware() -> Vec<u8> {
+        let mut firmware = vec![0u8; 0x3000];
+        // BFV contents
+        for (i, byte) in firmware[..0x1100].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        // TDX metadata at 0x2000
+        let mut metadata = Vec::new();
+        metadata.extend_from_slice(&TDX_METADATA_GUID);
+        metadata.extend_from_slice(&TDVF_SIGNATURE.to_le_bytes());
+        metadata.extend_from_slice(&(16u32 + 2 * 32).to_le_bytes());
+        metadata.extend_from_slice(&1u32.to_le_bytes());
+        metadata.extend_from_slice(&2u32.to_le_bytes());
+        // BFV, 0x1100 bytes of data loaded into 2 pages
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.extend_from_slice(&0x1100u32.to_le_bytes());
+        metadata.extend_from_slice(&0xffffe000u64.to_le_bytes());
+        metadata.extend_from_slice(&0x2000u64.to_le_bytes());
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.extend_from_slice(&TDVF_ATTRIBUTE_MR_EXTEND.to_le_bytes());
+        // TD HOB, 1 page
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.extend_from_slice(&0x809000u64.to_le_bytes());
+        metadata.extend_from_slice(&0x1000u64.to_le_bytes());
+        metadata.extend_from_slice(&2u32.to_le_bytes());
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        firmware[0x2000 - 16..0x2000 - 16 + metadata.len()].copy_from_slice(&metadata);
+
+        // OVMF table with a single entry, followed by the footer and the reset vector
+        let mut table = Vec::new();
+        table.extend_from_slice(&(0x3000u32 - 0x2000).to_le_bytes());
+        table.extend_from_slice(&22u16.to_le_bytes());
+        table.extend_from_slice(&TDX_METADATA_OFFSET_GUID);
+        table.extend_from_slice(&(22u16 + 18).to_le_bytes());
+        table.extend_from_slice(&OVMF_TABLE_FOOTER_GUID);
+        let table_start = 0x3000 - 0x20 - table.len();
+        firmware[table_start..0x3000 - 0x20].copy_from_slice(&table);
+        firmware
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata = TdvfMetadata::from_firmware(&test_firmware()).unwrap();
+        assert_eq!(metadata.version, 1);
+        assert_eq!(metadata.sections.len(), 2);
+        assert_eq!(metadata.sections[0].section_type, TdvfSectionType::Bfv);
+        assert!(metadata.sections[0].is_extended());
+        assert_eq!(metadata.sections[1].section_type, TdvfSectionType::TdHob);
+        assert!(!metadata.sections[1].is_extended());
+    }
+
+    #[test]
+    fn test_compute_mrtd() {
+        let firmware = test_firmware();
+
+        let mut expected = Sha384::new();
+        let mut bfv = firmware[..0x1100].to_vec();
+        bfv.resize(0x2000, 0);
+        for page in 0..2u64 {
+            let gpa = 0xffffe000 + page * 0x1000;
+            let mut page_add = [0u8; 128];
+            page_add[..12].copy_from_slice(b"MEM.PAGE.ADD");
+            page_add[16..24].copy_from_slice(&gpa.to_le_bytes());
+            expected.update(page_add);
+            for chunk in 0..16u64 {
+                let mut extend = [0u8; 128];
+                extend[..9].copy_from_slice(b"MR.EXTEND");
+                extend[16..24].copy_from_slice(&(gpa + chunk * 256).to_le_bytes());
+                expected.update(extend);
+                let start = (page * 0x1000 + chunk * 256) as usize;
+                expected.update(&bfv[start..start + 256]);
+            }
+        }
+        let mut page_add = [0u8; 128];
+        page_add[..12].copy_from_slice(b"MEM.PAGE.ADD");
+        page_add[16..24].copy_from_slice(&0x809000u64.to_le_bytes());
+        expected.update(page_add);
+        let expected: [u8; 48] = expected.finalize().into();
+
+        assert_eq!(compute_mrtd(&firmware).unwrap(), expected);
+    }
+
+    /// Expected values computed independently of this crate, by hashing the TDH.MEM.PAGE.ADD and
+    /// TDH.MR.EXTEND records as laid out in the TDX module specification
+    #[test]
+    fn test_mrtd_known_answer() {
+        let mut firmware = test_firmware();
+        assert_eq!(
+            compute_mrtd(&firmware).unwrap(),
+            from_hex("4eb7d86063f6ebed9fede8c3f01fb2e66ce535d9808a79133e7be8c1d51e2634342bc7c12ca808a25f81463f8e34398a")
+        );
+
+        // A measured section more than a page larger than its data is extended with zero pages
+        firmware[0x2020..0x2028].copy_from_slice(&0x3000u64.to_le_bytes());
+        assert_eq!(
+            compute_mrtd(&firmware).unwrap(),
+            from_hex("9eb781d33d62fd0d005c5454d1070897a6977e3658bf9adaebeedfa0920d4939dad46330a06ca5f56b30d9c34609faf3")
+        );
+    }
+
+    #[test]
+    fn test_section_address_overflow() {
+        let mut firmware = test_firmware();
+        firmware[0x2018..0x2020].copy_from_slice(&(u64::MAX - 0xfff).to_le_bytes());
+        assert_eq!(compute_mrtd(&firmware), Err(TdvfError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_not_tdvf() {
+        assert_eq!(compute_mrtd(&[0; 0x1000]), Err(TdvfError::NoOvmfTable));
+    }
+}
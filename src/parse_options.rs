@@ -0,0 +1,42 @@
+//! Options controlling how strictly quotes are parsed
+
+/// The maximum length of the certification data section in strict mode. Real quotes contain a PCK
+/// certificate chain of a few kilobytes.
+pub(crate) const STRICT_MAX_CERTIFICATION_DATA_LEN: usize = 64 * 1024;
+
+/// Options for parsing a quote
+///
+/// By default, parsing is lenient: length fields which are not needed to find the end of a
+/// section are ignored, as are trailing bytes and reserved fields. In strict mode, only quotes in
+/// canonical form are accepted, and the certification data is limited to 64 KiB.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseOptions {
+    /// Reject inconsistent length fields, trailing bytes and non-zero reserved fields
+    pub strict: bool,
+    /// In strict mode, accept trailing bytes if they are all zero, as quotes are sometimes
+    /// padded by the software which retrieves them
+    pub allow_zero_padding: bool,
+    /// The maximum length of the certification data section. This is unlimited by default.
+    pub max_certification_data_len: usize,
+}
+
+impl ParseOptions {
+    /// Options for strict parsing, accepting only quotes in canonical form
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            max_certification_data_len: STRICT_MAX_CERTIFICATION_DATA_LEN,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            allow_zero_padding: false,
+            max_certification_data_len: usize::MAX,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Minimal PE/COFF parsing, for computing the Authenticode hash of an EFI application as
+//! measured by the firmware when the application is loaded
+//!
+//! This is based on the [PE Format](https://learn.microsoft.com/en-us/windows/win32/debug/pe-format)
+//! specification and the Windows Authenticode Portable Executable Signature Format.
+use crate::error::PeError;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha384};
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+/// Index of the certificate table in the optional header data directories
+const CERTIFICATE_TABLE_INDEX: u32 = 4;
+const SECTION_HEADER_LENGTH: usize = 40;
+
+/// A section of a PE/COFF image
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PeSection<'a> {
+    /// Section name, with trailing null bytes removed
+    pub name: &'a [u8],
+    pub virtual_size: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    /// The raw section data from the image
+    pub raw_data: &'a [u8],
+}
+
+impl<'a> PeSection<'a> {
+    /// The section data as loaded into memory, which is the raw data truncated to the virtual
+    /// size
+    pub fn data(&self) -> &'a [u8] {
+        let len = self.raw_data.len().min(self.virtual_size as usize);
+        &self.raw_data[..len]
+    }
+}
+
+/// A parsed PE/COFF image
+#[derive(Debug, Clone)]
+pub struct PeImage<'a> {
+    image: &'a [u8],
+    checksum_offset: usize,
+    /// Offset of the certificate table data directory entry, if present
+    certificate_entry_offset: Option<usize>,
+    certificate_table_size: usize,
+    size_of_headers: usize,
+    sections: Vec<PeSection<'a>>,
+}
+
+impl<'a> PeImage<'a> {
+    /// Parse a PE/COFF image
+    pub fn parse(image: &'a [u8]) -> Result<Self, PeError> {
+        if image.get(..2) != Some(b"MZ") {
+            return Err(PeError::NotPe);
+        }
+        let pe_offset = read_u32(image, 0x3c)? as usize;
+        if image.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+            return Err(PeError::NotPe);
+        }
+        let coff_offset = pe_offset + 4;
+        let number_of_sections = read_u16(image, coff_offset + 2)? as usize;
+        let size_of_optional_header = read_u16(image, coff_offset + 16)? as usize;
+
+        let optional_header_offset = coff_offset + 20;
+        let (number_of_rva_and_sizes_offset, data_directories_offset) =
+            match read_u16(image, optional_header_offset)? {
+                PE32_MAGIC => (optional_header_offset + 92, optional_header_offset + 96),
+                PE32_PLUS_MAGIC => (optional_header_offset + 108, optional_header_offset + 112),
+                _ => return Err(PeError::UnknownOptionalHeaderMagic),
+            };
+        let size_of_headers = read_u32(image, optional_header_offset + 60)? as usize;
+        let checksum_offset = optional_header_offset + 64;
+
+        let (certificate_entry_offset, certificate_table_size) =
+            if read_u32(image, number_of_rva_and_sizes_offset)? > CERTIFICATE_TABLE_INDEX {
+                let offset = data_directories_offset + 8 * CERTIFICATE_TABLE_INDEX as usize;
+                (Some(offset), read_u32(image, offset + 4)? as usize)
+            } else {
+                (None, 0)
+            };
+
+        let section_table_offset = optional_header_offset + size_of_optional_header;
+        let sections = (0..number_of_sections)
+            .map(|index| {
+                let header = image
+                    .get(section_table_offset + index * SECTION_HEADER_LENGTH..)
+                    .and_then(|header| header.get(..SECTION_HEADER_LENGTH))
+                    .ok_or(PeError::OutOfBounds)?;
+                let name = &header[..8];
+                let name_length = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+                let size_of_raw_data = read_u32(header, 16)?;
+                let pointer_to_raw_data = read_u32(header, 20)?;
+                let raw_data = image
+                    .get(
+                        pointer_to_raw_data as usize
+                            ..pointer_to_raw_data as usize + size_of_raw_data as usize,
+                    )
+                    .ok_or(PeError::OutOfBounds)?;
+                Ok(PeSection {
+                    name: &name[..name_length],
+                    virtual_size: read_u32(header, 8)?,
+                    size_of_raw_data,
+                    pointer_to_raw_data,
+                    raw_data,
+                })
+            })
+            .collect::<Result<Vec<_>, PeError>>()?;
+
+        if size_of_headers > image.len() || checksum_offset + 4 > size_of_headers {
+            return Err(PeError::OutOfBounds);
+        }
+
+        Ok(Self {
+            image,
+            checksum_offset,
+            certificate_entry_offset,
+            certificate_table_size,
+            size_of_headers,
+            sections,
+        })
+    }
+
+    /// The sections of the image, in the order they appear in the section table
+    pub fn sections(&self) -> &[PeSection<'a>] {
+        &self.sections
+    }
+
+    /// Find a section by name, for example `.linux`
+    pub fn section(&self, name: &str) -> Option<&PeSection<'a>> {
+        self.sections
+            .iter()
+            .find(|section| section.name == name.as_bytes())
+    }
+
+    /// Compute the SHA384 Authenticode hash of the image, which is what the firmware measures
+    /// when loading an EFI application
+    pub fn authenticode_sha384(&self) -> Result<[u8; 48], PeError> {
+        Ok(self.authenticode_hasher::<Sha384>()?.finalize().into())
+    }
+
+    /// A hasher which has been given the parts of the image covered by the Authenticode hash
+    fn authenticode_hasher<D: Digest>(&self) -> Result<D, PeError> {
+        let mut hasher = D::new();
+
+        // Headers, omitting the checksum and the certificate table entry
+        hasher.update(&self.image[..self.checksum_offset]);
+        match self.certificate_entry_offset {
+            Some(certificate_entry_offset) => {
+                if certificate_entry_offset + 8 > self.size_of_headers {
+                    return Err(PeError::OutOfBounds);
+                }
+                hasher.update(&self.image[self.checksum_offset + 4..certificate_entry_offset]);
+                hasher.update(&self.image[certificate_entry_offset + 8..self.size_of_headers]);
+            }
+            None => hasher.update(&self.image[self.checksum_offset + 4..self.size_of_headers]),
+        }
+
+        // Sections, in the order they appear in the file
+        let mut sections: Vec<&PeSection> = self
+            .sections
+            .iter()
+            .filter(|section| section.size_of_raw_data > 0)
+            .collect();
+        sections.sort_by_key(|section| section.pointer_to_raw_data);
+        let mut sum_of_bytes_hashed = self.size_of_headers;
+        for section in sections {
+            hasher.update(section.raw_data);
+            sum_of_bytes_hashed += section.raw_data.len();
+        }
+
+        // Any remaining data which is not part of the certificate table
+        let end = self
+            .image
+            .len()
+            .checked_sub(self.certificate_table_size)
+            .ok_or(PeError::OutOfBounds)?;
+        if end > sum_of_bytes_hashed {
+            hasher.update(&self.image[sum_of_bytes_hashed..end]);
+        }
+
+        Ok(hasher)
+    }
+}
+
+fn read_u16(input: &[u8], offset: usize) -> Result<u16, PeError> {
+    input
+        .get(offset..offset + 2)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u16::from_le_bytes)
+        .ok_or(PeError::OutOfBounds)
+}
+
+fn read_u32(input: &[u8], offset: usize) -> Result<u32, PeError> {
+    input
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or(PeError::OutOfBounds)
+}
+
+/// Build a minimal PE32+ image with the given sections, for testing
+#[cfg(test)]
+pub(crate) fn test_image(sections: &[(&str, &[u8])]) -> Vec<u8> {
+    use alloc::vec;
+    let size_of_headers = 0x200;
+    let mut image = vec![0u8; size_of_headers];
+    image[..2].copy_from_slice(b"MZ");
+    image[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+    image[0x40..0x44].copy_from_slice(b"PE\0\0");
+    // COFF header
+    image[0x46..0x48].copy_from_slice(&(sections.len() as u16).to_le_bytes());
+    image[0x54..0x56].copy_from_slice(&240u16.to_le_bytes());
+    // Optional header
+    let optional_header = 0x58;
+    image[optional_header..optional_header + 2].copy_from_slice(&PE32_PLUS_MAGIC.to_le_bytes());
+    image[optional_header + 60..optional_header + 64]
+        .copy_from_slice(&(size_of_headers as u32).to_le_bytes());
+    image[optional_header + 64..optional_header + 68].copy_from_slice(&[0xaa; 4]);
+    image[optional_header + 108..optional_header + 112].copy_from_slice(&16u32.to_le_bytes());
+    // Section table
+    let mut section_header = optional_header + 240;
+    for (name, data) in sections {
+        let pointer_to_raw_data = image.len() as u32;
+        image[section_header..section_header + name.len()].copy_from_slice(name.as_bytes());
+        image[section_header + 8..section_header + 12]
+            .copy_from_slice(&(data.len() as u32).to_le_bytes());
+        image[section_header + 16..section_header + 20]
+            .copy_from_slice(&(data.len() as u32).to_le_bytes());
+        image[section_header + 20..section_header + 24]
+            .copy_from_slice(&pointer_to_raw_data.to_le_bytes());
+        image.extend_from_slice(data);
+        section_header += SECTION_HEADER_LENGTH;
+    }
+    image
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::rtmr::tests::from_hex;
+
+    /// A PE32+ executable with an Authenticode signature, the Windows launcher from conda
+    /// (BSD-3-Clause)
+    pub(crate) const SIGNED_IMAGE: &[u8] = include_bytes!("../tests/test-pe/conda_cli_64.exe");
+
+    #[test]
+    fn test_authenticode_known_answer() {
+        let pe = PeImage::parse(SIGNED_IMAGE).unwrap();
+
+        // The signature is over the SHA256 Authenticode hash, which is given in the signed data.
+        // This is in the certificate table at offset 41984, after its 8 byte header and the
+        // start of the PKCS#7 structure.
+        let sha256: [u8; 32] = pe
+            .authenticode_hasher::<sha2::Sha256>()
+            .unwrap()
+            .finalize()
+            .into();
+        let signed_digest = &SIGNED_IMAGE[41984 + 8 + 105..][..32];
+        assert_eq!(sha256, signed_digest);
+        assert_eq!(
+            sha256,
+            from_hex("1576c2b3b289c303206f91b1e638e6827407fd3865294842055249f574d860b5")
+        );
+
+        assert_eq!(
+            pe.authenticode_sha384(),
+            Ok(from_hex("6b0ace07f452d14040421a47fffc6f2f6cf10e077d5bde7c9b0a91c3da7f514ca797a79c3cb5cd930d786e623861c918"))
+        );
+    }
+
+    #[test]
+    fn test_authenticode_ignores_checksum_and_certificates() {
+        let mut image = test_image(&[(".text", &[1; 0x100]), (".data", &[2; 0x80])]);
+        let pe = PeImage::parse(&image).unwrap();
+        assert_eq!(pe.sections().len(), 2);
+        assert_eq!(pe.section(".data").unwrap().data(), &[2; 0x80]);
+        let hash = pe.authenticode_sha384().unwrap();
+
+        let mut expected = Sha384::new();
+        expected.update(&image[..0x58 + 64]);
+        expected.update(&image[0x58 + 68..0x58 + 144]);
+        expected.update(&image[0x58 + 152..]);
+        let expected: [u8; 48] = expected.finalize().into();
+        assert_eq!(hash, expected);
+
+        // Changing the checksum or appending a certificate table does not change the hash
+        image[0x58 + 64] = 0;
+        image.extend_from_slice(&[3; 0x10]);
+        image[0x58 + 144..0x58 + 148].copy_from_slice(&(0x380u32).to_le_bytes());
+        image[0x58 + 148..0x58 + 152].copy_from_slice(&(0x10u32).to_le_bytes());
+        assert_eq!(
+            PeImage::parse(&image).unwrap().authenticode_sha384(),
+            Ok(hash)
+        );
+
+        // Changing section data does
+        image[0x200] = 0;
+        assert_ne!(
+            PeImage::parse(&image).unwrap().authenticode_sha384(),
+            Ok(hash)
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Conversion to and from the protobuf `QuoteV4` message used by
+//! [go-tdx-guest](https://github.com/google/go-tdx-guest)
+//!
+//! The message types follow `proto/tdx.proto` from go-tdx-guest, so they can be embedded in other
+//! prost messages. Bytes following a quote (`extra_bytes`) have no place in a [Quote], so are given
+//! separately by [Quote::from_protobuf_with_extra_bytes].
+use crate::{
+    error::ProtobufError, serialize::certification_data_length, AttestationKey, AttestionKeyType,
+    CertificationData, QeReportCertificationData, Quote, QuoteBody, QuoteHeader, QuoteSignature,
+    ReportBody, TDXVersion, TEEType,
+};
+use alloc::{vec, vec::Vec};
+use prost::Message;
+
+/// A version 4 quote
+#[derive(Clone, PartialEq, Message)]
+pub struct QuoteV4 {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<Header>,
+    #[prost(message, optional, tag = "2")]
+    pub td_quote_body: Option<TdQuoteBody>,
+    /// Length of the signature section, as given in the quote
+    #[prost(uint32, tag = "3")]
+    pub signed_data_size: u32,
+    #[prost(message, optional, tag = "4")]
+    pub signed_data: Option<Ecdsa256BitQuoteV4AuthData>,
+    /// Any bytes following the quote
+    #[prost(bytes = "vec", tag = "5")]
+    pub extra_bytes: Vec<u8>,
+}
+
+/// A quote header
+#[derive(Clone, PartialEq, Message)]
+pub struct Header {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint32, tag = "2")]
+    pub attestation_key_type: u32,
+    #[prost(uint32, tag = "3")]
+    pub tee_type: u32,
+    #[prost(bytes = "vec", tag = "4")]
+    pub qe_svn: Vec<u8>,
+    #[prost(bytes = "vec", tag = "5")]
+    pub pce_svn: Vec<u8>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub qe_vendor_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub user_data: Vec<u8>,
+}
+
+/// A TD report body
+#[derive(Clone, PartialEq, Message)]
+pub struct TdQuoteBody {
+    #[prost(bytes = "vec", tag = "1")]
+    pub tee_tcb_svn: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub mr_seam: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub mr_signer_seam: Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub seam_attributes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "5")]
+    pub td_attributes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub xfam: Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub mr_td: Vec<u8>,
+    #[prost(bytes = "vec", tag = "8")]
+    pub mr_config_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "9")]
+    pub mr_owner: Vec<u8>,
+    #[prost(bytes = "vec", tag = "10")]
+    pub mr_owner_config: Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "11")]
+    pub rtmrs: Vec<Vec<u8>>,
+    #[prost(bytes = "vec", tag = "12")]
+    pub report_data: Vec<u8>,
+}
+
+/// The signature section
+#[derive(Clone, PartialEq, Message)]
+pub struct Ecdsa256BitQuoteV4AuthData {
+    #[prost(bytes = "vec", tag = "1")]
+    pub signature: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub ecdsa_attestation_key: Vec<u8>,
+    #[prost(message, optional, tag = "3")]
+    pub certification_data: Option<CertificationDataMessage>,
+}
+
+/// Certification data, which is always QE report certification data
+#[derive(Clone, PartialEq, Message)]
+pub struct CertificationDataMessage {
+    #[prost(uint32, tag = "1")]
+    pub certificate_data_type: u32,
+    #[prost(uint32, tag = "2")]
+    pub size: u32,
+    #[prost(message, optional, tag = "3")]
+    pub qe_report_certification_data: Option<QeReportCertificationDataMessage>,
+}
+
+/// QE report certification data
+#[derive(Clone, PartialEq, Message)]
+pub struct QeReportCertificationDataMessage {
+    #[prost(message, optional, tag = "1")]
+    pub qe_report: Option<EnclaveReport>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub qe_report_signature: Vec<u8>,
+    #[prost(message, optional, tag = "3")]
+    pub qe_auth_data: Option<QeAuthData>,
+    #[prost(message, optional, tag = "4")]
+    pub pck_certificate_chain_data: Option<PckCertificateChainData>,
+}
+
+/// QE authentication data
+#[derive(Clone, PartialEq, Message)]
+pub struct QeAuthData {
+    #[prost(uint32, tag = "1")]
+    pub parsed_data_size: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+}
+
+/// The certification data nested within QE report certification data, which is typically a PCK
+/// certificate chain
+#[derive(Clone, PartialEq, Message)]
+pub struct PckCertificateChainData {
+    #[prost(uint32, tag = "1")]
+    pub certificate_data_type: u32,
+    #[prost(uint32, tag = "2")]
+    pub size: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub pck_cert_chain: Vec<u8>,
+}
+
+/// An SGX enclave report, used for the QE report. Reserved fields include the fields of
+/// [crate::SgxReportBody] which go-tdx-guest does not decode.
+#[derive(Clone, PartialEq, Message)]
+pub struct EnclaveReport {
+    #[prost(bytes = "vec", tag = "1")]
+    pub cpu_svn: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub misc_select: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub reserved1: Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub attributes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "5")]
+    pub mr_enclave: Vec<u8>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub reserved2: Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub mr_signer: Vec<u8>,
+    #[prost(bytes = "vec", tag = "8")]
+    pub reserved3: Vec<u8>,
+    #[prost(uint32, tag = "9")]
+    pub isv_prod_id: u32,
+    #[prost(uint32, tag = "10")]
+    pub isv_svn: u32,
+    #[prost(bytes = "vec", tag = "11")]
+    pub reserved4: Vec<u8>,
+    #[prost(bytes = "vec", tag = "12")]
+    pub report_data: Vec<u8>,
+}
+
+impl Quote {
+    /// Encode the quote as a go-tdx-guest `QuoteV4` protobuf message
+    pub fn to_protobuf(&self) -> Result<Vec<u8>, ProtobufError> {
+        Ok(QuoteV4::try_from(self)?.encode_to_vec())
+    }
+
+    /// Encode the quote as a go-tdx-guest `QuoteV4` protobuf message, with the given bytes
+    /// following the quote
+    pub fn to_protobuf_with_extra_bytes(
+        &self,
+        extra_bytes: &[u8],
+    ) -> Result<Vec<u8>, ProtobufError> {
+        let mut message = QuoteV4::try_from(self)?;
+        message.extra_bytes = extra_bytes.to_vec();
+        Ok(message.encode_to_vec())
+    }
+
+    /// Decode a quote from a go-tdx-guest `QuoteV4` protobuf message. The quote is not verified.
+    ///
+    /// This fails with [ProtobufError::ExtraBytes] if the message has bytes following the quote.
+    pub fn from_protobuf(input: &[u8]) -> Result<Self, ProtobufError> {
+        QuoteV4::decode(input)
+            .map_err(|_| ProtobufError::Decode)?
+            .try_into()
+    }
+
+    /// Decode a quote from a go-tdx-guest `QuoteV4` protobuf message, together with any bytes
+    /// following the quote. The quote is not verified.
+    pub fn from_protobuf_with_extra_bytes(input: &[u8]) -> Result<(Self, Vec<u8>), ProtobufError> {
+        let mut message = QuoteV4::decode(input).map_err(|_| ProtobufError::Decode)?;
+        let extra_bytes = core::mem::take(&mut message.extra_bytes);
+        Ok((message.try_into()?, extra_bytes))
+    }
+}
+
+impl TryFrom<&Quote> for QuoteV4 {
+    type Error = ProtobufError;
+
+    fn try_from(quote: &Quote) -> Result<Self, Self::Error> {
+        let (ReportBody::Td(body), CertificationData::QeReportCertificationData(qe_certification)) =
+            (&quote.body, &quote.certification_data)
+        else {
+            return Err(ProtobufError::Unsupported);
+        };
+        if quote.header.version != 4
+            || quote.header.tee_type != TEEType::TDX
+            || body.tdx_version != TDXVersion::One
+            || quote.header.attestation_key_type != AttestionKeyType::ECDSA256WithP256
+        {
+            return Err(ProtobufError::Unsupported);
+        }
+        let (nested_type, pck_cert_chain) =
+            split_certification_data(&qe_certification.certification_data)
+                .ok_or(ProtobufError::InvalidField("pck_certificate_chain_data"))?;
+        let certification_data_size = certification_data_length(&quote.certification_data);
+
+        Ok(QuoteV4 {
+            header: Some(Header {
+                version: quote.header.version.into(),
+                attestation_key_type: quote.header.attestation_key_type.clone() as u32,
+                tee_type: quote.header.tee_type.clone() as u32,
+                qe_svn: quote.header.reserved1.to_vec(),
+                pce_svn: quote.header.reserved2.to_vec(),
+                qe_vendor_id: quote.header.qe_vendor_id.to_vec(),
+                user_data: quote.header.user_data.to_vec(),
+            }),
+            td_quote_body: Some(TdQuoteBody {
+                tee_tcb_svn: body.tee_tcb_svn.to_vec(),
+                mr_seam: body.mrseam.to_vec(),
+                mr_signer_seam: body.mrsignerseam.to_vec(),
+                seam_attributes: body.seamattributes.to_vec(),
+                td_attributes: body.tdattributes.to_vec(),
+                xfam: body.xfam.to_vec(),
+                mr_td: body.mrtd.to_vec(),
+                mr_config_id: body.mrconfigid.to_vec(),
+                mr_owner: body.mrowner.to_vec(),
+                mr_owner_config: body.mrownerconfig.to_vec(),
+                rtmrs: vec![
+                    body.rtmr0.to_vec(),
+                    body.rtmr1.to_vec(),
+                    body.rtmr2.to_vec(),
+                    body.rtmr3.to_vec(),
+                ],
+                report_data: body.reportdata.to_vec(),
+            }),
+            signed_data_size: size(quote.signature_section_length())?,
+            signed_data: Some(Ecdsa256BitQuoteV4AuthData {
+                signature: quote.signature.to_bytes(),
+                ecdsa_attestation_key: quote.attestation_key.to_raw_bytes(),
+                certification_data: Some(CertificationDataMessage {
+                    certificate_data_type: 6,
+                    size: size(certification_data_size)?,
+                    qe_report_certification_data: Some(QeReportCertificationDataMessage {
+                        qe_report: Some(enclave_report(&qe_certification.qe_report)),
+                        qe_report_signature: qe_certification.signature.to_bytes().to_vec(),
+                        qe_auth_data: Some(QeAuthData {
+                            parsed_data_size: size(qe_certification.qe_authentication_data.len())?,
+                            data: qe_certification.qe_authentication_data.clone(),
+                        }),
+                        pck_certificate_chain_data: Some(PckCertificateChainData {
+                            certificate_data_type: u32::try_from(nested_type).map_err(|_| {
+                                ProtobufError::InvalidField("pck_certificate_chain_data")
+                            })?,
+                            size: size(pck_cert_chain.len())?,
+                            pck_cert_chain: pck_cert_chain.to_vec(),
+                        }),
+                    }),
+                }),
+            }),
+            extra_bytes: Vec::new(),
+        })
+    }
+}
+
+impl TryFrom<QuoteV4> for Quote {
+    type Error = ProtobufError;
+
+    fn try_from(message: QuoteV4) -> Result<Self, Self::Error> {
+        if !message.extra_bytes.is_empty() {
+            return Err(ProtobufError::ExtraBytes);
+        }
+        let header = message
+            .header
+            .ok_or(ProtobufError::MissingField("header"))?;
+        let body = message
+            .td_quote_body
+            .ok_or(ProtobufError::MissingField("td_quote_body"))?;
+        let signed_data = message
+            .signed_data
+            .ok_or(ProtobufError::MissingField("signed_data"))?;
+        let certification_data = signed_data
+            .certification_data
+            .ok_or(ProtobufError::MissingField("certification_data"))?;
+        let qe_certification = certification_data
+            .qe_report_certification_data
+            .ok_or(ProtobufError::MissingField("qe_report_certification_data"))?;
+        let qe_report = qe_certification
+            .qe_report
+            .ok_or(ProtobufError::MissingField("qe_report"))?;
+        let qe_auth_data = qe_certification
+            .qe_auth_data
+            .ok_or(ProtobufError::MissingField("qe_auth_data"))?;
+        let pck_certificate_chain_data = qe_certification
+            .pck_certificate_chain_data
+            .ok_or(ProtobufError::MissingField("pck_certificate_chain_data"))?;
+
+        if header.version != 4 {
+            return Err(ProtobufError::Unsupported);
+        }
+        // The message only has a TD quote body, so the quote must be for TDX
+        if header.tee_type != TEEType::TDX as u32 {
+            return Err(ProtobufError::InvalidField("tee_type"));
+        }
+        if certification_data.certificate_data_type != 6 {
+            return Err(ProtobufError::InvalidField("certificate_data_type"));
+        }
+        let attestation_key_type = u16::try_from(header.attestation_key_type)
+            .ok()
+            .and_then(|value| AttestionKeyType::try_from(value).ok())
+            .filter(|value| *value == AttestionKeyType::ECDSA256WithP256)
+            .ok_or(ProtobufError::InvalidField("attestation_key_type"))?;
+        let [rtmr0, rtmr1, rtmr2, rtmr3] = <[Vec<u8>; 4]>::try_from(body.rtmrs)
+            .map_err(|_| ProtobufError::InvalidField("rtmrs"))?;
+        check_size(
+            qe_auth_data.parsed_data_size,
+            &qe_auth_data.data,
+            "parsed_data_size",
+        )?;
+        if u16::try_from(qe_auth_data.data.len()).is_err() {
+            return Err(ProtobufError::InvalidField("qe_auth_data"));
+        }
+        check_size(
+            pck_certificate_chain_data.size,
+            &pck_certificate_chain_data.pck_cert_chain,
+            "pck_certificate_chain_data",
+        )?;
+
+        let mut nested_certification_data = Vec::new();
+        nested_certification_data.extend_from_slice(
+            &i16::try_from(pck_certificate_chain_data.certificate_data_type)
+                .map_err(|_| ProtobufError::InvalidField("pck_certificate_chain_data"))?
+                .to_le_bytes(),
+        );
+        nested_certification_data.extend_from_slice(&pck_certificate_chain_data.size.to_le_bytes());
+        nested_certification_data.extend_from_slice(&pck_certificate_chain_data.pck_cert_chain);
+
+        let quote = Quote {
+            header: QuoteHeader {
+                version: 4,
+                attestation_key_type: attestation_key_type.clone(),
+                tee_type: TEEType::TDX,
+                reserved1: fixed(&header.qe_svn, "qe_svn")?,
+                reserved2: fixed(&header.pce_svn, "pce_svn")?,
+                qe_vendor_id: fixed(&header.qe_vendor_id, "qe_vendor_id")?,
+                user_data: fixed(&header.user_data, "user_data")?,
+            },
+            body: ReportBody::Td(QuoteBody {
+                tdx_version: TDXVersion::One,
+                tee_tcb_svn: fixed(&body.tee_tcb_svn, "tee_tcb_svn")?,
+                mrseam: fixed(&body.mr_seam, "mr_seam")?,
+                mrsignerseam: fixed(&body.mr_signer_seam, "mr_signer_seam")?,
+                seamattributes: fixed(&body.seam_attributes, "seam_attributes")?,
+                tdattributes: fixed(&body.td_attributes, "td_attributes")?,
+                xfam: fixed(&body.xfam, "xfam")?,
+                mrtd: fixed(&body.mr_td, "mr_td")?,
+                mrconfigid: fixed(&body.mr_config_id, "mr_config_id")?,
+                mrowner: fixed(&body.mr_owner, "mr_owner")?,
+                mrownerconfig: fixed(&body.mr_owner_config, "mr_owner_config")?,
+                rtmr0: fixed(&rtmr0, "rtmrs")?,
+                rtmr1: fixed(&rtmr1, "rtmrs")?,
+                rtmr2: fixed(&rtmr2, "rtmrs")?,
+                rtmr3: fixed(&rtmr3, "rtmrs")?,
+                reportdata: fixed(&body.report_data, "report_data")?,
+                tee_tcb_svn_2: None,
+                mrservicetd: None,
+            }),
+            signature: QuoteSignature::from_raw_bytes_lenient(
+                &attestation_key_type,
+                &signed_data.signature,
+            )
+            .map_err(|_| ProtobufError::InvalidField("signature"))?,
+            attestation_key: AttestationKey::from_raw_bytes_lenient(
+                &attestation_key_type,
+                &signed_data.ecdsa_attestation_key,
+            )
+            .map_err(|_| ProtobufError::InvalidField("ecdsa_attestation_key"))?,
+            certification_data: CertificationData::QeReportCertificationData(
+                QeReportCertificationData {
+                    qe_report: qe_report_bytes(&qe_report)?,
+                    signature: QuoteSignature::from_raw_bytes_lenient(
+                        &AttestionKeyType::ECDSA256WithP256,
+                        &qe_certification.qe_report_signature,
+                    )
+                    .map_err(|_| ProtobufError::InvalidField("qe_report_signature"))?,
+                    qe_authentication_data: qe_auth_data.data,
+                    certification_data: nested_certification_data,
+                },
+            ),
+        };
+
+        // Sizes are derived from the other fields, so must agree with them
+        if certification_data.size as usize != certification_data_length(&quote.certification_data)
+        {
+            return Err(ProtobufError::InvalidField("size"));
+        }
+        if message.signed_data_size as usize != quote.signature_section_length() {
+            return Err(ProtobufError::InvalidField("signed_data_size"));
+        }
+        Ok(quote)
+    }
+}
+
+/// Split certification data nested within QE report certification data into its type and
+/// contents. The message has no place for bytes following the contents, so there must be none.
+fn split_certification_data(input: &[u8]) -> Option<(i16, &[u8])> {
+    let certification_data_type = i16::from_le_bytes(input.get(0..2)?.try_into().ok()?);
+    let length = u32::from_le_bytes(input.get(2..6)?.try_into().ok()?);
+    let data = input.get(6..)?;
+    if data.len() != usize::try_from(length).ok()? {
+        return None;
+    }
+    Some((certification_data_type, data))
+}
+
+/// Split a QE report into the fields of [EnclaveReport]
+fn enclave_report(qe_report: &[u8; 384]) -> EnclaveReport {
+    EnclaveReport {
+        cpu_svn: qe_report[0..16].to_vec(),
+        misc_select: u32::from_le_bytes([
+            qe_report[16],
+            qe_report[17],
+            qe_report[18],
+            qe_report[19],
+        ]),
+        reserved1: qe_report[20..48].to_vec(),
+        attributes: qe_report[48..64].to_vec(),
+        mr_enclave: qe_report[64..96].to_vec(),
+        reserved2: qe_report[96..128].to_vec(),
+        mr_signer: qe_report[128..160].to_vec(),
+        reserved3: qe_report[160..256].to_vec(),
+        isv_prod_id: u16::from_le_bytes([qe_report[256], qe_report[257]]).into(),
+        isv_svn: u16::from_le_bytes([qe_report[258], qe_report[259]]).into(),
+        reserved4: qe_report[260..320].to_vec(),
+        report_data: qe_report[320..384].to_vec(),
+    }
+}
+
+/// Join the fields of an [EnclaveReport] into a QE report
+fn qe_report_bytes(report: &EnclaveReport) -> Result<[u8; 384], ProtobufError> {
+    let isv_prod_id = u16::try_from(report.isv_prod_id)
+        .map_err(|_| ProtobufError::InvalidField("isv_prod_id"))?;
+    let isv_svn =
+        u16::try_from(report.isv_svn).map_err(|_| ProtobufError::InvalidField("isv_svn"))?;
+    let fields: [(&'static str, &[u8], usize); 12] = [
+        ("cpu_svn", &report.cpu_svn, 16),
+        ("misc_select", &report.misc_select.to_le_bytes(), 4),
+        ("reserved1", &report.reserved1, 28),
+        ("attributes", &report.attributes, 16),
+        ("mr_enclave", &report.mr_enclave, 32),
+        ("reserved2", &report.reserved2, 32),
+        ("mr_signer", &report.mr_signer, 32),
+        ("reserved3", &report.reserved3, 96),
+        ("isv_prod_id", &isv_prod_id.to_le_bytes(), 2),
+        ("isv_svn", &isv_svn.to_le_bytes(), 2),
+        ("reserved4", &report.reserved4, 60),
+        ("report_data", &report.report_data, 64),
+    ];
+    let mut output = [0; 384];
+    let mut offset = 0;
+    for (name, field, length) in fields {
+        if field.len() != length {
+            return Err(ProtobufError::InvalidField(name));
+        }
+        output[offset..offset + length].copy_from_slice(field);
+        offset += length;
+    }
+    Ok(output)
+}
+
+/// Convert a byte field to a fixed length array
+fn fixed<const N: usize>(field: &[u8], name: &'static str) -> Result<[u8; N], ProtobufError> {
+    field
+        .try_into()
+        .map_err(|_| ProtobufError::InvalidField(name))
+}
+
+/// Convert a length to a size field
+fn size(length: usize) -> Result<u32, ProtobufError> {
+    u32::try_from(length).map_err(|_| ProtobufError::Unsupported)
+}
+
+/// Check that a size field gives the length of the data it describes
+fn check_size(size: u32, data: &[u8], name: &'static str) -> Result<(), ProtobufError> {
+    if size as usize != data.len() {
+        return Err(ProtobufError::InvalidField(name));
+    }
+    Ok(())
+}
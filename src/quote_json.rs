@@ -0,0 +1,618 @@
+//! Rendering of quotes as JSON, in this crate's own layout or in those of other attestation tooling
+use crate::{
+    display::{certification_data_type_name, Hex, Uuid},
+    serialize::certification_data_length,
+    AttestionKeyType, CertificationData, QeReportCertificationData, Quote, QuoteBody, QuoteHeader,
+    ReportBody, SgxReportBody, TDXVersion, TEEType,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use serde_json::{json, Map, Value};
+
+/// Layout of the JSON given by [Quote::to_json_with_layout]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum JsonLayout {
+    /// This crate's own layout, as described for [Quote::to_json]
+    #[default]
+    TdxQuote,
+    /// The field names of the quote structures in Intel's DCAP headers (`sgx_quote_4.h` and
+    /// `sgx_quote_5.h`), as printed by Intel's `tdx-quote-parser`. Byte fields are hex encoded.
+    Intel,
+    /// The protobuf JSON encoding of the `QuoteV4` message used by go-tdx-guest. Byte fields are
+    /// base64 encoded. Only TDX quotes have a body, and only a PCK certificate chain within QE
+    /// report certification data is given, as go-tdx-guest has no fields for anything else.
+    GoTdxGuest,
+}
+
+impl Quote {
+    /// Render the quote as JSON, giving every field. Byte fields are lowercase hex.
+    ///
+    /// - `header` gives `version`, `attestation_key_type` and `tee_type` decoded to their names,
+    ///   and `qe_vendor_id` as a UUID.
+    /// - `body` gives `type` (`"TDX 1.0"`, `"TDX 1.5"` or `"SGX"`) followed by the fields of the
+    ///   report body. TCB security version numbers are given as lists of numbers, and
+    ///   `tee_tcb_svn` is also split into `tdx_module_major_version` and `tdx_module_svn`.
+    ///   Attribute fields are followed by a `_flags` field listing the names of the bits set.
+    /// - `signature` and `attestation_key` are the raw encodings of the signature and key.
+    /// - `certification_data` gives `type` as a number and its `name`. QE report certification
+    ///   data gives the decoded `qe_report`, `qe_report_signature`, `qe_authentication_data` and
+    ///   the nested `certification_data`. Certificates are given as a `pem_certificates` list, and
+    ///   other certification data as hex `data`.
+    pub fn to_json(&self) -> String {
+        self.to_json_with_layout(JsonLayout::TdxQuote)
+    }
+
+    /// Render the quote as pretty-printed JSON with the given layout
+    pub fn to_json_with_layout(&self, layout: JsonLayout) -> String {
+        let value = match layout {
+            JsonLayout::TdxQuote => self.tdx_quote_json(),
+            JsonLayout::Intel => self.intel_json(),
+            JsonLayout::GoTdxGuest => self.go_tdx_guest_json(),
+        };
+        format!("{value:#}")
+    }
+
+    fn tdx_quote_json(&self) -> Value {
+        json!({
+            "header": header_json(&self.header),
+            "body": match &self.body {
+                ReportBody::Td(body) => td_body_json(body),
+                ReportBody::Sgx(body) => sgx_body_json(body, Some("SGX")),
+            },
+            "signature": Hex(&self.signature.to_bytes()).to_string(),
+            "attestation_key": Hex(&self.attestation_key.to_raw_bytes()).to_string(),
+            "certification_data": certification_data_json(&self.certification_data),
+        })
+    }
+
+    fn intel_json(&self) -> Value {
+        let mut output = Map::new();
+        output.insert("header".into(), intel_header_json(&self.header));
+        if self.header.version == 5 {
+            let (body_type, body_size) = match &self.body {
+                ReportBody::Sgx(_) => (1, 384),
+                ReportBody::Td(body) => match body.tdx_version {
+                    TDXVersion::One => (2, 584),
+                    TDXVersion::OnePointFive => (3, 648),
+                },
+            };
+            output.insert("type".into(), body_type.into());
+            output.insert("size".into(), body_size.into());
+        }
+        output.insert(
+            "report_body".into(),
+            match &self.body {
+                ReportBody::Td(body) => intel_td_body_json(body),
+                ReportBody::Sgx(body) => intel_sgx_body_json(body),
+            },
+        );
+        output.insert(
+            "signature_data_len".into(),
+            self.signature_section_length().into(),
+        );
+        output.insert(
+            "signature_data".into(),
+            json!({
+                "sig": Hex(&self.signature.to_bytes()).to_string(),
+                "attest_pub_key": Hex(&self.attestation_key.to_raw_bytes()).to_string(),
+                "certification_data": intel_certification_data_json(&self.certification_data),
+            }),
+        );
+        Value::Object(output)
+    }
+
+    fn go_tdx_guest_json(&self) -> Value {
+        let header = &self.header;
+        let mut output = Map::new();
+        output.insert(
+            "header".into(),
+            json!({
+                "version": header.version,
+                "attestationKeyType": attestation_key_type_value(&header.attestation_key_type),
+                "teeType": tee_type_value(&header.tee_type),
+                "qeSvn": base64(&header.reserved1),
+                "pceSvn": base64(&header.reserved2),
+                "qeVendorId": base64(&header.qe_vendor_id),
+                "userData": base64(&header.user_data),
+            }),
+        );
+        if let ReportBody::Td(body) = &self.body {
+            output.insert(
+                "tdQuoteBody".into(),
+                json!({
+                    "teeTcbSvn": base64(&body.tee_tcb_svn),
+                    "mrSeam": base64(&body.mrseam),
+                    "mrSignerSeam": base64(&body.mrsignerseam),
+                    "seamAttributes": base64(&body.seamattributes),
+                    "tdAttributes": base64(&body.tdattributes),
+                    "xfam": base64(&body.xfam),
+                    "mrTd": base64(&body.mrtd),
+                    "mrConfigId": base64(&body.mrconfigid),
+                    "mrOwner": base64(&body.mrowner),
+                    "mrOwnerConfig": base64(&body.mrownerconfig),
+                    "rtmrs": [
+                        base64(&body.rtmr0),
+                        base64(&body.rtmr1),
+                        base64(&body.rtmr2),
+                        base64(&body.rtmr3),
+                    ],
+                    "reportData": base64(&body.reportdata),
+                }),
+            );
+        }
+        output.insert(
+            "signedDataSize".into(),
+            self.signature_section_length().into(),
+        );
+
+        let mut certification_data = Map::new();
+        certification_data.insert(
+            "certificateDataType".into(),
+            self.certification_data.certification_data_type().into(),
+        );
+        certification_data.insert(
+            "size".into(),
+            certification_data_length(&self.certification_data).into(),
+        );
+        if let CertificationData::QeReportCertificationData(qe_report_certification_data) =
+            &self.certification_data
+        {
+            certification_data.insert(
+                "qeReportCertificationData".into(),
+                go_tdx_guest_qe_report_certification_data_json(qe_report_certification_data),
+            );
+        }
+        output.insert(
+            "signedData".into(),
+            json!({
+                "signature": base64(&self.signature.to_bytes()),
+                "ecdsaAttestationKey": base64(&self.attestation_key.to_raw_bytes()),
+                "certificationData": certification_data,
+            }),
+        );
+        Value::Object(output)
+    }
+}
+
+fn header_json(header: &QuoteHeader) -> Value {
+    json!({
+        "version": header.version,
+        "attestation_key_type": header.attestation_key_type.to_string(),
+        "tee_type": header.tee_type.to_string(),
+        "reserved1": Hex(&header.reserved1).to_string(),
+        "reserved2": Hex(&header.reserved2).to_string(),
+        "qe_vendor_id": Uuid(&header.qe_vendor_id).to_string(),
+        "user_data": Hex(&header.user_data).to_string(),
+    })
+}
+
+fn td_body_json(body: &QuoteBody) -> Value {
+    let mut output = Map::new();
+    let mut insert = |key: &str, value: Value| output.insert(key.into(), value);
+    insert("type", body.tdx_version.to_string().into());
+    insert("tee_tcb_svn", body.tee_tcb_svn.to_vec().into());
+    insert("tdx_module_svn", body.tee_tcb_svn[0].into());
+    insert("tdx_module_major_version", body.tee_tcb_svn[1].into());
+    insert("mrseam", Hex(&body.mrseam).to_string().into());
+    insert("mrsignerseam", Hex(&body.mrsignerseam).to_string().into());
+    insert(
+        "seamattributes",
+        Hex(&body.seamattributes).to_string().into(),
+    );
+    insert("tdattributes", Hex(&body.tdattributes).to_string().into());
+    insert(
+        "tdattributes_flags",
+        flags(u64::from_le_bytes(body.tdattributes), TD_ATTRIBUTES),
+    );
+    insert("xfam", Hex(&body.xfam).to_string().into());
+    insert("xfam_flags", flags(u64::from_le_bytes(body.xfam), XFAM));
+    insert("mrtd", Hex(&body.mrtd).to_string().into());
+    insert("mrconfigid", Hex(&body.mrconfigid).to_string().into());
+    insert("mrowner", Hex(&body.mrowner).to_string().into());
+    insert("mrownerconfig", Hex(&body.mrownerconfig).to_string().into());
+    insert("rtmr0", Hex(&body.rtmr0).to_string().into());
+    insert("rtmr1", Hex(&body.rtmr1).to_string().into());
+    insert("rtmr2", Hex(&body.rtmr2).to_string().into());
+    insert("rtmr3", Hex(&body.rtmr3).to_string().into());
+    insert("reportdata", Hex(&body.reportdata).to_string().into());
+    if let Some(tee_tcb_svn_2) = body.tee_tcb_svn_2 {
+        insert("tee_tcb_svn_2", tee_tcb_svn_2.to_vec().into());
+    }
+    if let Some(mrservicetd) = body.mrservicetd {
+        insert("mrservicetd", Hex(&mrservicetd).to_string().into());
+    }
+    Value::Object(output)
+}
+
+fn sgx_body_json(body: &SgxReportBody, body_type: Option<&str>) -> Value {
+    let (flags_bits, xfrm) = attributes_parts(&body.attributes);
+    let mut output = Map::new();
+    let mut insert = |key: &str, value: Value| output.insert(key.into(), value);
+    if let Some(body_type) = body_type {
+        insert("type", body_type.into());
+    }
+    insert("cpu_svn", body.cpu_svn.to_vec().into());
+    insert("misc_select", body.misc_select.into());
+    insert("reserved1", Hex(&body.reserved1).to_string().into());
+    insert(
+        "isv_ext_prod_id",
+        Hex(&body.isv_ext_prod_id).to_string().into(),
+    );
+    insert("attributes", Hex(&body.attributes).to_string().into());
+    insert("attributes_flags", flags(flags_bits, SGX_ATTRIBUTES));
+    insert("attributes_xfrm_flags", flags(xfrm, XFAM));
+    insert("mr_enclave", Hex(&body.mr_enclave).to_string().into());
+    insert("reserved2", Hex(&body.reserved2).to_string().into());
+    insert("mr_signer", Hex(&body.mr_signer).to_string().into());
+    insert("reserved3", Hex(&body.reserved3).to_string().into());
+    insert("config_id", Hex(&body.config_id).to_string().into());
+    insert("isv_prod_id", body.isv_prod_id.into());
+    insert("isv_svn", body.isv_svn.into());
+    insert("config_svn", body.config_svn.into());
+    insert("reserved4", Hex(&body.reserved4).to_string().into());
+    insert("isv_family_id", Hex(&body.isv_family_id).to_string().into());
+    insert("report_data", Hex(&body.report_data).to_string().into());
+    Value::Object(output)
+}
+
+fn certification_data_json(certification_data: &CertificationData) -> Value {
+    let certification_data_type = certification_data.certification_data_type();
+    match certification_data {
+        CertificationData::QeReportCertificationData(qe_report_certification_data) => {
+            let qe_report = match qe_report_certification_data.qe_report_body() {
+                Ok(qe_report) => sgx_body_json(&qe_report, None),
+                Err(_) => Hex(&qe_report_certification_data.qe_report)
+                    .to_string()
+                    .into(),
+            };
+            let nested = &qe_report_certification_data.certification_data;
+            json!({
+                "type": certification_data_type,
+                "name": certification_data_type_name(certification_data_type),
+                "qe_report": qe_report,
+                "qe_report_signature": Hex(&qe_report_certification_data.signature.to_bytes()).to_string(),
+                "qe_authentication_data": Hex(&qe_report_certification_data.qe_authentication_data).to_string(),
+                "certification_data": match nested_certification_data(nested) {
+                    Some((nested_type, data)) => certification_data_contents_json(nested_type, data),
+                    None => json!({ "data": Hex(nested).to_string() }),
+                },
+            })
+        }
+        CertificationData::PckIdPpidPlainCpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA2048CpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA3072CpusvnPcesvn(data)
+        | CertificationData::PckLeafCert(data)
+        | CertificationData::PckCertChain(data)
+        | CertificationData::PlatformManifest(data) => {
+            certification_data_contents_json(certification_data_type, data)
+        }
+    }
+}
+
+/// Certification data other than QE report certification data, with certificates given as PEM and
+/// other data as hex
+fn certification_data_contents_json(certification_data_type: i16, data: &[u8]) -> Value {
+    let mut output = Map::new();
+    output.insert("type".into(), certification_data_type.into());
+    output.insert(
+        "name".into(),
+        certification_data_type_name(certification_data_type).into(),
+    );
+    output.insert("size".into(), data.len().into());
+    match (certification_data_type, pem_certificates(data)) {
+        (4 | 5, Some(certificates)) => {
+            output.insert("pem_certificates".into(), certificates.into())
+        }
+        _ => output.insert("data".into(), Hex(data).to_string().into()),
+    };
+    Value::Object(output)
+}
+
+fn intel_header_json(header: &QuoteHeader) -> Value {
+    json!({
+        "version": header.version,
+        "att_key_type": attestation_key_type_value(&header.attestation_key_type),
+        "tee_type": tee_type_value(&header.tee_type),
+        "qe_svn": u16::from_le_bytes(header.reserved1),
+        "pce_svn": u16::from_le_bytes(header.reserved2),
+        "vendor_id": Hex(&header.qe_vendor_id).to_string(),
+        "user_data": Hex(&header.user_data).to_string(),
+    })
+}
+
+fn intel_td_body_json(body: &QuoteBody) -> Value {
+    let mut output = Map::new();
+    let mut insert = |key: &str, value: Value| output.insert(key.into(), value);
+    insert("tee_tcb_svn", Hex(&body.tee_tcb_svn).to_string().into());
+    insert("mr_seam", Hex(&body.mrseam).to_string().into());
+    insert("mrsigner_seam", Hex(&body.mrsignerseam).to_string().into());
+    insert(
+        "seam_attributes",
+        Hex(&body.seamattributes).to_string().into(),
+    );
+    insert("td_attributes", Hex(&body.tdattributes).to_string().into());
+    insert("xfam", Hex(&body.xfam).to_string().into());
+    insert("mr_td", Hex(&body.mrtd).to_string().into());
+    insert("mr_config_id", Hex(&body.mrconfigid).to_string().into());
+    insert("mr_owner", Hex(&body.mrowner).to_string().into());
+    insert(
+        "mr_owner_config",
+        Hex(&body.mrownerconfig).to_string().into(),
+    );
+    insert(
+        "rt_mr",
+        json!([
+            Hex(&body.rtmr0).to_string(),
+            Hex(&body.rtmr1).to_string(),
+            Hex(&body.rtmr2).to_string(),
+            Hex(&body.rtmr3).to_string(),
+        ]),
+    );
+    insert("report_data", Hex(&body.reportdata).to_string().into());
+    if let Some(tee_tcb_svn_2) = body.tee_tcb_svn_2 {
+        insert("tee_tcb_svn2", Hex(&tee_tcb_svn_2).to_string().into());
+    }
+    if let Some(mrservicetd) = body.mrservicetd {
+        insert("mr_servicetd", Hex(&mrservicetd).to_string().into());
+    }
+    Value::Object(output)
+}
+
+fn intel_sgx_body_json(body: &SgxReportBody) -> Value {
+    json!({
+        "cpu_svn": Hex(&body.cpu_svn).to_string(),
+        "misc_select": body.misc_select,
+        "reserved1": Hex(&body.reserved1).to_string(),
+        "isv_ext_prod_id": Hex(&body.isv_ext_prod_id).to_string(),
+        "attributes": Hex(&body.attributes).to_string(),
+        "mr_enclave": Hex(&body.mr_enclave).to_string(),
+        "reserved2": Hex(&body.reserved2).to_string(),
+        "mr_signer": Hex(&body.mr_signer).to_string(),
+        "reserved3": Hex(&body.reserved3).to_string(),
+        "config_id": Hex(&body.config_id).to_string(),
+        "isv_prod_id": body.isv_prod_id,
+        "isv_svn": body.isv_svn,
+        "config_svn": body.config_svn,
+        "reserved4": Hex(&body.reserved4).to_string(),
+        "isv_family_id": Hex(&body.isv_family_id).to_string(),
+        "report_data": Hex(&body.report_data).to_string(),
+    })
+}
+
+fn intel_certification_data_json(certification_data: &CertificationData) -> Value {
+    let certification_data_type = certification_data.certification_data_type();
+    let contents = match certification_data {
+        CertificationData::QeReportCertificationData(qe_report_certification_data) => {
+            let qe_report = match qe_report_certification_data.qe_report_body() {
+                Ok(qe_report) => intel_sgx_body_json(&qe_report),
+                Err(_) => Hex(&qe_report_certification_data.qe_report)
+                    .to_string()
+                    .into(),
+            };
+            let nested = &qe_report_certification_data.certification_data;
+            json!({
+                "qe_report": qe_report,
+                "qe_report_sig": Hex(&qe_report_certification_data.signature.to_bytes()).to_string(),
+                "auth_data": {
+                    "parsed_data_size": qe_report_certification_data.qe_authentication_data.len(),
+                    "data": Hex(&qe_report_certification_data.qe_authentication_data).to_string(),
+                },
+                "certification_data": match nested_certification_data(nested) {
+                    Some((nested_type, nested_data)) => json!({
+                        "cert_key_type": nested_type,
+                        "size": nested_data.len(),
+                        "certification_data": intel_certification_data_contents(
+                            nested_type,
+                            nested_data,
+                        ),
+                    }),
+                    None => Hex(nested).to_string().into(),
+                },
+            })
+        }
+        CertificationData::PckIdPpidPlainCpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA2048CpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA3072CpusvnPcesvn(data)
+        | CertificationData::PckLeafCert(data)
+        | CertificationData::PckCertChain(data)
+        | CertificationData::PlatformManifest(data) => {
+            intel_certification_data_contents(certification_data_type, data)
+        }
+    };
+    json!({
+        "cert_key_type": certification_data_type,
+        "size": certification_data_length(certification_data),
+        "certification_data": contents,
+    })
+}
+
+/// Certificates as PEM text, or other data as hex
+fn intel_certification_data_contents(certification_data_type: i16, data: &[u8]) -> Value {
+    match (certification_data_type, core::str::from_utf8(data)) {
+        (4 | 5, Ok(pem)) => pem.trim_end_matches('\0').into(),
+        _ => Hex(data).to_string().into(),
+    }
+}
+
+fn go_tdx_guest_qe_report_certification_data_json(
+    qe_report_certification_data: &QeReportCertificationData,
+) -> Value {
+    let qe_report = &qe_report_certification_data.qe_report;
+    let mut output = Map::new();
+    output.insert(
+        "qeReport".into(),
+        json!({
+            "cpuSvn": base64(&qe_report[0..16]),
+            "miscSelect": u32::from_le_bytes([qe_report[16], qe_report[17], qe_report[18], qe_report[19]]),
+            "reserved1": base64(&qe_report[20..48]),
+            "attributes": base64(&qe_report[48..64]),
+            "mrEnclave": base64(&qe_report[64..96]),
+            "reserved2": base64(&qe_report[96..128]),
+            "mrSigner": base64(&qe_report[128..160]),
+            "reserved3": base64(&qe_report[160..256]),
+            "isvProdId": u16::from_le_bytes([qe_report[256], qe_report[257]]),
+            "isvSvn": u16::from_le_bytes([qe_report[258], qe_report[259]]),
+            "reserved4": base64(&qe_report[260..320]),
+            "reportData": base64(&qe_report[320..384]),
+        }),
+    );
+    output.insert(
+        "qeReportSignature".into(),
+        base64(&qe_report_certification_data.signature.to_bytes()).into(),
+    );
+    output.insert(
+        "qeAuthData".into(),
+        json!({
+            "parsedDataSize": qe_report_certification_data.qe_authentication_data.len(),
+            "data": base64(&qe_report_certification_data.qe_authentication_data),
+        }),
+    );
+    if let Some((5, pck_cert_chain)) =
+        nested_certification_data(&qe_report_certification_data.certification_data)
+    {
+        output.insert(
+            "pckCertificateChainData".into(),
+            json!({
+                "certificateDataType": 5,
+                "size": pck_cert_chain.len(),
+                "pckCertChain": base64(pck_cert_chain),
+            }),
+        );
+    }
+    Value::Object(output)
+}
+
+/// Split the certification data nested within QE report certification data into its type and
+/// contents
+fn nested_certification_data(input: &[u8]) -> Option<(i16, &[u8])> {
+    let certification_data_type = i16::from_le_bytes(input.get(0..2)?.try_into().ok()?);
+    let length = i32::from_le_bytes(input.get(2..6)?.try_into().ok()?);
+    let data = input.get(6..6 + usize::try_from(length).ok()?)?;
+    Some((certification_data_type, data))
+}
+
+/// Split a PEM encoded certificate chain into certificates
+fn pem_certificates(data: &[u8]) -> Option<Vec<String>> {
+    const END: &str = "-----END CERTIFICATE-----";
+    let pem = core::str::from_utf8(data).ok()?;
+    let certificates: Vec<String> = pem
+        .split_inclusive(END)
+        .map(|certificate| certificate.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|certificate| !certificate.is_empty())
+        .map(String::from)
+        .collect();
+    certificates
+        .iter()
+        .all(|certificate| certificate.ends_with(END))
+        .then_some(certificates)
+}
+
+fn attestation_key_type_value(attestation_key_type: &AttestionKeyType) -> u16 {
+    match attestation_key_type {
+        AttestionKeyType::ECDSA256WithP256 => 2,
+        AttestionKeyType::ECDSA384WithP384 => 3,
+    }
+}
+
+fn tee_type_value(tee_type: &TEEType) -> u32 {
+    match tee_type {
+        TEEType::SGX => 0x00000000,
+        TEEType::TDX => 0x00000081,
+    }
+}
+
+/// Names of the bits of the TD attributes
+const TD_ATTRIBUTES: &[(u32, &str)] = &[
+    (0, "DEBUG"),
+    (27, "LASS"),
+    (28, "SEPT_VE_DISABLE"),
+    (29, "MIGRATABLE"),
+    (30, "PKS"),
+    (31, "KL"),
+    (63, "PERFMON"),
+];
+
+/// Names of the bits of the flags in SGX enclave attributes
+const SGX_ATTRIBUTES: &[(u32, &str)] = &[
+    (0, "INIT"),
+    (1, "DEBUG"),
+    (2, "MODE64BIT"),
+    (4, "PROVISIONKEY"),
+    (5, "EINITTOKEN_KEY"),
+    (6, "CET"),
+    (7, "KSS"),
+    (10, "AEXNOTIFY"),
+];
+
+/// Names of the bits of the extended feature set, used for XFAM and XFRM
+const XFAM: &[(u32, &str)] = &[
+    (0, "FP"),
+    (1, "SSE"),
+    (2, "AVX"),
+    (3, "MPX_BNDREGS"),
+    (4, "MPX_BNDCSR"),
+    (5, "AVX512_OPMASK"),
+    (6, "AVX512_ZMM_HI256"),
+    (7, "AVX512_HI16_ZMM"),
+    (8, "PT"),
+    (9, "PKRU"),
+    (10, "PASID"),
+    (11, "CET_U"),
+    (12, "CET_S"),
+    (13, "HDC"),
+    (14, "ULI"),
+    (15, "LBR"),
+    (16, "HWP"),
+    (17, "AMX_TILECFG"),
+    (18, "AMX_TILEDATA"),
+    (19, "APX"),
+];
+
+/// Split SGX attributes into the flags and the XFRM
+fn attributes_parts(attributes: &[u8; 16]) -> (u64, u64) {
+    let (flags, xfrm) = attributes.split_at(8);
+    (
+        u64::from_le_bytes(flags.try_into().unwrap_or_default()),
+        u64::from_le_bytes(xfrm.try_into().unwrap_or_default()),
+    )
+}
+
+/// List the names of the bits set, using `BIT<n>` for bits without a name
+fn flags(bits: u64, names: &[(u32, &str)]) -> Value {
+    (0..64)
+        .filter(|bit| bits & (1 << bit) != 0)
+        .map(
+            |bit| match names.iter().find(|(named_bit, _)| *named_bit == bit) {
+                Some((_, name)) => String::from(*name),
+                None => format!("BIT{bit}"),
+            },
+        )
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize],
+                ));
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
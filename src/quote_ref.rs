@@ -0,0 +1,598 @@
+//! Zero-copy parsing of quotes, borrowing fields from the input rather than copying them
+use crate::{
+    body_parser, check_v5_body_type, error::QuoteField, qe_report_certification_data_parser,
+    quote_header_parser, sgx_report_body_parser, AttestationKey, ParseOptions, QuoteBody,
+    QuoteHeader, QuoteParseError, QuoteSignature, QuoteVerificationError, SgxReportBody,
+    TDXVersion, QUOTE_HEADER_LENGTH,
+};
+#[cfg(feature = "alloc")]
+use crate::{AttestionKeyType, CertificationData, QeReportCertificationData, Quote, ReportBody};
+use nom::{
+    bytes::complete::take,
+    number::complete::{le_i16, le_i32},
+};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+
+/// A TDX or SGX quote which borrows from the input it was parsed from
+///
+/// This can be converted to an owned [Quote] with [QuoteRef::to_quote]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QuoteRef<'a> {
+    pub header: QuoteHeader,
+    pub body: ReportBodyRef<'a>,
+    /// The signature, as encoded in the quote
+    pub signature: &'a [u8],
+    /// The attestation key, uncompressed without the 0x04 prefix
+    pub attestation_key: &'a [u8],
+    pub certification_data: CertificationDataRef<'a>,
+    /// The header and body, which are signed by the attestation key
+    signed_data: &'a [u8],
+}
+
+impl<'a> QuoteRef<'a> {
+    /// Parse and validate a TDX or SGX quote, without copying
+    pub fn from_bytes(input: &'a [u8]) -> Result<Self, QuoteParseError> {
+        Self::from_bytes_with_options(input, &ParseOptions::default())
+    }
+
+    /// Parse and validate a TDX or SGX quote with the given options, without copying
+    pub fn from_bytes_with_options(
+        input: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<Self, QuoteParseError> {
+        let quote = Self::parse_unverified_with_options(input, options)?;
+        quote.verify_signature()?;
+        if quote.qe_report_certification_data().is_some() {
+            quote.verify_qe_binding()?;
+        }
+        Ok(quote)
+    }
+
+    /// Parse a TDX or SGX quote without verifying the signature or the QE report binding, so that
+    /// broken or tampered quotes can be inspected. [QuoteRef::verify_signature] and
+    /// [QuoteRef::verify_qe_binding] should be used before trusting its contents.
+    pub fn parse_unverified(input: &'a [u8]) -> Result<Self, QuoteParseError> {
+        Self::parse_unverified_with_options(input, &ParseOptions::default())
+    }
+
+    /// Parse a TDX or SGX quote with the given options, without verifying the signature or the
+    /// QE report binding
+    pub fn parse_unverified_with_options(
+        original_input: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<Self, QuoteParseError> {
+        let locate = |field| QuoteParseError::locate(original_input, field);
+
+        // Parse header
+        let (input, header) =
+            quote_header_parser(original_input).map_err(locate(QuoteField::Header))?;
+        if !header.attestation_key_type.is_supported() {
+            return Err(QuoteParseError::UnsupportedAttestationKeyType);
+        };
+        if !(3..=5).contains(&header.version) {
+            return Err(QuoteParseError::UnknownQuoteVersion(header.version));
+        }
+
+        // Parse body
+        check_v5_body_type(&header, input)?;
+        let (input, body) = body_parser(input, &header).map_err(locate(QuoteField::Body))?;
+
+        // Get signed data
+        let signed_data = &original_input[..original_input.len() - input.len()];
+
+        // Signature - P-256 signatures and keys are 64 bytes, P-384 are 96 bytes
+        let key_length = header.attestation_key_type.key_length();
+        let (input, signature_section_length) =
+            le_i32(input).map_err(locate(QuoteField::SignatureSection))?;
+        let (input, signature) =
+            take(key_length)(input).map_err(locate(QuoteField::SignatureSection))?;
+        let (input, attestation_key) =
+            take(key_length)(input).map_err(locate(QuoteField::SignatureSection))?;
+
+        // Certification data
+        let (remaining, certification_data) = if header.version == 3 {
+            // Version 3 quotes always contain QE report certification data, which is not preceded
+            // by a type and length
+            let qe_report_certification_data_len = usize::try_from(signature_section_length)?
+                .checked_sub(2 * key_length)
+                .ok_or(QuoteParseError::Parse {
+                    field: QuoteField::SignatureSection,
+                    offset: signed_data.len(),
+                })?;
+            if qe_report_certification_data_len > options.max_certification_data_len {
+                return Err(QuoteParseError::CertificationDataTooLong(
+                    qe_report_certification_data_len,
+                ));
+            }
+            let (input, qe_report_certification_data) =
+                take(qe_report_certification_data_len)(input)
+                    .map_err(locate(QuoteField::CertificationData(6)))?;
+            let (_, qe_report_certification_data) =
+                qe_report_certification_data_parser(qe_report_certification_data)
+                    .map_err(locate(QuoteField::CertificationData(6)))?;
+            (
+                input,
+                CertificationDataRef::QeReportCertificationData(qe_report_certification_data),
+            )
+        } else {
+            let (input, certification_data_type) =
+                le_i16(input).map_err(locate(QuoteField::SignatureSection))?;
+            let (input, certification_dat_len) =
+                le_i32(input).map_err(locate(QuoteField::SignatureSection))?;
+            let certification_dat_len: usize = certification_dat_len.try_into()?;
+            if certification_dat_len > options.max_certification_data_len {
+                return Err(QuoteParseError::CertificationDataTooLong(
+                    certification_dat_len,
+                ));
+            }
+            let (remaining, certification_data) = take(certification_dat_len)(input).map_err(
+                locate(QuoteField::CertificationData(certification_data_type)),
+            )?;
+            (
+                remaining,
+                CertificationDataRef::new(certification_data_type, certification_data)
+                    .map_err(|error| error.offset_by(original_input.len() - input.len()))?,
+            )
+        };
+
+        let quote = QuoteRef {
+            header,
+            body,
+            signature,
+            attestation_key,
+            certification_data,
+            signed_data,
+        };
+        if options.strict {
+            quote.check_canonical(original_input, remaining, signature_section_length, options)?;
+        }
+        Ok(quote)
+    }
+
+    /// Check that a parsed quote is in canonical form, given the input it was parsed from and
+    /// the bytes which remain after parsing
+    fn check_canonical(
+        &self,
+        original_input: &[u8],
+        remaining: &[u8],
+        signature_section_length: i32,
+        options: &ParseOptions,
+    ) -> Result<(), QuoteParseError> {
+        // Version 3 quotes use the reserved header fields for security version numbers
+        if self.header.version > 3 {
+            if self.header.reserved1 != [0; 2] {
+                return Err(QuoteParseError::NonZeroReserved { offset: 8 });
+            }
+            if self.header.reserved2 != [0; 2] {
+                return Err(QuoteParseError::NonZeroReserved { offset: 10 });
+            }
+        }
+
+        if let ReportBodyRef::Sgx(body) = &self.body {
+            for reserved in [
+                &body.reserved1[..],
+                &body.reserved2[..],
+                &body.reserved3[..],
+                &body.reserved4[..],
+            ] {
+                if reserved.iter().any(|byte| *byte != 0) {
+                    return Err(QuoteParseError::NonZeroReserved {
+                        offset: offset_of(original_input, reserved),
+                    });
+                }
+            }
+        }
+
+        // Version 5 quotes give the size of the body following the body type and size
+        if self.header.version == 5 {
+            let body_size = u32::from_le_bytes(
+                original_input[QUOTE_HEADER_LENGTH + 2..QUOTE_HEADER_LENGTH + 6]
+                    .try_into()
+                    .map_err(|_| QuoteParseError::InconsistentLength(QuoteField::Body))?,
+            );
+            if usize::try_from(body_size)? != self.signed_data.len() - QUOTE_HEADER_LENGTH - 6 {
+                return Err(QuoteParseError::InconsistentLength(QuoteField::Body));
+            }
+        }
+
+        // The signature section length is followed by the rest of the quote
+        let signature_section_start = self.signed_data.len() + 4;
+        if usize::try_from(signature_section_length)?
+            != original_input.len() - remaining.len() - signature_section_start
+        {
+            return Err(QuoteParseError::InconsistentLength(
+                QuoteField::SignatureSection,
+            ));
+        }
+
+        // QE report certification data contains further certification data, with its own type
+        // and length
+        if let Some(qe_report_certification_data) = self.qe_report_certification_data() {
+            let nested = qe_report_certification_data.certification_data;
+            let (nested, nested_type) = le_i16(nested).map_err(QuoteParseError::locate(
+                original_input,
+                QuoteField::CertificationData(6),
+            ))?;
+            let (nested, nested_length) = le_i32(nested).map_err(QuoteParseError::locate(
+                original_input,
+                QuoteField::CertificationData(6),
+            ))?;
+            if usize::try_from(nested_length)? != nested.len() {
+                return Err(QuoteParseError::InconsistentLength(
+                    QuoteField::CertificationData(nested_type),
+                ));
+            }
+        }
+
+        let zero_padded = options.allow_zero_padding && remaining.iter().all(|byte| *byte == 0);
+        if !remaining.is_empty() && !zero_padded {
+            return Err(QuoteParseError::TrailingBytes {
+                offset: original_input.len() - remaining.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Verify that the header and body are signed by the attestation key
+    pub fn verify_signature(&self) -> Result<(), QuoteVerificationError> {
+        let attestation_key = self
+            .decode_attestation_key()
+            .map_err(|_| QuoteVerificationError::BadAttestationKey)?;
+        let signature = self
+            .decode_signature()
+            .map_err(|_| QuoteVerificationError::BadAttestationKeySignature)?;
+        attestation_key.verify(self.signed_data, &signature)
+    }
+
+    /// Verify that the QE report contains the hash of the attestation key, so that a valid
+    /// signature of the QE report from the PCK also certifies the attestation key
+    pub fn verify_qe_binding(&self) -> Result<(), QuoteVerificationError> {
+        let qe_report_certification_data = self
+            .qe_report_certification_data()
+            .ok_or(QuoteVerificationError::NoQeReportCertificationData)?;
+        let attestation_key = self
+            .decode_attestation_key()
+            .map_err(|_| QuoteVerificationError::BadAttestationKey)?;
+        qe_report_certification_data.check_attestation_key(&attestation_key)
+    }
+
+    /// Returns the header and body, which are signed by the attestation key
+    pub fn signed_data(&self) -> &'a [u8] {
+        self.signed_data
+    }
+
+    /// Decode the signature made with the attestation key
+    pub fn decode_signature(&self) -> Result<QuoteSignature, QuoteParseError> {
+        QuoteSignature::from_raw_bytes(&self.header.attestation_key_type, self.signature)
+    }
+
+    /// Decode the attestation key
+    pub fn decode_attestation_key(&self) -> Result<AttestationKey, QuoteParseError> {
+        AttestationKey::from_raw_bytes(&self.header.attestation_key_type, self.attestation_key)
+    }
+
+    /// Returns the report data
+    pub fn report_input_data(&self) -> &'a [u8; 64] {
+        match &self.body {
+            ReportBodyRef::Td(body) => body.reportdata,
+            ReportBodyRef::Sgx(body) => body.report_data,
+        }
+    }
+
+    /// Returns the build-time measurement register, if this is a TDX quote
+    pub fn mrtd(&self) -> Option<&'a [u8; 48]> {
+        self.td_body().map(|body| body.mrtd)
+    }
+
+    /// Returns the TD report, if this is a TDX quote
+    pub fn td_body(&self) -> Option<&QuoteBodyRef<'a>> {
+        match &self.body {
+            ReportBodyRef::Td(body) => Some(body),
+            ReportBodyRef::Sgx(_) => None,
+        }
+    }
+
+    /// Returns the enclave report, if this is an SGX quote
+    pub fn sgx_body(&self) -> Option<&SgxReportBodyRef<'a>> {
+        match &self.body {
+            ReportBodyRef::Sgx(body) => Some(body),
+            ReportBodyRef::Td(_) => None,
+        }
+    }
+
+    /// Returns the QeReportCertificationData if present
+    pub fn qe_report_certification_data(&self) -> Option<&QeReportCertificationDataRef<'a>> {
+        if let CertificationDataRef::QeReportCertificationData(qe_report_certification_data) =
+            &self.certification_data
+        {
+            Some(qe_report_certification_data)
+        } else {
+            None
+        }
+    }
+
+    /// Attempt to verify the report with a given provisioning certification key (PCK)
+    pub fn verify_with_pck(&self, pck: VerifyingKey) -> Result<(), QuoteVerificationError> {
+        let qe_report_certification_data = self
+            .qe_report_certification_data()
+            .ok_or(QuoteVerificationError::NoQeReportCertificationData)?;
+        let signature = Signature::from_bytes(qe_report_certification_data.signature.into())?;
+        pck.verify(&qe_report_certification_data.qe_report[..], &signature)?;
+        Ok(())
+    }
+
+    /// Copy into an owned [Quote]. A signature or attestation key which cannot be decoded is kept
+    /// as raw bytes.
+    #[cfg(feature = "alloc")]
+    pub fn to_quote(&self) -> Result<Quote, QuoteParseError> {
+        Ok(Quote {
+            header: self.header.clone(),
+            body: (&self.body).into(),
+            signature: QuoteSignature::from_raw_bytes_lenient(
+                &self.header.attestation_key_type,
+                self.signature,
+            )?,
+            attestation_key: AttestationKey::from_raw_bytes_lenient(
+                &self.header.attestation_key_type,
+                self.attestation_key,
+            )?,
+            certification_data: (&self.certification_data).try_into()?,
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&QuoteRef<'_>> for Quote {
+    type Error = QuoteParseError;
+
+    fn try_from(quote: &QuoteRef<'_>) -> Result<Quote, QuoteParseError> {
+        quote.to_quote()
+    }
+}
+
+/// The body of a quote, borrowed from the input
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReportBodyRef<'a> {
+    /// A TD report, from a TDX quote
+    Td(QuoteBodyRef<'a>),
+    /// An enclave report, from an SGX quote
+    Sgx(SgxReportBodyRef<'a>),
+}
+
+#[cfg(feature = "alloc")]
+impl From<&ReportBodyRef<'_>> for ReportBody {
+    fn from(body: &ReportBodyRef<'_>) -> ReportBody {
+        match body {
+            ReportBodyRef::Td(body) => ReportBody::Td(body.into()),
+            ReportBodyRef::Sgx(body) => ReportBody::Sgx(body.into()),
+        }
+    }
+}
+
+/// A TDX quote body, borrowed from the input
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct QuoteBodyRef<'a> {
+    pub tdx_version: TDXVersion,
+    pub tee_tcb_svn: &'a [u8; 16],
+    pub mrseam: &'a [u8; 48],
+    pub mrsignerseam: &'a [u8; 48],
+    pub seamattributes: &'a [u8; 8],
+    pub tdattributes: &'a [u8; 8],
+    pub xfam: &'a [u8; 8],
+    /// Build-time measurement
+    pub mrtd: &'a [u8; 48],
+    pub mrconfigid: &'a [u8; 48],
+    pub mrowner: &'a [u8; 48],
+    pub mrownerconfig: &'a [u8; 48],
+    /// Runtime extendable measurement register
+    pub rtmr0: &'a [u8; 48],
+    pub rtmr1: &'a [u8; 48],
+    pub rtmr2: &'a [u8; 48],
+    pub rtmr3: &'a [u8; 48],
+    /// User defined input data
+    pub reportdata: &'a [u8; 64],
+    /// Optional as only for TDX 1.5
+    pub tee_tcb_svn_2: Option<&'a [u8; 16]>,
+    /// Optional as only for TDX 1.5
+    pub mrservicetd: Option<&'a [u8; 48]>,
+}
+
+impl From<&QuoteBodyRef<'_>> for QuoteBody {
+    fn from(body: &QuoteBodyRef<'_>) -> QuoteBody {
+        QuoteBody {
+            tdx_version: body.tdx_version.clone(),
+            tee_tcb_svn: *body.tee_tcb_svn,
+            mrseam: *body.mrseam,
+            mrsignerseam: *body.mrsignerseam,
+            seamattributes: *body.seamattributes,
+            tdattributes: *body.tdattributes,
+            xfam: *body.xfam,
+            mrtd: *body.mrtd,
+            mrconfigid: *body.mrconfigid,
+            mrowner: *body.mrowner,
+            mrownerconfig: *body.mrownerconfig,
+            rtmr0: *body.rtmr0,
+            rtmr1: *body.rtmr1,
+            rtmr2: *body.rtmr2,
+            rtmr3: *body.rtmr3,
+            reportdata: *body.reportdata,
+            tee_tcb_svn_2: body.tee_tcb_svn_2.copied(),
+            mrservicetd: body.mrservicetd.copied(),
+        }
+    }
+}
+
+/// An SGX enclave report body, borrowed from the input
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SgxReportBodyRef<'a> {
+    pub cpu_svn: &'a [u8; 16],
+    pub misc_select: u32,
+    pub reserved1: &'a [u8; 12],
+    pub isv_ext_prod_id: &'a [u8; 16],
+    pub attributes: &'a [u8; 16],
+    /// Measurement of the enclave
+    pub mr_enclave: &'a [u8; 32],
+    pub reserved2: &'a [u8; 32],
+    /// Hash of the key used to sign the enclave
+    pub mr_signer: &'a [u8; 32],
+    pub reserved3: &'a [u8; 32],
+    pub config_id: &'a [u8; 64],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub config_svn: u16,
+    pub reserved4: &'a [u8; 42],
+    pub isv_family_id: &'a [u8; 16],
+    /// User defined input data
+    pub report_data: &'a [u8; 64],
+}
+
+impl From<&SgxReportBodyRef<'_>> for SgxReportBody {
+    fn from(body: &SgxReportBodyRef<'_>) -> SgxReportBody {
+        SgxReportBody {
+            cpu_svn: *body.cpu_svn,
+            misc_select: body.misc_select,
+            reserved1: *body.reserved1,
+            isv_ext_prod_id: *body.isv_ext_prod_id,
+            attributes: *body.attributes,
+            mr_enclave: *body.mr_enclave,
+            reserved2: *body.reserved2,
+            mr_signer: *body.mr_signer,
+            reserved3: *body.reserved3,
+            config_id: *body.config_id,
+            isv_prod_id: body.isv_prod_id,
+            isv_svn: body.isv_svn,
+            config_svn: body.config_svn,
+            reserved4: *body.reserved4,
+            isv_family_id: *body.isv_family_id,
+            report_data: *body.report_data,
+        }
+    }
+}
+
+/// Data related to certifying the QE Report, borrowed from the input
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificationDataRef<'a> {
+    PckIdPpidPlainCpusvnPcesvn(&'a [u8]),
+    PckIdPpidRSA2048CpusvnPcesvn(&'a [u8]),
+    PckIdPpidRSA3072CpusvnPcesvn(&'a [u8]),
+    PckLeafCert(&'a [u8]),
+    PckCertChain(&'a [u8]),
+    QeReportCertificationData(QeReportCertificationDataRef<'a>),
+    PlatformManifest(&'a [u8]),
+}
+
+impl<'a> CertificationDataRef<'a> {
+    /// Parse certification data of the given type
+    pub fn new(certification_data_type: i16, data: &'a [u8]) -> Result<Self, QuoteParseError> {
+        match certification_data_type {
+            1 => Ok(Self::PckIdPpidPlainCpusvnPcesvn(data)),
+            2 => Ok(Self::PckIdPpidRSA2048CpusvnPcesvn(data)),
+            3 => Ok(Self::PckIdPpidRSA3072CpusvnPcesvn(data)),
+            4 => Ok(Self::PckLeafCert(data)),
+            5 => Ok(Self::PckCertChain(data)),
+            6 => Ok(Self::QeReportCertificationData(
+                qe_report_certification_data_parser(data)
+                    .map_err(QuoteParseError::locate(
+                        data,
+                        QuoteField::CertificationData(6),
+                    ))?
+                    .1,
+            )),
+            7 => Ok(Self::PlatformManifest(data)),
+            _ => Err(QuoteParseError::UnknownCertificationDataType(
+                certification_data_type,
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&CertificationDataRef<'_>> for CertificationData {
+    type Error = QuoteParseError;
+
+    fn try_from(
+        certification_data: &CertificationDataRef<'_>,
+    ) -> Result<CertificationData, QuoteParseError> {
+        Ok(match certification_data {
+            CertificationDataRef::PckIdPpidPlainCpusvnPcesvn(data) => {
+                Self::PckIdPpidPlainCpusvnPcesvn(data.to_vec())
+            }
+            CertificationDataRef::PckIdPpidRSA2048CpusvnPcesvn(data) => {
+                Self::PckIdPpidRSA2048CpusvnPcesvn(data.to_vec())
+            }
+            CertificationDataRef::PckIdPpidRSA3072CpusvnPcesvn(data) => {
+                Self::PckIdPpidRSA3072CpusvnPcesvn(data.to_vec())
+            }
+            CertificationDataRef::PckLeafCert(data) => Self::PckLeafCert(data.to_vec()),
+            CertificationDataRef::PckCertChain(data) => Self::PckCertChain(data.to_vec()),
+            CertificationDataRef::QeReportCertificationData(qe_report_certification_data) => {
+                Self::QeReportCertificationData(qe_report_certification_data.try_into()?)
+            }
+            CertificationDataRef::PlatformManifest(data) => Self::PlatformManifest(data.to_vec()),
+        })
+    }
+}
+
+/// Certification data which contains a signature from the PCK, borrowed from the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QeReportCertificationDataRef<'a> {
+    /// The report data of this should contain SHA256(attestation_public_key || QE authentication data)
+    /// followed by null bytes. For P-384 attestation keys, SHA384 is used.
+    pub qe_report: &'a [u8; 384],
+    /// Signature of the qe_report field made using the PCK key
+    pub signature: &'a [u8; 64],
+    /// Authentication data used by the quoting enclave to provide additional context
+    pub qe_authentication_data: &'a [u8],
+    /// Data required to verify the QE report signature
+    pub certification_data: &'a [u8],
+}
+
+impl<'a> QeReportCertificationDataRef<'a> {
+    /// Decode the QE report, which has the format of an SGX enclave report
+    pub fn qe_report_body(&self) -> Result<SgxReportBodyRef<'a>, QuoteParseError> {
+        let (_input, body) = sgx_report_body_parser(self.qe_report).map_err(
+            QuoteParseError::locate(self.qe_report, QuoteField::QeReport),
+        )?;
+        Ok(body)
+    }
+
+    /// Check that the QE report data contains the hash of the given attestation key
+    pub(crate) fn check_attestation_key(
+        &self,
+        attestation_key: &AttestationKey,
+    ) -> Result<(), QuoteVerificationError> {
+        // The last part of the qe_report is the report data, which begins with the hash of the
+        // attestation key and authentication data, followed by null bytes (which we ignore)
+        if !attestation_key
+            .matches_qe_report_data(self.qe_authentication_data, &self.qe_report[384 - 64..])
+        {
+            return Err(QuoteVerificationError::AttestationKeyDoesNotMatch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&QeReportCertificationDataRef<'_>> for QeReportCertificationData {
+    type Error = QuoteParseError;
+
+    fn try_from(
+        qe_report_certification_data: &QeReportCertificationDataRef<'_>,
+    ) -> Result<QeReportCertificationData, QuoteParseError> {
+        Ok(QeReportCertificationData {
+            qe_report: *qe_report_certification_data.qe_report,
+            signature: QuoteSignature::from_raw_bytes_lenient(
+                &AttestionKeyType::ECDSA256WithP256,
+                qe_report_certification_data.signature,
+            )?,
+            qe_authentication_data: qe_report_certification_data.qe_authentication_data.to_vec(),
+            certification_data: qe_report_certification_data.certification_data.to_vec(),
+        })
+    }
+}
+
+/// The offset of part of the input from its start
+fn offset_of(input: &[u8], part: &[u8]) -> usize {
+    (part.as_ptr() as usize).saturating_sub(input.as_ptr() as usize)
+}
@@ -0,0 +1,130 @@
+//! Bind typed values such as nonces and public keys to a quote's report data
+#[cfg(feature = "alloc")]
+use crate::{error::QuoteVerificationError, Quote};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+
+/// Builds the 64 bytes of report data from typed components, so that a quote can be shown to
+/// bind them.
+///
+/// Without a domain, the report data is the SHA-512 hash of the concatenated components, for
+/// example `SHA-512(nonce || public key)`. With a domain, the hash input begins with the domain
+/// tag and a version number, and each component is prefixed with its length, so that the report
+/// data cannot be confused with that of another protocol.
+#[derive(Debug, Clone)]
+pub struct ReportDataBinding {
+    hasher: Sha512,
+    domain_separated: bool,
+}
+
+impl ReportDataBinding {
+    /// Create a binding with the SHA-512 hash of the concatenated components
+    pub fn new() -> Self {
+        Self {
+            hasher: Sha512::new(),
+            domain_separated: false,
+        }
+    }
+
+    /// Create a domain separated binding with the given domain tag and version
+    pub fn with_domain(domain: &[u8], version: u16) -> Self {
+        let mut binding = Self {
+            hasher: Sha512::new(),
+            domain_separated: true,
+        };
+        binding.update(domain);
+        binding.hasher.update(version.to_le_bytes());
+        binding
+    }
+
+    /// Convenience function for the common case `SHA-512(nonce || public key)`
+    pub fn nonce_and_public_key(nonce: &[u8], public_key: &[u8]) -> Self {
+        Self::new().nonce(nonce).public_key(public_key)
+    }
+
+    /// Add a nonce, for example one given by the verifier to ensure freshness
+    pub fn nonce(self, nonce: &[u8]) -> Self {
+        self.component(nonce)
+    }
+
+    /// Add an encoded public key, for example a key belonging to the attested TD
+    pub fn public_key(self, public_key: &[u8]) -> Self {
+        self.component(public_key)
+    }
+
+    /// Add some other data to be bound to the quote
+    pub fn component(mut self, data: &[u8]) -> Self {
+        self.update(data);
+        self
+    }
+
+    /// Get the report data to be given when requesting a quote
+    pub fn to_report_data(&self) -> [u8; 64] {
+        self.hasher.clone().finalize().into()
+    }
+
+    /// Check, in constant time, whether the given report data matches
+    pub fn matches(&self, reportdata: &[u8; 64]) -> bool {
+        self.to_report_data().ct_eq(reportdata).into()
+    }
+
+    /// Check that the given quote binds the components of this binding
+    #[cfg(feature = "alloc")]
+    pub fn verify(&self, quote: &Quote) -> Result<(), QuoteVerificationError> {
+        if self.matches(&quote.report_input_data()) {
+            Ok(())
+        } else {
+            Err(QuoteVerificationError::ReportDataMismatch)
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        if self.domain_separated {
+            self.hasher.update((data.len() as u64).to_le_bytes());
+        }
+        self.hasher.update(data);
+    }
+}
+
+impl Default for ReportDataBinding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&ReportDataBinding> for [u8; 64] {
+    fn from(binding: &ReportDataBinding) -> [u8; 64] {
+        binding.to_report_data()
+    }
+}
+
+impl From<ReportDataBinding> for [u8; 64] {
+    fn from(binding: ReportDataBinding) -> [u8; 64] {
+        binding.to_report_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_data_binding() {
+        let binding = ReportDataBinding::nonce_and_public_key(b"nonce", b"key");
+        let expected: [u8; 64] = Sha512::digest(b"noncekey").into();
+        assert_eq!(binding.to_report_data(), expected);
+        assert!(binding.matches(&expected));
+        assert!(!ReportDataBinding::nonce_and_public_key(b"other", b"key").matches(&expected));
+
+        // With a domain, moving bytes between components gives different report data
+        let domain = ReportDataBinding::with_domain(b"my-protocol", 1);
+        assert_ne!(
+            domain
+                .clone()
+                .nonce(b"nonce")
+                .public_key(b"key")
+                .to_report_data(),
+            domain.nonce(b"nonc").public_key(b"ekey").to_report_data()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Simulate runtime extendable measurement registers (RTMRs)
+//!
+//! This allows the expected values of `rtmr0`..`rtmr3` to be computed ahead of time, for example
+//! as part of a build pipeline, so that they can be compared with those in a [QuoteBody].
+use crate::QuoteBody;
+use sha2::{Digest, Sha384};
+
+/// A runtime extendable measurement register
+///
+/// Registers start zeroed, and are extended with
+/// `RTMR[new] = SHA384(RTMR[old] || digest)`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rtmr([u8; 48]);
+
+impl Rtmr {
+    /// Create a register with the initial (all zero) value
+    pub fn new() -> Self {
+        Self([0; 48])
+    }
+
+    /// Create a register with a given value, for example one taken from a quote
+    pub fn from_value(value: [u8; 48]) -> Self {
+        Self(value)
+    }
+
+    /// Extend the register with a SHA384 digest
+    pub fn extend(&mut self, digest: &[u8; 48]) {
+        let mut hasher = Sha384::new();
+        hasher.update(self.0);
+        hasher.update(digest);
+        self.0 = hasher.finalize().into();
+    }
+
+    /// Hash the given data with SHA384, and extend the register with the digest
+    pub fn extend_data(&mut self, data: &[u8]) {
+        self.extend(&Sha384::digest(data).into());
+    }
+
+    /// Returns the current value of the register
+    pub fn value(&self) -> [u8; 48] {
+        self.0
+    }
+}
+
+impl Default for Rtmr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Rtmr> for [u8; 48] {
+    fn from(rtmr: Rtmr) -> [u8; 48] {
+        rtmr.0
+    }
+}
+
+/// One of the four runtime extendable measurement registers of a TD
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RtmrIndex {
+    Rtmr0,
+    Rtmr1,
+    Rtmr2,
+    Rtmr3,
+}
+
+/// The set of four runtime extendable measurement registers of a TD
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RtmrSet {
+    pub rtmr0: Rtmr,
+    pub rtmr1: Rtmr,
+    pub rtmr2: Rtmr,
+    pub rtmr3: Rtmr,
+}
+
+impl RtmrSet {
+    /// Create a set of registers with their initial (all zero) values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the register values from a quote body
+    pub fn from_quote_body(body: &QuoteBody) -> Self {
+        Self {
+            rtmr0: Rtmr::from_value(body.rtmr0),
+            rtmr1: Rtmr::from_value(body.rtmr1),
+            rtmr2: Rtmr::from_value(body.rtmr2),
+            rtmr3: Rtmr::from_value(body.rtmr3),
+        }
+    }
+
+    /// Returns the register with the given index (0-3)
+    pub fn get(&self, index: usize) -> Option<&Rtmr> {
+        match index {
+            0 => Some(&self.rtmr0),
+            1 => Some(&self.rtmr1),
+            2 => Some(&self.rtmr2),
+            3 => Some(&self.rtmr3),
+            _ => None,
+        }
+    }
+
+    /// Returns the register with the given index (0-3) mutably, so that it can be extended
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Rtmr> {
+        match index {
+            0 => Some(&mut self.rtmr0),
+            1 => Some(&mut self.rtmr1),
+            2 => Some(&mut self.rtmr2),
+            3 => Some(&mut self.rtmr3),
+            _ => None,
+        }
+    }
+
+    /// Returns the given register
+    pub fn register(&self, index: RtmrIndex) -> &Rtmr {
+        match index {
+            RtmrIndex::Rtmr0 => &self.rtmr0,
+            RtmrIndex::Rtmr1 => &self.rtmr1,
+            RtmrIndex::Rtmr2 => &self.rtmr2,
+            RtmrIndex::Rtmr3 => &self.rtmr3,
+        }
+    }
+
+    /// Returns the given register mutably, so that it can be extended
+    pub fn register_mut(&mut self, index: RtmrIndex) -> &mut Rtmr {
+        match index {
+            RtmrIndex::Rtmr0 => &mut self.rtmr0,
+            RtmrIndex::Rtmr1 => &mut self.rtmr1,
+            RtmrIndex::Rtmr2 => &mut self.rtmr2,
+            RtmrIndex::Rtmr3 => &mut self.rtmr3,
+        }
+    }
+
+    /// Whether all four registers match those in the given quote body
+    pub fn matches(&self, body: &QuoteBody) -> bool {
+        *self == Self::from_quote_body(body)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Decode a measurement or digest from hex
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_hex<const N: usize>(input: &str) -> [u8; N] {
+        let mut output = [0; N];
+        for (i, byte) in output.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&input[2 * i..2 * i + 2], 16).unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut rtmr = Rtmr::new();
+        rtmr.extend_data(b"some event");
+
+        let digest: [u8; 48] = Sha384::digest(b"some event").into();
+        let mut hasher = Sha384::new();
+        hasher.update([0; 48]);
+        hasher.update(digest);
+        let expected: [u8; 48] = hasher.finalize().into();
+        assert_eq!(rtmr.value(), expected);
+
+        let mut other = Rtmr::new();
+        other.extend(&digest);
+        assert_eq!(rtmr, other);
+    }
+}
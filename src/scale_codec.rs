@@ -0,0 +1,386 @@
+//! SCALE encoding and type information, for handling quotes in a Substrate runtime
+//!
+//! Most types derive these directly. Those containing keys, signatures, offsets or variable length
+//! data are converted to and from a private encoded form, which bounds the length of the data.
+use crate::{
+    parse_options::STRICT_MAX_CERTIFICATION_DATA_LEN, AttestationKey, AttestionKeyType,
+    CertificationData, QeReportCertificationData, QuoteParseError, QuoteSignature,
+};
+use alloc::vec::Vec;
+use codec::{Compact, Decode, Encode, EncodeLike, Error, Input, MaxEncodedLen, Output};
+use scale_info::{Path, Type, TypeInfo};
+
+/// The maximum length of each variable length field of certification data
+const MAX_BYTES_LEN: u32 = STRICT_MAX_CERTIFICATION_DATA_LEN as u32;
+
+/// Implement the codec traits and type information for a type by converting it to and from its
+/// encoded form
+macro_rules! impl_via_encoded {
+    ($ty:ident) => {
+        impl Encode for $ty {
+            fn size_hint(&self) -> usize {
+                encoded::$ty::from(self).size_hint()
+            }
+
+            fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+                encoded::$ty::from(self).encode_to(dest)
+            }
+        }
+
+        impl EncodeLike for $ty {}
+
+        impl Decode for $ty {
+            fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+                encoded::$ty::decode(input)?.try_into()
+            }
+        }
+
+        impl MaxEncodedLen for $ty {
+            fn max_encoded_len() -> usize {
+                encoded::$ty::max_encoded_len()
+            }
+        }
+
+        impl TypeInfo for $ty {
+            type Identity = Self;
+
+            fn type_info() -> Type {
+                let mut type_info = encoded::$ty::type_info();
+                type_info.path = Path::new(stringify!($ty), "tdx_quote");
+                type_info
+            }
+        }
+    };
+}
+
+impl_via_encoded!(AttestationKey);
+impl_via_encoded!(QuoteSignature);
+impl_via_encoded!(CertificationData);
+impl_via_encoded!(QeReportCertificationData);
+impl_via_encoded!(QuoteParseError);
+
+/// Variable length bytes with a bounded length, encoded in the same way as `Vec<u8>`
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct BoundedBytes(Vec<u8>);
+
+impl Encode for BoundedBytes {
+    fn size_hint(&self) -> usize {
+        self.0.size_hint()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        self.0.encode_to(dest)
+    }
+}
+
+impl Decode for BoundedBytes {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let Compact(len) = Compact::<u32>::decode(input)?;
+        if len > MAX_BYTES_LEN {
+            return Err("Certification data is longer than the maximum allowed length".into());
+        }
+        let mut bytes = alloc::vec![0; len as usize];
+        input.read(&mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl MaxEncodedLen for BoundedBytes {
+    fn max_encoded_len() -> usize {
+        Compact::<u32>::max_encoded_len() + MAX_BYTES_LEN as usize
+    }
+}
+
+impl TypeInfo for BoundedBytes {
+    type Identity = Vec<u8>;
+
+    fn type_info() -> Type {
+        Vec::<u8>::type_info()
+    }
+}
+
+impl From<&Vec<u8>> for BoundedBytes {
+    fn from(bytes: &Vec<u8>) -> Self {
+        Self(bytes.clone())
+    }
+}
+
+/// The encoded forms of types which cannot derive the codec traits
+mod encoded {
+    use super::BoundedBytes;
+    use crate::{QuoteField, TEEType};
+    use codec::{Decode, Encode, MaxEncodedLen};
+    use scale_info::TypeInfo;
+
+    #[derive(Encode, Decode, MaxEncodedLen, TypeInfo)]
+    pub(super) enum AttestationKey {
+        P256([u8; 64]),
+        #[cfg(feature = "p384")]
+        P384([u8; 96]),
+    }
+
+    #[derive(Encode, Decode, MaxEncodedLen, TypeInfo)]
+    pub(super) enum QuoteSignature {
+        P256([u8; 64]),
+        #[cfg(feature = "p384")]
+        P384([u8; 96]),
+    }
+
+    #[allow(clippy::large_enum_variant)]
+    #[derive(Encode, Decode, MaxEncodedLen, TypeInfo)]
+    pub(super) enum CertificationData {
+        #[codec(index = 1)]
+        PckIdPpidPlainCpusvnPcesvn(BoundedBytes),
+        #[codec(index = 2)]
+        PckIdPpidRSA2048CpusvnPcesvn(BoundedBytes),
+        #[codec(index = 3)]
+        PckIdPpidRSA3072CpusvnPcesvn(BoundedBytes),
+        #[codec(index = 4)]
+        PckLeafCert(BoundedBytes),
+        #[codec(index = 5)]
+        PckCertChain(BoundedBytes),
+        #[codec(index = 6)]
+        QeReportCertificationData(QeReportCertificationData),
+        #[codec(index = 7)]
+        PlatformManifest(BoundedBytes),
+    }
+
+    #[derive(Encode, Decode, MaxEncodedLen, TypeInfo)]
+    pub(super) struct QeReportCertificationData {
+        pub(super) qe_report: [u8; 384],
+        pub(super) signature: QuoteSignature,
+        pub(super) qe_authentication_data: BoundedBytes,
+        pub(super) certification_data: BoundedBytes,
+    }
+
+    /// Offsets are encoded as 64 bit integers, as `usize` has no fixed encoding
+    #[derive(Encode, Decode, MaxEncodedLen, TypeInfo)]
+    pub(super) enum QuoteParseError {
+        Parse { field: QuoteField, offset: u64 },
+        Verification,
+        MalformedAttestationKey,
+        MalformedSignature,
+        UnknownCertificationDataType(i16),
+        UnknownQuoteVersion(u16),
+        UnknownBodyType(u16),
+        IntConversionError,
+        UnsupportedAttestationKeyType,
+        AttestationKeyDoesNotMatch,
+        InconsistentLength(QuoteField),
+        TrailingBytes { offset: u64 },
+        NonZeroReserved { offset: u64 },
+        CertificationDataTooLong(u64),
+        BodyTypeMismatch { tee_type: TEEType, body_type: u16 },
+    }
+}
+
+impl From<&AttestationKey> for encoded::AttestationKey {
+    fn from(key: &AttestationKey) -> Self {
+        let bytes = key.to_raw_bytes();
+        match key {
+            AttestationKey::P256(_) | AttestationKey::RawP256(_) => {
+                Self::P256(bytes.try_into().unwrap())
+            }
+            #[cfg(feature = "p384")]
+            AttestationKey::P384(_) | AttestationKey::RawP384(_) => {
+                Self::P384(bytes.try_into().unwrap())
+            }
+        }
+    }
+}
+
+impl TryFrom<encoded::AttestationKey> for AttestationKey {
+    type Error = Error;
+
+    fn try_from(key: encoded::AttestationKey) -> Result<Self, Error> {
+        let (attestation_key_type, bytes): (_, &[u8]) = match &key {
+            encoded::AttestationKey::P256(bytes) => (AttestionKeyType::ECDSA256WithP256, bytes),
+            #[cfg(feature = "p384")]
+            encoded::AttestationKey::P384(bytes) => (AttestionKeyType::ECDSA384WithP384, bytes),
+        };
+        AttestationKey::from_raw_bytes_lenient(&attestation_key_type, bytes)
+            .map_err(|_| "Attestation key is not a valid point".into())
+    }
+}
+
+impl From<&QuoteSignature> for encoded::QuoteSignature {
+    fn from(signature: &QuoteSignature) -> Self {
+        let bytes = signature.to_bytes();
+        match signature {
+            QuoteSignature::P256(_) | QuoteSignature::RawP256(_) => {
+                Self::P256(bytes.try_into().unwrap())
+            }
+            #[cfg(feature = "p384")]
+            QuoteSignature::P384(_) | QuoteSignature::RawP384(_) => {
+                Self::P384(bytes.try_into().unwrap())
+            }
+        }
+    }
+}
+
+impl TryFrom<encoded::QuoteSignature> for QuoteSignature {
+    type Error = Error;
+
+    fn try_from(signature: encoded::QuoteSignature) -> Result<Self, Error> {
+        let (attestation_key_type, bytes): (_, &[u8]) = match &signature {
+            encoded::QuoteSignature::P256(bytes) => (AttestionKeyType::ECDSA256WithP256, bytes),
+            #[cfg(feature = "p384")]
+            encoded::QuoteSignature::P384(bytes) => (AttestionKeyType::ECDSA384WithP384, bytes),
+        };
+        QuoteSignature::from_raw_bytes_lenient(&attestation_key_type, bytes)
+            .map_err(|_| "Malformed signature".into())
+    }
+}
+
+impl From<&CertificationData> for encoded::CertificationData {
+    fn from(certification_data: &CertificationData) -> Self {
+        match certification_data {
+            CertificationData::PckIdPpidPlainCpusvnPcesvn(data) => {
+                Self::PckIdPpidPlainCpusvnPcesvn(data.into())
+            }
+            CertificationData::PckIdPpidRSA2048CpusvnPcesvn(data) => {
+                Self::PckIdPpidRSA2048CpusvnPcesvn(data.into())
+            }
+            CertificationData::PckIdPpidRSA3072CpusvnPcesvn(data) => {
+                Self::PckIdPpidRSA3072CpusvnPcesvn(data.into())
+            }
+            CertificationData::PckLeafCert(data) => Self::PckLeafCert(data.into()),
+            CertificationData::PckCertChain(data) => Self::PckCertChain(data.into()),
+            CertificationData::QeReportCertificationData(data) => {
+                Self::QeReportCertificationData(data.into())
+            }
+            CertificationData::PlatformManifest(data) => Self::PlatformManifest(data.into()),
+        }
+    }
+}
+
+impl TryFrom<encoded::CertificationData> for CertificationData {
+    type Error = Error;
+
+    fn try_from(certification_data: encoded::CertificationData) -> Result<Self, Error> {
+        use encoded::CertificationData as Encoded;
+        Ok(match certification_data {
+            Encoded::PckIdPpidPlainCpusvnPcesvn(data) => Self::PckIdPpidPlainCpusvnPcesvn(data.0),
+            Encoded::PckIdPpidRSA2048CpusvnPcesvn(data) => {
+                Self::PckIdPpidRSA2048CpusvnPcesvn(data.0)
+            }
+            Encoded::PckIdPpidRSA3072CpusvnPcesvn(data) => {
+                Self::PckIdPpidRSA3072CpusvnPcesvn(data.0)
+            }
+            Encoded::PckLeafCert(data) => Self::PckLeafCert(data.0),
+            Encoded::PckCertChain(data) => Self::PckCertChain(data.0),
+            Encoded::QeReportCertificationData(data) => {
+                Self::QeReportCertificationData(data.try_into()?)
+            }
+            Encoded::PlatformManifest(data) => Self::PlatformManifest(data.0),
+        })
+    }
+}
+
+impl From<&QeReportCertificationData> for encoded::QeReportCertificationData {
+    fn from(qe_report_certification_data: &QeReportCertificationData) -> Self {
+        Self {
+            qe_report: qe_report_certification_data.qe_report,
+            signature: (&qe_report_certification_data.signature).into(),
+            qe_authentication_data: (&qe_report_certification_data.qe_authentication_data).into(),
+            certification_data: (&qe_report_certification_data.certification_data).into(),
+        }
+    }
+}
+
+impl TryFrom<encoded::QeReportCertificationData> for QeReportCertificationData {
+    type Error = Error;
+
+    fn try_from(
+        qe_report_certification_data: encoded::QeReportCertificationData,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            qe_report: qe_report_certification_data.qe_report,
+            signature: qe_report_certification_data.signature.try_into()?,
+            qe_authentication_data: qe_report_certification_data.qe_authentication_data.0,
+            certification_data: qe_report_certification_data.certification_data.0,
+        })
+    }
+}
+
+impl From<&QuoteParseError> for encoded::QuoteParseError {
+    fn from(error: &QuoteParseError) -> Self {
+        match *error {
+            QuoteParseError::Parse { field, offset } => Self::Parse {
+                field,
+                offset: offset as u64,
+            },
+            QuoteParseError::Verification => Self::Verification,
+            QuoteParseError::MalformedAttestationKey => Self::MalformedAttestationKey,
+            QuoteParseError::MalformedSignature => Self::MalformedSignature,
+            QuoteParseError::UnknownCertificationDataType(certification_data_type) => {
+                Self::UnknownCertificationDataType(certification_data_type)
+            }
+            QuoteParseError::UnknownQuoteVersion(version) => Self::UnknownQuoteVersion(version),
+            QuoteParseError::UnknownBodyType(body_type) => Self::UnknownBodyType(body_type),
+            QuoteParseError::IntConversionError => Self::IntConversionError,
+            QuoteParseError::UnsupportedAttestationKeyType => Self::UnsupportedAttestationKeyType,
+            QuoteParseError::AttestationKeyDoesNotMatch => Self::AttestationKeyDoesNotMatch,
+            QuoteParseError::InconsistentLength(field) => Self::InconsistentLength(field),
+            QuoteParseError::TrailingBytes { offset } => Self::TrailingBytes {
+                offset: offset as u64,
+            },
+            QuoteParseError::NonZeroReserved { offset } => Self::NonZeroReserved {
+                offset: offset as u64,
+            },
+            QuoteParseError::CertificationDataTooLong(len) => {
+                Self::CertificationDataTooLong(len as u64)
+            }
+            QuoteParseError::BodyTypeMismatch {
+                ref tee_type,
+                body_type,
+            } => Self::BodyTypeMismatch {
+                tee_type: tee_type.clone(),
+                body_type,
+            },
+        }
+    }
+}
+
+impl TryFrom<encoded::QuoteParseError> for QuoteParseError {
+    type Error = Error;
+
+    fn try_from(error: encoded::QuoteParseError) -> Result<Self, Error> {
+        use encoded::QuoteParseError as Encoded;
+        let to_usize = |value: u64| usize::try_from(value).map_err(|_| "Offset out of range");
+        Ok(match error {
+            Encoded::Parse { field, offset } => Self::Parse {
+                field,
+                offset: to_usize(offset)?,
+            },
+            Encoded::Verification => Self::Verification,
+            Encoded::MalformedAttestationKey => Self::MalformedAttestationKey,
+            Encoded::MalformedSignature => Self::MalformedSignature,
+            Encoded::UnknownCertificationDataType(certification_data_type) => {
+                Self::UnknownCertificationDataType(certification_data_type)
+            }
+            Encoded::UnknownQuoteVersion(version) => Self::UnknownQuoteVersion(version),
+            Encoded::UnknownBodyType(body_type) => Self::UnknownBodyType(body_type),
+            Encoded::IntConversionError => Self::IntConversionError,
+            Encoded::UnsupportedAttestationKeyType => Self::UnsupportedAttestationKeyType,
+            Encoded::AttestationKeyDoesNotMatch => Self::AttestationKeyDoesNotMatch,
+            Encoded::InconsistentLength(field) => Self::InconsistentLength(field),
+            Encoded::TrailingBytes { offset } => Self::TrailingBytes {
+                offset: to_usize(offset)?,
+            },
+            Encoded::NonZeroReserved { offset } => Self::NonZeroReserved {
+                offset: to_usize(offset)?,
+            },
+            Encoded::CertificationDataTooLong(len) => {
+                Self::CertificationDataTooLong(to_usize(len)?)
+            }
+            Encoded::BodyTypeMismatch {
+                tee_type,
+                body_type,
+            } => Self::BodyTypeMismatch {
+                tee_type,
+                body_type,
+            },
+        })
+    }
+}
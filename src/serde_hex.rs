@@ -0,0 +1,91 @@
+//! Serde helpers which encode bytes as hex in human-readable formats, and as raw bytes otherwise
+
+/// For fixed length byte arrays
+pub(crate) mod array {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serdect::array::serialize_hex_lower_or_bin(bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let mut bytes = [0; N];
+        serdect::array::deserialize_hex_or_bin(&mut bytes, deserializer)?;
+        Ok(bytes)
+    }
+}
+
+/// For optional fixed length byte arrays
+pub(crate) mod option_array {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Array<const N: usize>(#[serde(with = "super::array")] [u8; N]);
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &Option<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        bytes.map(Array).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Option<[u8; N]>, D::Error> {
+        Ok(Option::<Array<N>>::deserialize(deserializer)?.map(|array| array.0))
+    }
+}
+
+/// For variable length bytes
+pub(crate) mod vec {
+    use alloc::vec::Vec;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serdect::slice::serialize_hex_lower_or_bin(&bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        serdect::slice::deserialize_hex_or_bin_vec(deserializer)
+    }
+}
+
+/// For P-256 verifying keys, as a SEC1 encoded point
+pub(crate) mod p256_key {
+    use p256::ecdsa::VerifyingKey;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &VerifyingKey, serializer: S) -> Result<S::Ok, S::Error> {
+        super::vec::serialize(key.to_encoded_point(false).as_bytes(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<VerifyingKey, D::Error> {
+        VerifyingKey::from_sec1_bytes(&super::vec::deserialize(deserializer)?)
+            .map_err(|_| D::Error::custom("invalid P-256 public key"))
+    }
+}
+
+/// For P-384 verifying keys, as a SEC1 encoded point
+#[cfg(feature = "p384")]
+pub(crate) mod p384_key {
+    use p384::ecdsa::VerifyingKey;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &VerifyingKey, serializer: S) -> Result<S::Ok, S::Error> {
+        super::vec::serialize(key.to_encoded_point(false).as_bytes(), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<VerifyingKey, D::Error> {
+        VerifyingKey::from_sec1_bytes(&super::vec::deserialize(deserializer)?)
+            .map_err(|_| D::Error::custom("invalid P-384 public key"))
+    }
+}
@@ -0,0 +1,378 @@
+//! Serialize quotes back to the format in which they are produced
+use crate::{
+    CertificationData, QeReportCertificationData, Quote, QuoteBody, QuoteHeader,
+    QuoteSerializeError, ReportBody, SgxReportBody, TDXVersion, TEEType, QUOTE_HEADER_LENGTH,
+    V4_QUOTE_BODY_LENGTH,
+};
+use alloc::vec::Vec;
+
+impl Quote {
+    /// Serialize the quote. Parsing the output with [Quote::from_bytes] gives the same quote.
+    ///
+    /// This fails if a variable length field is too long for its length to be given in the quote,
+    /// or if the body, attestation key or certification data cannot be given in a quote with this
+    /// header.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, QuoteSerializeError> {
+        check_body(&self.header, &self.body)?;
+        if self.attestation_key.attestation_key_type() != self.header.attestation_key_type {
+            return Err(QuoteSerializeError::AttestationKeyDoesNotMatchHeader);
+        }
+        // Version 3 quotes have no certification data type, as they always contain QE report
+        // certification data
+        if self.header.version == 3
+            && !matches!(
+                self.certification_data,
+                CertificationData::QeReportCertificationData(_)
+            )
+        {
+            return Err(QuoteSerializeError::CertificationDataDoesNotMatchVersion);
+        }
+        let mut output = self.signed_data();
+
+        let certification_data = certification_data_serializer(&self.certification_data)?;
+        let signature_section_length = i32::try_from(self.signature_section_length())
+            .map_err(|_| QuoteSerializeError::TooLong("signature section"))?;
+        output.extend_from_slice(&signature_section_length.to_le_bytes());
+
+        output.extend_from_slice(&self.signature.to_bytes());
+        output.extend_from_slice(&self.attestation_key.to_raw_bytes());
+
+        if self.header.version != 3 {
+            let certification_data_length = i32::try_from(certification_data.len())
+                .map_err(|_| QuoteSerializeError::TooLong("certification data"))?;
+            output.extend_from_slice(
+                &self
+                    .certification_data
+                    .certification_data_type()
+                    .to_le_bytes(),
+            );
+            output.extend_from_slice(&certification_data_length.to_le_bytes());
+        }
+        output.extend_from_slice(&certification_data);
+        Ok(output)
+    }
+
+    /// Serialize the header and body, which is the data signed by the attestation key
+    pub(crate) fn signed_data(&self) -> Vec<u8> {
+        signed_data_serializer(&self.header, &self.body)
+    }
+
+    /// Length of the signature section, which follows the header and body
+    pub(crate) fn signature_section_length(&self) -> usize {
+        // Version 3 quotes have no type and length before the QE report certification data
+        let certification_data_header_length = if self.header.version == 3 { 0 } else { 2 + 4 };
+        2 * self.attestation_key.attestation_key_type().key_length()
+            + certification_data_header_length
+            + certification_data_length(&self.certification_data)
+    }
+}
+
+/// Check that the body can be given in a quote with the given header. TDX 1.5 bodies are only found
+/// in version 5 quotes.
+pub(crate) fn check_body(
+    header: &QuoteHeader,
+    body: &ReportBody,
+) -> Result<(), QuoteSerializeError> {
+    let matches = match body {
+        ReportBody::Sgx(_) => header.tee_type == TEEType::SGX,
+        ReportBody::Td(body) => {
+            let tdx_1_5_fields = (body.tee_tcb_svn_2.is_some(), body.mrservicetd.is_some());
+            header.tee_type == TEEType::TDX
+                && match (header.version, &body.tdx_version) {
+                    (4 | 5, TDXVersion::One) => tdx_1_5_fields == (false, false),
+                    (5, TDXVersion::OnePointFive) => tdx_1_5_fields == (true, true),
+                    _ => false,
+                }
+        }
+    };
+    if !matches {
+        return Err(QuoteSerializeError::BodyDoesNotMatchHeader);
+    }
+    Ok(())
+}
+
+/// Serialize a quote header and body, giving the data signed by the attestation key
+pub(crate) fn signed_data_serializer(header: &QuoteHeader, body: &ReportBody) -> Vec<u8> {
+    let mut output = Vec::from(quote_header_serializer(header));
+    if header.version == 5 {
+        let (body_type, body_size): (u16, u32) = match body {
+            ReportBody::Sgx(_) => (1, 384),
+            ReportBody::Td(body) => match body.tdx_version {
+                TDXVersion::One => (2, V4_QUOTE_BODY_LENGTH as u32),
+                TDXVersion::OnePointFive => (3, V4_QUOTE_BODY_LENGTH as u32 + 64),
+            },
+        };
+        output.extend_from_slice(&body_type.to_le_bytes());
+        output.extend_from_slice(&body_size.to_le_bytes());
+    }
+    match body {
+        ReportBody::Td(body) => {
+            output.extend_from_slice(&quote_body_v4_serializer(body));
+            if let (Some(tee_tcb_svn_2), Some(mrservicetd)) = (body.tee_tcb_svn_2, body.mrservicetd)
+            {
+                output.extend_from_slice(&tee_tcb_svn_2);
+                output.extend_from_slice(&mrservicetd);
+            }
+        }
+        ReportBody::Sgx(body) => output.extend_from_slice(&sgx_report_body_serializer(body)),
+    }
+    output
+}
+
+impl CertificationData {
+    /// The certification data type, as given in the quote
+    pub fn certification_data_type(&self) -> i16 {
+        match self {
+            CertificationData::PckIdPpidPlainCpusvnPcesvn(_) => 1,
+            CertificationData::PckIdPpidRSA2048CpusvnPcesvn(_) => 2,
+            CertificationData::PckIdPpidRSA3072CpusvnPcesvn(_) => 3,
+            CertificationData::PckLeafCert(_) => 4,
+            CertificationData::PckCertChain(_) => 5,
+            CertificationData::QeReportCertificationData(_) => 6,
+            CertificationData::PlatformManifest(_) => 7,
+        }
+    }
+}
+
+/// Serialize cerification data
+pub(crate) fn certification_data_serializer(
+    input: &CertificationData,
+) -> Result<Vec<u8>, QuoteSerializeError> {
+    match input {
+        CertificationData::QeReportCertificationData(qe_report_certification_data) => {
+            qe_report_certification_data_serializer(qe_report_certification_data)
+        }
+        CertificationData::PckIdPpidPlainCpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA2048CpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA3072CpusvnPcesvn(data)
+        | CertificationData::PckLeafCert(data)
+        | CertificationData::PckCertChain(data)
+        | CertificationData::PlatformManifest(data) => Ok(data.to_vec()),
+    }
+}
+
+/// Length of serialized certification data
+pub(crate) fn certification_data_length(input: &CertificationData) -> usize {
+    match input {
+        CertificationData::QeReportCertificationData(qe_report_certification_data) => {
+            384 + 64
+                + 2
+                + qe_report_certification_data.qe_authentication_data.len()
+                + qe_report_certification_data.certification_data.len()
+        }
+        CertificationData::PckIdPpidPlainCpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA2048CpusvnPcesvn(data)
+        | CertificationData::PckIdPpidRSA3072CpusvnPcesvn(data)
+        | CertificationData::PckLeafCert(data)
+        | CertificationData::PckCertChain(data)
+        | CertificationData::PlatformManifest(data) => data.len(),
+    }
+}
+
+/// Serialize QE report certification data, including the nested certification data
+fn qe_report_certification_data_serializer(
+    input: &QeReportCertificationData,
+) -> Result<Vec<u8>, QuoteSerializeError> {
+    let qe_authentication_data_length = u16::try_from(input.qe_authentication_data.len())
+        .map_err(|_| QuoteSerializeError::TooLong("QE authentication data"))?;
+    let mut output = Vec::with_capacity(
+        384 + 64 + 2 + input.qe_authentication_data.len() + input.certification_data.len(),
+    );
+    output.extend_from_slice(&input.qe_report);
+    output.extend_from_slice(&input.signature.to_bytes());
+    output.extend_from_slice(&qe_authentication_data_length.to_le_bytes());
+    output.extend_from_slice(&input.qe_authentication_data);
+    output.extend_from_slice(&input.certification_data);
+    Ok(output)
+}
+
+/// Serialize a quote header
+pub(crate) fn quote_header_serializer(input: &QuoteHeader) -> [u8; QUOTE_HEADER_LENGTH] {
+    let mut output = [1; QUOTE_HEADER_LENGTH];
+    let version = input.version.to_le_bytes();
+    output[..2].copy_from_slice(&version);
+
+    let attestation_key_type = input.attestation_key_type.clone() as u16;
+    let attestation_key_type = attestation_key_type.to_le_bytes();
+    output[2..4].copy_from_slice(&attestation_key_type);
+
+    let tee_type = input.tee_type.clone() as u32;
+    let tee_type = tee_type.to_le_bytes();
+    output[4..8].copy_from_slice(&tee_type);
+
+    output[8..10].copy_from_slice(&input.reserved1);
+    output[10..12].copy_from_slice(&input.reserved2);
+    output[12..28].copy_from_slice(&input.qe_vendor_id);
+    output[28..48].copy_from_slice(&input.user_data);
+
+    output
+}
+
+/// Serialize a quote body, omitting the extra fields for TDX 1.5
+pub(crate) fn quote_body_v4_serializer(input: &QuoteBody) -> [u8; V4_QUOTE_BODY_LENGTH] {
+    let mut output = [1; V4_QUOTE_BODY_LENGTH];
+    output[..16].copy_from_slice(&input.tee_tcb_svn);
+    output[16..64].copy_from_slice(&input.mrseam);
+    output[64..112].copy_from_slice(&input.mrsignerseam);
+    output[112..120].copy_from_slice(&input.seamattributes);
+    output[120..128].copy_from_slice(&input.tdattributes);
+    output[128..136].copy_from_slice(&input.xfam);
+    output[136..184].copy_from_slice(&input.mrtd);
+    output[184..232].copy_from_slice(&input.mrconfigid);
+    output[232..280].copy_from_slice(&input.mrowner);
+    output[280..328].copy_from_slice(&input.mrownerconfig);
+    output[328..376].copy_from_slice(&input.rtmr0);
+    output[376..424].copy_from_slice(&input.rtmr1);
+    output[424..472].copy_from_slice(&input.rtmr2);
+    output[472..520].copy_from_slice(&input.rtmr3);
+    output[520..].copy_from_slice(&input.reportdata);
+    output
+}
+
+/// Serialize an SGX enclave report body
+pub(crate) fn sgx_report_body_serializer(input: &SgxReportBody) -> [u8; 384] {
+    let mut output = [0; 384];
+    output[..16].copy_from_slice(&input.cpu_svn);
+    output[16..20].copy_from_slice(&input.misc_select.to_le_bytes());
+    output[20..32].copy_from_slice(&input.reserved1);
+    output[32..48].copy_from_slice(&input.isv_ext_prod_id);
+    output[48..64].copy_from_slice(&input.attributes);
+    output[64..96].copy_from_slice(&input.mr_enclave);
+    output[96..128].copy_from_slice(&input.reserved2);
+    output[128..160].copy_from_slice(&input.mr_signer);
+    output[160..192].copy_from_slice(&input.reserved3);
+    output[192..256].copy_from_slice(&input.config_id);
+    output[256..258].copy_from_slice(&input.isv_prod_id.to_le_bytes());
+    output[258..260].copy_from_slice(&input.isv_svn.to_le_bytes());
+    output[260..262].copy_from_slice(&input.config_svn.to_le_bytes());
+    output[262..304].copy_from_slice(&input.reserved4);
+    output[304..320].copy_from_slice(&input.isv_family_id);
+    output[320..].copy_from_slice(&input.report_data);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::{AttestionKeyType, Quote};
+    use std::{io::Read, vec::Vec};
+
+    #[test]
+    fn test_serialize_header() {
+        let mut file = std::fs::File::open("tests/test-quotes/v4_quote.dat").unwrap();
+        let mut input = Vec::new();
+        file.read_to_end(&mut input).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+        let serialized = quote_header_serializer(&quote.header);
+        assert_eq!(serialized, input[..48]);
+    }
+
+    #[test]
+    fn test_serialize_body() {
+        let mut file = std::fs::File::open("tests/test-quotes/v4_quote.dat").unwrap();
+        let mut input = Vec::new();
+        file.read_to_end(&mut input).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+        let serialized = quote_body_v4_serializer(quote.td_body().unwrap());
+        assert_eq!(serialized, input[48..48 + 584]);
+    }
+
+    #[test]
+    fn test_serialize_errors() {
+        let mut file = std::fs::File::open("tests/test-quotes/v4_quote.dat").unwrap();
+        let mut input = Vec::new();
+        file.read_to_end(&mut input).unwrap();
+        let mut quote = Quote::from_bytes(&input).unwrap();
+
+        // The signature section length is given by the attestation key, but the parser uses the
+        // key type in the header
+        quote.header.attestation_key_type = AttestionKeyType::ECDSA384WithP384;
+        assert_eq!(
+            quote.to_bytes(),
+            Err(QuoteSerializeError::AttestationKeyDoesNotMatchHeader)
+        );
+        quote.header.attestation_key_type = AttestionKeyType::ECDSA256WithP256;
+
+        // An SGX quote, whose signature is no longer valid
+        quote.header.version = 3;
+        quote.header.tee_type = TEEType::SGX;
+        quote.body = ReportBody::Sgx(
+            quote
+                .qe_report_certification_data()
+                .unwrap()
+                .qe_report_body()
+                .unwrap(),
+        );
+        assert!(Quote::parse_unverified(&quote.to_bytes().unwrap()).is_ok());
+        quote.certification_data = CertificationData::PckCertChain(Vec::from(*b"chain"));
+        assert_eq!(
+            quote.to_bytes(),
+            Err(QuoteSerializeError::CertificationDataDoesNotMatchVersion)
+        );
+    }
+
+    #[test]
+    fn test_serialize_qe_report() {
+        let mut file = std::fs::File::open("tests/test-quotes/v4_quote.dat").unwrap();
+        let mut input = Vec::new();
+        file.read_to_end(&mut input).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+        let qe_report_certification_data = quote.qe_report_certification_data().unwrap();
+        let qe_report_body = qe_report_certification_data.qe_report_body().unwrap();
+        assert_eq!(
+            sgx_report_body_serializer(&qe_report_body),
+            qe_report_certification_data.qe_report
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_v5_round_trip() {
+        use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+        use rand_core::OsRng;
+
+        let attestation_key = SigningKey::random(&mut OsRng);
+        let mut quote = Quote::mock(
+            attestation_key.clone(),
+            SigningKey::random(&mut OsRng),
+            [1; 64],
+        );
+        quote.header.version = 5;
+        let ReportBody::Td(body) = &mut quote.body else {
+            panic!("Mock quote should be TDX");
+        };
+        body.tdx_version = TDXVersion::OnePointFive;
+        body.tee_tcb_svn_2 = Some([2; 16]);
+        body.mrservicetd = Some([3; 48]);
+        let signature: Signature = attestation_key.sign(&quote.signed_data());
+        quote.signature = signature.into();
+
+        let serialized = quote.to_bytes().unwrap();
+        assert_eq!(
+            serialized.len(),
+            48 + 6 + 648 + 4 + 64 + 64 + 6 + 384 + 64 + 2
+        );
+        assert_eq!(Quote::from_bytes(&serialized).unwrap(), quote);
+
+        // Version 4 quotes cannot contain TDX 1.5 fields
+        quote.header.version = 4;
+        assert_eq!(
+            quote.to_bytes(),
+            Err(QuoteSerializeError::BodyDoesNotMatchHeader)
+        );
+        quote.header.version = 5;
+
+        // The QE authentication data length is given as 16 bits
+        let CertificationData::QeReportCertificationData(qe_report_certification_data) =
+            &mut quote.certification_data
+        else {
+            panic!("Mock quote should have QE report certification data");
+        };
+        qe_report_certification_data.qe_authentication_data = alloc::vec![0; 0x10000];
+        assert_eq!(
+            quote.to_bytes(),
+            Err(QuoteSerializeError::TooLong("QE authentication data"))
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Predict the runtime measurement register RTMR0 from a QEMU/OVMF virtual machine configuration
+//!
+//! TDVF records the following events in RTMR0, in this order: the TD HOB, the configuration
+//! firmware volume (CFV), the Secure Boot variables, a separator, the ACPI tables generated by
+//! QEMU, and then the boot variables.
+use crate::{boot::EventLog, mrtd::TdvfSectionType, Rtmr, RtmrIndex, TdvfMetadata};
+use alloc::vec::Vec;
+
+pub const EV_PLATFORM_CONFIG_FLAGS: u32 = 0x0000000a;
+pub const EV_EFI_VARIABLE_DRIVER_CONFIG: u32 = 0x80000001;
+pub const EV_EFI_VARIABLE_BOOT: u32 = 0x80000002;
+pub const EV_EFI_PLATFORM_FIRMWARE_BLOB2: u32 = 0x8000000a;
+pub const EV_EFI_HANDOFF_TABLES2: u32 = 0x8000000b;
+
+/// 8be4df61-93ca-11d2-aa0d-00e098032b8c - vendor GUID of SecureBoot, PK, KEK and boot variables
+pub const EFI_GLOBAL_VARIABLE_GUID: [u8; 16] = [
+    0x61, 0xdf, 0xe4, 0x8b, 0xca, 0x93, 0xd2, 0x11, 0xaa, 0x0d, 0x00, 0xe0, 0x98, 0x03, 0x2b, 0x8c,
+];
+/// d719b2cb-3d3a-4596-a3bc-dad00e67656f - vendor GUID of the db and dbx variables
+pub const EFI_IMAGE_SECURITY_DATABASE_GUID: [u8; 16] = [
+    0xcb, 0xb2, 0x19, 0xd7, 0x3a, 0x3d, 0x96, 0x45, 0xa3, 0xbc, 0xda, 0xd0, 0x0e, 0x67, 0x65, 0x6f,
+];
+
+/// A UEFI variable
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EfiVariable<'a> {
+    pub vendor_guid: [u8; 16],
+    pub name: &'a str,
+    pub data: &'a [u8],
+}
+
+impl<'a> EfiVariable<'a> {
+    pub fn new(vendor_guid: [u8; 16], name: &'a str, data: &'a [u8]) -> Self {
+        Self {
+            vendor_guid,
+            name,
+            data,
+        }
+    }
+
+    /// Serialize as a `UEFI_VARIABLE_DATA` structure, which is measured for Secure Boot variables
+    pub fn uefi_variable_data(&self) -> Vec<u8> {
+        let name: Vec<u16> = self.name.encode_utf16().collect();
+        let mut output = Vec::with_capacity(32 + 2 * name.len() + self.data.len());
+        output.extend_from_slice(&self.vendor_guid);
+        output.extend_from_slice(&(name.len() as u64).to_le_bytes());
+        output.extend_from_slice(&(self.data.len() as u64).to_le_bytes());
+        output.extend(name.iter().flat_map(|c| c.to_le_bytes()));
+        output.extend_from_slice(self.data);
+        output
+    }
+}
+
+/// The Secure Boot variables, which are measured whether or not Secure Boot is enabled. Variables
+/// which are not set are measured with empty data.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SecureBootVariables<'a> {
+    /// Value of the `SecureBoot` variable - 1 if enabled, 0 if disabled, or `None` if it is not
+    /// set, as when OVMF is not configured for Secure Boot
+    pub secure_boot: Option<u8>,
+    pub pk: &'a [u8],
+    pub kek: &'a [u8],
+    pub db: &'a [u8],
+    pub dbx: &'a [u8],
+}
+
+impl SecureBootVariables<'_> {
+    /// Secure Boot not configured, so that none of the variables are set
+    pub fn disabled() -> Self {
+        Self {
+            secure_boot: None,
+            pk: &[],
+            kek: &[],
+            db: &[],
+            dbx: &[],
+        }
+    }
+}
+
+impl Default for SecureBootVariables<'_> {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+/// The ACPI table blobs which QEMU passes to the firmware using fw_cfg
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AcpiTables<'a> {
+    /// Contents of `etc/table-loader`
+    pub table_loader: &'a [u8],
+    /// Contents of `etc/acpi/rsdp`
+    pub rsdp: &'a [u8],
+    /// Contents of `etc/acpi/tables`
+    pub tables: &'a [u8],
+}
+
+/// Describes a QEMU/OVMF virtual machine, in order to predict RTMR0
+#[derive(Debug, Clone)]
+pub struct VmConfiguration<'a> {
+    td_hob: &'a [u8],
+    cfv: &'a [u8],
+    secure_boot: SecureBootVariables<'a>,
+    acpi_tables: AcpiTables<'a>,
+    boot_variables: Vec<EfiVariable<'a>>,
+}
+
+impl<'a> VmConfiguration<'a> {
+    /// Create a configuration from the TD HOB, as generated by the VMM, and the contents of the
+    /// configuration firmware volume
+    pub fn new(td_hob: &'a [u8], cfv: &'a [u8]) -> Self {
+        Self {
+            td_hob,
+            cfv,
+            secure_boot: SecureBootVariables::disabled(),
+            acpi_tables: AcpiTables::default(),
+            boot_variables: Vec::new(),
+        }
+    }
+
+    /// Create a configuration using the configuration firmware volume from the given TDVF
+    /// firmware image. Returns `None` if the image has no CFV section.
+    pub fn from_firmware(
+        td_hob: &'a [u8],
+        metadata: &TdvfMetadata,
+        firmware: &'a [u8],
+    ) -> Option<Self> {
+        let section = metadata
+            .sections
+            .iter()
+            .find(|section| section.section_type == TdvfSectionType::Cfv)?;
+        let start = section.data_offset as usize;
+        let cfv = firmware.get(start..start.checked_add(section.raw_data_size as usize)?)?;
+        Some(Self::new(td_hob, cfv))
+    }
+
+    /// Set the Secure Boot variables. By default Secure Boot is disabled.
+    pub fn secure_boot(mut self, secure_boot: SecureBootVariables<'a>) -> Self {
+        self.secure_boot = secure_boot;
+        self
+    }
+
+    /// Set the ACPI tables generated by QEMU
+    pub fn acpi_tables(mut self, acpi_tables: AcpiTables<'a>) -> Self {
+        self.acpi_tables = acpi_tables;
+        self
+    }
+
+    /// Add a boot variable, for example `BootOrder` or `Boot0000`, in the order they are measured
+    pub fn boot_variable(mut self, variable: EfiVariable<'a>) -> Self {
+        self.boot_variables.push(variable);
+        self
+    }
+
+    /// Get the log of events for RTMR0
+    pub fn event_log(&self) -> EventLog {
+        let mut log = EventLog::new();
+        log.measure_data(RtmrIndex::Rtmr0, EV_EFI_HANDOFF_TABLES2, self.td_hob);
+        log.measure_data(RtmrIndex::Rtmr0, EV_EFI_PLATFORM_FIRMWARE_BLOB2, self.cfv);
+
+        let secure_boot = self.secure_boot.secure_boot.as_slice();
+        for variable in [
+            EfiVariable::new(EFI_GLOBAL_VARIABLE_GUID, "SecureBoot", secure_boot),
+            EfiVariable::new(EFI_GLOBAL_VARIABLE_GUID, "PK", self.secure_boot.pk),
+            EfiVariable::new(EFI_GLOBAL_VARIABLE_GUID, "KEK", self.secure_boot.kek),
+            EfiVariable::new(EFI_IMAGE_SECURITY_DATABASE_GUID, "db", self.secure_boot.db),
+            EfiVariable::new(
+                EFI_IMAGE_SECURITY_DATABASE_GUID,
+                "dbx",
+                self.secure_boot.dbx,
+            ),
+        ] {
+            log.measure_data(
+                RtmrIndex::Rtmr0,
+                EV_EFI_VARIABLE_DRIVER_CONFIG,
+                &variable.uefi_variable_data(),
+            );
+        }
+        log.measure_separator(RtmrIndex::Rtmr0);
+
+        log.measure_data(
+            RtmrIndex::Rtmr0,
+            EV_PLATFORM_CONFIG_FLAGS,
+            self.acpi_tables.table_loader,
+        );
+        log.measure_data(
+            RtmrIndex::Rtmr0,
+            EV_PLATFORM_CONFIG_FLAGS,
+            self.acpi_tables.rsdp,
+        );
+        log.measure_data(
+            RtmrIndex::Rtmr0,
+            EV_PLATFORM_CONFIG_FLAGS,
+            self.acpi_tables.tables,
+        );
+
+        // For boot variables only the variable data is measured
+        for variable in self.boot_variables.iter() {
+            log.measure_data(RtmrIndex::Rtmr0, EV_EFI_VARIABLE_BOOT, variable.data);
+        }
+        log
+    }
+
+    /// Compute the expected value of RTMR0
+    pub fn rtmr0(&self) -> Rtmr {
+        self.event_log().replay().rtmr0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtmr::tests::from_hex;
+
+    #[test]
+    fn test_uefi_variable_data() {
+        let variable = EfiVariable::new(EFI_GLOBAL_VARIABLE_GUID, "PK", &[1, 2]);
+        let data = variable.uefi_variable_data();
+        assert_eq!(&data[..16], &EFI_GLOBAL_VARIABLE_GUID);
+        assert_eq!(&data[16..24], &2u64.to_le_bytes());
+        assert_eq!(&data[24..32], &2u64.to_le_bytes());
+        assert_eq!(&data[32..], b"P\0K\0\x01\x02");
+    }
+
+    #[test]
+    fn test_rtmr0() {
+        let config = VmConfiguration::new(b"hob", b"cfv")
+            .acpi_tables(AcpiTables {
+                table_loader: b"loader",
+                rsdp: b"rsdp",
+                tables: b"tables",
+            })
+            .boot_variable(EfiVariable::new(
+                EFI_GLOBAL_VARIABLE_GUID,
+                "BootOrder",
+                &[0, 0],
+            ));
+        let log = config.event_log();
+        assert_eq!(log.events.len(), 12);
+        assert!(log
+            .events
+            .iter()
+            .all(|event| event.rtmr_index == RtmrIndex::Rtmr0));
+
+        let mut expected = Rtmr::new();
+        for event in log.events.iter() {
+            expected.extend(&event.digest);
+        }
+        assert_eq!(config.rtmr0(), expected);
+        assert_ne!(
+            config.rtmr0(),
+            config
+                .clone()
+                .secure_boot(SecureBootVariables {
+                    secure_boot: Some(1),
+                    ..Default::default()
+                })
+                .rtmr0()
+        );
+    }
+
+    /// Expected values computed independently of this crate, by hashing the UEFI_VARIABLE_DATA
+    /// structures and other events as measured by OVMF, and extending a register with each digest
+    #[test]
+    fn test_rtmr0_known_answer() {
+        let config = VmConfiguration::new(b"hob", b"cfv")
+            .acpi_tables(AcpiTables {
+                table_loader: b"loader",
+                rsdp: b"rsdp",
+                tables: b"tables",
+            })
+            .boot_variable(EfiVariable::new(
+                EFI_GLOBAL_VARIABLE_GUID,
+                "BootOrder",
+                &[0, 0],
+            ));
+        // Without Secure Boot configured, the variables are measured with empty data
+        assert_eq!(
+            config.rtmr0().value(),
+            from_hex("337f209b0a151e49ca41b503e91b0057b5b36f4ff0d3852f530e65200ae6513c2ca872fe711c6d0e3b4a31651fe9bfd2")
+        );
+        let secure_boot = config.secure_boot(SecureBootVariables {
+            secure_boot: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(
+            secure_boot.rtmr0().value(),
+            from_hex("75011a0833514f6c9086e8d961ed2add48c663f499ff9184afb7cce2d587e4b2e7dd1e9597c933511bb8cfe143e4d4f2")
+        );
+    }
+}
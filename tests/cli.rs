@@ -0,0 +1,100 @@
+#![cfg(feature = "cli")]
+use std::{fs, path::PathBuf, process::Command};
+use tdx_quote::Quote;
+
+const KNOWN_PCK: &str = "04a667883a9d9b7cba4b518557ffe9b6c07debe679ad936c2fbef0b54bb51f9480e1c0c047ed1cb44ba124739f4c75e22e725bc4eff840a819ff65f1a271f5fd94";
+
+fn tdx_quote(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_tdx-quote"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+/// A path in the temporary directory which is not used by any other test or concurrent test run
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tdx-quote-test-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn test_cli_verify() {
+    let (code, output) = tdx_quote(&[
+        "verify",
+        "tests/test-quotes/known_pck_quote_1.dat",
+        "--pck",
+        KNOWN_PCK,
+        "--collateral",
+        "tests/test-collateral",
+    ]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        output,
+        "signature: ok\nQE binding: ok\nPCK signature: ok\nTCB status: UpToDate\n"
+    );
+
+    // The older quoting enclave is out of date, unless the policy accepts that
+    let policy = temp_path("policy.json");
+    fs::write(
+        &policy,
+        r#"{"quote": {"body": {"tdx_module_major_version": 1}}, "accepted_tcb_statuses": ["OutOfDate"]}"#,
+    )
+    .unwrap();
+    let args = [
+        "verify",
+        "tests/test-quotes/v4_quote.dat",
+        "--collateral",
+        "tests/test-collateral",
+    ];
+    let (code, output) = tdx_quote(&args);
+    assert_eq!(code, Some(1));
+    assert!(output.contains("TCB status: FAILED: OutOfDate"));
+    let (code, output) = tdx_quote(&[&args[..], &["--policy", policy.to_str().unwrap()]].concat());
+    assert_eq!(code, Some(0));
+    assert!(output.ends_with("TCB status: OutOfDate\npolicy: ok\n"));
+    fs::remove_file(policy).unwrap();
+}
+
+#[test]
+fn test_cli_convert_and_diff() {
+    let quote = "tests/test-quotes/known_pck_quote_1.dat";
+    let hex_path = temp_path("quote.hex");
+    let hex_quote = hex_path.to_str().unwrap();
+    let (code, _) = tdx_quote(&["convert", quote, "--to", "hex", "--output", hex_quote]);
+    assert_eq!(code, Some(0));
+
+    // The hex encoding is detected, and gives the same quote
+    let (code, output) = tdx_quote(&["diff", quote, hex_quote]);
+    assert_eq!((code, output.as_str()), (Some(0), ""));
+    let (_, json) = tdx_quote(&["inspect", hex_quote, "--json"]);
+    let (_, converted) = tdx_quote(&["convert", quote, "--to", "json"]);
+    assert_eq!(json, converted);
+    let (_, json) = tdx_quote(&["inspect", hex_quote, "--layout", "intel"]);
+    let (_, converted) = tdx_quote(&["convert", quote, "--to", "json", "--layout", "intel"]);
+    assert_eq!(json, converted);
+    fs::remove_file(&hex_path).unwrap();
+
+    let (code, output) = tdx_quote(&["diff", quote, "tests/test-quotes/v4_quote.dat"]);
+    assert_eq!(code, Some(1));
+    assert!(output.contains("body.mrtd: 91eb2b44"));
+}
+
+#[test]
+fn test_cli_hexdump() {
+    let (code, output) = tdx_quote(&["hexdump", "tests/test-quotes/known_pck_quote_1.dat"]);
+    assert_eq!(code, Some(0));
+    assert!(output.contains("\n000178    rtmr0 (48 bytes)\n"));
+}
+
+#[test]
+fn test_cli_inspect() {
+    let quote = "tests/test-quotes/known_pck_quote_1.dat";
+    let (code, output) = tdx_quote(&["inspect", quote]);
+    assert_eq!(code, Some(0));
+    let expected = Quote::from_bytes(&fs::read(quote).unwrap()).unwrap();
+    assert_eq!(output, expected.to_string());
+    assert!(output.starts_with("Header:\n"));
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "alloc")]
+#[cfg(feature = "json")]
+use std::fs;
+use tdx_quote::{CollateralError, QeIdentity, TcbInfo, COMPACT_COLLATERAL_VERSION};
+#[cfg(feature = "json")]
+use tdx_quote::{EnclaveId, PlatformTcb, Quote, TcbStatus};
+
+#[cfg(feature = "json")]
+fn collateral() -> (TcbInfo, QeIdentity) {
+    let tcb_info = fs::read_to_string("tests/test-collateral/tdx_tcb_info.json").unwrap();
+    let qe_identity = fs::read_to_string("tests/test-collateral/td_qe_identity.json").unwrap();
+    (
+        TcbInfo::from_json(&tcb_info).unwrap(),
+        QeIdentity::from_json(&qe_identity).unwrap(),
+    )
+}
+
+#[cfg(feature = "json")]
+fn platform(sgx_tcb_components: [u8; 16]) -> PlatformTcb {
+    PlatformTcb {
+        fmspc: [0x00, 0x80, 0x6f, 0x05, 0x00, 0x00],
+        pce_id: [0, 0],
+        sgx_tcb_components,
+        pce_svn: 13,
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_collateral_from_json() {
+    use sha2::{Digest, Sha256};
+
+    let json = fs::read_to_string("tests/test-collateral/tdx_tcb_info.json").unwrap();
+    let (tcb_info, qe_identity) = collateral();
+    assert!(tcb_info.tdx);
+    assert_eq!(tcb_info.fmspc, [0x00, 0x80, 0x6f, 0x05, 0x00, 0x00]);
+    assert_eq!(tcb_info.issue_date, 1729156211);
+    assert_eq!(tcb_info.tdx_module_identities[1].major_version, 3);
+    assert_eq!(tcb_info.tcb_levels.len(), 3);
+    assert_eq!(
+        tcb_info.tcb_levels[1].advisory_ids,
+        ["INTEL-SA-00960", "INTEL-SA-00982"]
+    );
+    assert_eq!(qe_identity.misc_select_mask, 0xffffffff);
+    assert_eq!(qe_identity.tcb_levels[0].isv_svn, 6);
+
+    // The signed data is the tcbInfo object exactly as it appears in the JSON
+    let signed_data = &json["{\"tcbInfo\":".len()..json.find(",\"signature\"").unwrap()];
+    assert_eq!(
+        tcb_info.signature_reference.signed_data_hash,
+        <[u8; 32]>::from(Sha256::digest(signed_data))
+    );
+
+    // Round trip through the compact form
+    let compact = tcb_info.to_compact_bytes().unwrap();
+    assert!(compact.len() < json.len() / 3);
+    assert_eq!(TcbInfo::from_compact_bytes(&compact), Ok(tcb_info.clone()));
+    let compact = qe_identity.to_compact_bytes().unwrap();
+    assert_eq!(QeIdentity::from_compact_bytes(&compact), Ok(qe_identity));
+
+    // Lengths which do not fit their prefix in the compact form are not truncated
+    let mut long_advisory_id = tcb_info;
+    long_advisory_id.tcb_levels[1].advisory_ids[0] = "A".repeat(256);
+    assert_eq!(
+        long_advisory_id.to_compact_bytes(),
+        Err(CollateralError::TooLong("advisory ID"))
+    );
+    let mut many_tcb_levels = long_advisory_id;
+    many_tcb_levels.tcb_levels = vec![many_tcb_levels.tcb_levels[0].clone(); 0x10000];
+    assert_eq!(
+        many_tcb_levels.to_compact_bytes(),
+        Err(CollateralError::TooLong("TCB levels"))
+    );
+
+    assert_eq!(
+        TcbInfo::from_json("{\"tcbInfo\":{},\"signature\":\"00\"}"),
+        Err(CollateralError::InvalidJsonField("signature"))
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_evaluate_tcb() {
+    let (tcb_info, qe_identity) = collateral();
+    let input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let quote = Quote::from_bytes(&input).unwrap();
+
+    // The platform is at the second TCB level, which has advisories
+    let platform_tcb = platform([2, 2, 24, 26, 3, 255, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let evaluation = quote
+        .evaluate_tcb(&platform_tcb, &tcb_info, &qe_identity)
+        .unwrap();
+    assert_eq!(evaluation.platform, TcbStatus::OutOfDate);
+    assert_eq!(evaluation.tdx_module, Some(TcbStatus::UpToDate));
+    assert_eq!(evaluation.qe, TcbStatus::UpToDate);
+    assert_eq!(evaluation.status(), TcbStatus::OutOfDate);
+    assert_eq!(
+        evaluation.advisory_ids,
+        ["INTEL-SA-00960", "INTEL-SA-00982"]
+    );
+
+    // The same result comes from the compact form
+    let compact_tcb_info =
+        TcbInfo::from_compact_bytes(&tcb_info.to_compact_bytes().unwrap()).unwrap();
+    let up_to_date = platform([2, 2, 25, 26, 3, 255, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let evaluation = quote
+        .evaluate_tcb(&up_to_date, &compact_tcb_info, &qe_identity)
+        .unwrap();
+    assert_eq!(evaluation.status(), TcbStatus::UpToDate);
+    assert!(evaluation.advisory_ids.is_empty());
+
+    // An older quoting enclave
+    let input = fs::read("tests/test-quotes/v4_quote.dat").unwrap();
+    let quote = Quote::from_bytes(&input).unwrap();
+    let evaluation = quote
+        .evaluate_tcb(&up_to_date, &tcb_info, &qe_identity)
+        .unwrap();
+    assert_eq!(evaluation.qe, TcbStatus::OutOfDate);
+    assert_eq!(evaluation.status(), TcbStatus::OutOfDate);
+
+    // A platform below every TCB level, and one from another family
+    let below = platform([1; 16]);
+    assert_eq!(
+        quote.evaluate_tcb(&below, &tcb_info, &qe_identity),
+        Err(CollateralError::NoMatchingTcbLevel)
+    );
+    let mut other_family = up_to_date.clone();
+    other_family.fmspc[0] = 1;
+    assert_eq!(
+        quote.evaluate_tcb(&other_family, &tcb_info, &qe_identity),
+        Err(CollateralError::FmspcMismatch)
+    );
+
+    // Collateral for SGX or another enclave does not apply to TDX quotes
+    let mut sgx_tcb_info = tcb_info.clone();
+    sgx_tcb_info.tdx = false;
+    assert_eq!(
+        quote.evaluate_tcb(&up_to_date, &sgx_tcb_info, &qe_identity),
+        Err(CollateralError::NotTdxTcbInfo)
+    );
+    let mut sgx_qe_identity = qe_identity;
+    sgx_qe_identity.id = EnclaveId::Qe;
+    assert_eq!(
+        quote.evaluate_tcb(&up_to_date, &tcb_info, &sgx_qe_identity),
+        Err(CollateralError::NotTdQeIdentity)
+    );
+}
+
+#[test]
+fn test_compact_collateral_errors() {
+    assert_eq!(
+        TcbInfo::from_compact_bytes(b"QEID\x01"),
+        Err(CollateralError::Malformed)
+    );
+    assert_eq!(
+        QeIdentity::from_compact_bytes(&[b'Q', b'E', b'I', b'D', COMPACT_COLLATERAL_VERSION + 1]),
+        Err(CollateralError::UnsupportedFormatVersion(
+            COMPACT_COLLATERAL_VERSION + 1
+        ))
+    );
+    assert_eq!(
+        QeIdentity::from_compact_bytes(&[b'Q', b'E', b'I', b'D', COMPACT_COLLATERAL_VERSION]),
+        Err(CollateralError::Malformed)
+    );
+}
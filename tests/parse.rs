@@ -1,5 +1,12 @@
-use std::{fs, io::Read};
-use tdx_quote::{Quote, QuoteParseError, VerifyingKey};
+use std::fs;
+#[cfg(feature = "alloc")]
+use tdx_quote::{
+    AttestationKey, BatchVerifier, CompactQuote, FieldValue, PckId, Quote, QuoteDecoder,
+    QuoteLayout, QuoteSerializeError, QuoteSignature,
+};
+use tdx_quote::{
+    ParseOptions, QuoteField, QuoteParseError, QuoteRef, QuoteVerificationError, VerifyingKey,
+};
 
 /// The PCK used for some of the test quotes
 const KNOWN_PCK: [u8; 65] = [
@@ -9,18 +16,22 @@ const KNOWN_PCK: [u8; 65] = [
     162, 113, 245, 253, 148,
 ];
 
+#[cfg(feature = "alloc")]
 #[test]
 fn test_parse() {
     for entry in fs::read_dir("tests/test-quotes").unwrap() {
         let entry = entry.unwrap();
-        let mut file = fs::File::open(entry.path()).unwrap();
-        let mut input = Vec::new();
-        file.read_to_end(&mut input).unwrap();
+        let mut input = fs::read(entry.path()).unwrap();
         let quote = Quote::from_bytes(&input).unwrap();
 
         // We currently don't have any v5 quotes to test with
         assert_eq!(quote.header.version, 4);
 
+        // Serializing gives the original input, which is followed by zero padding
+        let serialized = quote.to_bytes().unwrap();
+        assert_eq!(serialized, input[..serialized.len()]);
+        assert!(input[serialized.len()..].iter().all(|byte| *byte == 0));
+
         // We have one quote for which the PCK is unknown, but we still want to test that it parses
         if entry.file_name().to_str().unwrap().starts_with("known_pck") {
             let pck = VerifyingKey::from_sec1_bytes(&KNOWN_PCK).unwrap();
@@ -36,6 +47,771 @@ fn test_parse() {
     }
 }
 
+#[cfg(feature = "alloc")]
+#[test]
+fn test_parse_unverified() {
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+
+        // A quote with a tampered body can still be inspected
+        let mut tampered_body = input.clone();
+        tampered_body[49] += 1;
+        let quote = Quote::parse_unverified(&tampered_body).unwrap();
+        assert_eq!(
+            quote.verify_signature(),
+            Err(QuoteVerificationError::BadAttestationKeySignature)
+        );
+        assert_eq!(quote.verify_qe_binding(), Ok(()));
+
+        // The QE report data begins 320 bytes into the QE report, which follows the signature
+        // section length, signature, attestation key and certification data type and length
+        let mut tampered_qe_report = input.clone();
+        tampered_qe_report[48 + 584 + 4 + 64 + 64 + 2 + 4 + 320] += 1;
+        let quote = Quote::parse_unverified(&tampered_qe_report).unwrap();
+        assert_eq!(quote.verify_signature(), Ok(()));
+        assert_eq!(
+            quote.verify_qe_binding(),
+            Err(QuoteVerificationError::AttestationKeyDoesNotMatch)
+        );
+        assert_eq!(
+            Quote::from_bytes(&tampered_qe_report),
+            Err(QuoteParseError::AttestationKeyDoesNotMatch)
+        );
+
+        // Signatures and keys which cannot be decoded are kept as they are in the quote
+        let signature = 48 + 584 + 4;
+        let attestation_key = signature + 64;
+        let qe_report_signature = attestation_key + 64 + 2 + 4 + 384;
+        let mut zero_signatures = input.clone();
+        zero_signatures[signature..signature + 64].fill(0);
+        zero_signatures[qe_report_signature..qe_report_signature + 64].fill(0);
+        let quote = Quote::parse_unverified(&zero_signatures).unwrap();
+        assert_eq!(quote.signature, QuoteSignature::RawP256([0; 64]));
+        assert_eq!(
+            quote.verify_signature(),
+            Err(QuoteVerificationError::BadAttestationKeySignature)
+        );
+        assert_eq!(quote.verify_qe_binding(), Ok(()));
+        // Some of the test quotes are followed by padding, which is not serialized
+        assert!(zero_signatures.starts_with(&quote.to_bytes().unwrap()));
+        assert_eq!(
+            Quote::from_bytes(&zero_signatures),
+            Err(QuoteParseError::Verification)
+        );
+
+        let mut invalid_key = input.clone();
+        invalid_key[attestation_key..attestation_key + 64].fill(0xff);
+        let quote = Quote::parse_unverified(&invalid_key).unwrap();
+        assert_eq!(quote.attestation_key, AttestationKey::RawP256([0xff; 64]));
+        assert_eq!(
+            quote.verify_signature(),
+            Err(QuoteVerificationError::BadAttestationKey)
+        );
+        assert!(invalid_key.starts_with(&quote.to_bytes().unwrap()));
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    let input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+
+    // Errors are located by the field being parsed and the byte offset
+    let error = QuoteRef::from_bytes(&input[..100]).unwrap_err();
+    assert_eq!(
+        error,
+        QuoteParseError::Parse {
+            field: QuoteField::Body,
+            offset: 64
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Cannot parse quote body at byte offset 64"
+    );
+
+    assert_eq!(
+        QuoteRef::from_bytes(&input[..1000]),
+        Err(QuoteParseError::Parse {
+            field: QuoteField::CertificationData(6),
+            offset: 48 + 584 + 4 + 64 + 64 + 2 + 4
+        })
+    );
+
+    let mut unknown_version = input.clone();
+    unknown_version[0] = 6;
+    assert_eq!(
+        QuoteRef::from_bytes(&unknown_version),
+        Err(QuoteParseError::UnknownQuoteVersion(6))
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_parse_large_certification_data() {
+    let input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let mut quote = Quote::from_bytes(&input).unwrap();
+    let tdx_quote::CertificationData::QeReportCertificationData(qe_report_certification_data) =
+        &mut quote.certification_data
+    else {
+        panic!("Expected QE report certification data");
+    };
+    qe_report_certification_data
+        .certification_data
+        .resize(0x10000, 0);
+    let large = quote.to_bytes().unwrap();
+
+    // Certification data is only limited to 64 KiB in strict mode
+    assert!(Quote::from_bytes(&large).is_ok());
+    assert!(QuoteRef::from_bytes(&large).is_ok());
+    assert!(matches!(
+        QuoteRef::from_bytes_with_options(&large, &ParseOptions::strict()),
+        Err(QuoteParseError::CertificationDataTooLong(_))
+    ));
+}
+
+#[test]
+fn test_parse_strict() {
+    let mut input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    // The test quotes are followed by zero padding
+    let quote_length = 48 + 584 + 4 + 4300;
+    let certification_data_start = 48 + 584 + 4 + 64 + 64 + 2 + 4;
+
+    assert_eq!(
+        QuoteRef::from_bytes_with_options(&input, &ParseOptions::strict()),
+        Err(QuoteParseError::TrailingBytes {
+            offset: quote_length
+        })
+    );
+    let padded = ParseOptions {
+        allow_zero_padding: true,
+        ..ParseOptions::strict()
+    };
+    let quote = QuoteRef::from_bytes_with_options(&input, &padded).unwrap();
+    assert!(
+        QuoteRef::from_bytes_with_options(&input[..quote_length], &ParseOptions::strict()).is_ok()
+    );
+
+    // Certification data length is bounded
+    let bounded = ParseOptions {
+        max_certification_data_len: 1000,
+        ..Default::default()
+    };
+    assert_eq!(
+        QuoteRef::from_bytes_with_options(&input, &bounded),
+        Err(QuoteParseError::CertificationDataTooLong(
+            quote_length - certification_data_start
+        ))
+    );
+
+    // The nested certification data follows the QE report, its signature and the QE
+    // authentication data
+    let nested_length_offset = certification_data_start
+        + 384
+        + 64
+        + 2
+        + quote
+            .qe_report_certification_data()
+            .unwrap()
+            .qe_authentication_data
+            .len()
+        + 2;
+    let mut inconsistent_nested_length = input.clone();
+    inconsistent_nested_length[nested_length_offset] += 1;
+    assert!(QuoteRef::from_bytes(&inconsistent_nested_length).is_ok());
+    assert_eq!(
+        QuoteRef::from_bytes_with_options(&inconsistent_nested_length, &padded),
+        Err(QuoteParseError::InconsistentLength(
+            QuoteField::CertificationData(5)
+        ))
+    );
+
+    // The signature section length is not signed, so is only checked in strict mode
+    input[48 + 584] += 1;
+    assert!(QuoteRef::from_bytes(&input).is_ok());
+    assert_eq!(
+        QuoteRef::from_bytes_with_options(&input, &padded),
+        Err(QuoteParseError::InconsistentLength(
+            QuoteField::SignatureSection
+        ))
+    );
+    input[48 + 584] -= 1;
+
+    input[9] = 1;
+    assert_eq!(
+        QuoteRef::parse_unverified_with_options(&input, &padded),
+        Err(QuoteParseError::NonZeroReserved { offset: 8 })
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_decode_stream() {
+    let quote_bytes = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    // Remove the padding, and follow the quote by the start of another message
+    let quote_length = 48 + 584 + 4 + 4300;
+    let mut stream = quote_bytes[..quote_length].to_vec();
+    stream.extend_from_slice(b"next message");
+
+    let mut decoder = QuoteDecoder::new();
+    assert_eq!(decoder.bytes_needed(), Ok(48));
+    let mut decoded = None;
+    for chunk in stream.chunks(100) {
+        let mut chunk = chunk;
+        if let Some(quote) = decoder.push(&mut chunk).unwrap() {
+            decoded = Some(quote);
+            assert_eq!(chunk, b"next message");
+        }
+    }
+    assert_eq!(decoded.unwrap(), Quote::from_bytes(&quote_bytes).unwrap());
+    assert_eq!(decoder.buffered(), 0);
+
+    // Once the header, body and signature section length have been read, the certification data
+    // length is needed
+    let mut input = &quote_bytes[..48 + 584 + 4];
+    assert_eq!(decoder.push(&mut input), Ok(None));
+    assert_eq!(decoder.bytes_needed(), Ok(64 + 64 + 2 + 4));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_batch_verify() {
+    use std::{cell::Cell, collections::BTreeSet};
+
+    let mut quotes = Vec::new();
+    let mut names = Vec::new();
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let entry = entry.unwrap();
+        let input = fs::read(entry.path()).unwrap();
+        // Include each quote twice
+        for _ in 0..2 {
+            quotes.push(Quote::from_bytes(&input).unwrap());
+            names.push(entry.file_name().into_string().unwrap());
+        }
+    }
+
+    let calls = Cell::new(0);
+    let mut verifier = BatchVerifier::new(|_certification_data: &[u8]| {
+        calls.set(calls.get() + 1);
+        Some(VerifyingKey::from_sec1_bytes(&KNOWN_PCK).unwrap())
+    });
+    let results = verifier.verify(&quotes);
+    for (name, result) in names.iter().zip(results) {
+        if name.starts_with("known_pck") {
+            assert_eq!(result, Ok(()));
+        } else {
+            assert_eq!(result, Err(QuoteVerificationError::BadSignature));
+        }
+    }
+
+    // The PCK is only looked up once for each distinct certification data
+    let distinct_certification_data: BTreeSet<_> = quotes
+        .iter()
+        .map(|quote| {
+            quote
+                .qe_report_certification_data()
+                .unwrap()
+                .certification_data
+        })
+        .collect();
+    assert_eq!(calls.get(), distinct_certification_data.len());
+    verifier.verify(&quotes);
+    assert_eq!(calls.get(), distinct_certification_data.len());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_diff() {
+    let quote_bytes = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let quote = Quote::from_bytes(&quote_bytes).unwrap();
+    assert!(quote
+        .diff(&Quote::from_bytes(&quote_bytes).unwrap())
+        .is_empty());
+
+    let other_quote =
+        Quote::from_bytes(&fs::read("tests/test-quotes/v4_quote.dat").unwrap()).unwrap();
+    let diff = quote.diff(&other_quote);
+    let rtmr0 = diff.get("body.rtmr0").unwrap();
+    assert_eq!(
+        rtmr0.old,
+        FieldValue::Bytes(quote.td_body().unwrap().rtmr0.to_vec())
+    );
+    assert_eq!(
+        rtmr0.new,
+        FieldValue::Bytes(other_quote.td_body().unwrap().rtmr0.to_vec())
+    );
+    assert!(diff.get("body.rtmr3").is_none());
+    assert!(diff.get("header.version").is_none());
+    let qe_svn = diff.get("certification_data.qe_report.isv_svn").unwrap();
+    assert_eq!(
+        (&qe_svn.old, &qe_svn.new),
+        (&FieldValue::Number(6), &FieldValue::Number(5))
+    );
+    assert!(diff
+        .to_string()
+        .contains("certification_data.qe_report.isv_svn: 6 -> 5\n"));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_compute_mrtd_firmware() {
+    for entry in fs::read_dir("tests/test-firmware").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "fd") {
+            continue;
+        }
+        let firmware = fs::read(&path).unwrap();
+        let expected = fs::read_to_string(path.with_extension("mrtd")).unwrap();
+        let expected: Vec<u8> = (0..96)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&expected.trim()[i..i + 2], 16).unwrap())
+            .collect();
+        assert_eq!(
+            tdx_quote::compute_mrtd(&firmware).unwrap().to_vec(),
+            expected,
+            "{}",
+            path.display()
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_layout() {
+    let quote_bytes = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let layout = QuoteLayout::from_bytes(&quote_bytes);
+    assert_eq!(layout.error, None);
+    assert_eq!(layout.get("header"), Some(0..48));
+    assert_eq!(layout.get("body.rtmr0"), Some(0x178..0x1a8));
+    assert_eq!(
+        layout.get("certification_data.qe_report.isv_svn"),
+        Some(0x404..0x406)
+    );
+    let pck_chain = layout
+        .get("certification_data.certification_data.data")
+        .unwrap();
+    assert_eq!(pck_chain.len(), 3678);
+    assert!(quote_bytes[pck_chain].starts_with(b"-----BEGIN CERTIFICATE-----"));
+    // This quote has zero padding following the signature section
+    assert_eq!(layout.get("trailing_bytes"), Some(4936..quote_bytes.len()));
+
+    let hexdump = layout.hexdump(&quote_bytes);
+    assert!(hexdump.contains("\n000178    rtmr0 (48 bytes)\n000178      09 d4 78 3e"));
+    assert!(hexdump.contains("\n000404      isv_svn: 06 00\n"));
+
+    // A truncated quote is laid out up to where it ends
+    let layout = QuoteLayout::from_bytes(&quote_bytes[..1000]);
+    assert_eq!(
+        layout.error,
+        Some(QuoteParseError::Parse {
+            field: QuoteField::QeReport,
+            offset: 962
+        })
+    );
+    assert_eq!(layout.get("certification_data"), Some(0x2fc..962));
+    assert!(layout
+        .hexdump(&quote_bytes[..1000])
+        .ends_with("error: Cannot parse quote QE report at byte offset 962\n"));
+    // A dump of input shorter than the layout stops where the input ends
+    let layout = QuoteLayout::from_bytes(&quote_bytes);
+    let hexdump = layout.hexdump(&quote_bytes[..1000]);
+    assert!(hexdump.ends_with("\n0003e8  truncated: input ends\n"));
+
+    // Bytes following the QE report certification data within the certification data length are
+    // part of the certification data
+    let mut padded = quote_bytes.clone();
+    let certification_data_size = 48 + 584 + 4 + 64 + 64 + 2;
+    for offset in [48 + 584, certification_data_size] {
+        let length = u32::from_le_bytes(padded[offset..offset + 4].try_into().unwrap());
+        padded[offset..offset + 4].copy_from_slice(&(length + 70).to_le_bytes());
+    }
+    let layout = QuoteLayout::from_bytes(&padded);
+    assert_eq!(layout.error, None);
+    assert_eq!(layout.get("certification_data.padding"), Some(4936..5006));
+    assert_eq!(layout.get("certification_data"), Some(0x2fc..5006));
+    assert_eq!(layout.get("trailing_bytes"), None);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_display() {
+    let quote_bytes = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let quote = Quote::from_bytes(&quote_bytes).unwrap();
+    assert_eq!(
+        quote.header.to_string(),
+        "Version: 4
+Attestation key type: ECDSA-256-with-P-256
+TEE type: TDX
+Reserved 1: 0000
+Reserved 2: 0000
+QE vendor ID: 939a7233-f79c-4ca9-940a-0db3957f0607
+User data: eecbe2e60cf2c34f55d1fbc929eb853b00000000
+"
+    );
+    let body = quote.td_body().unwrap().to_string();
+    assert!(
+        body.starts_with("TDX version: TDX 1.0\nTEE TCB SVN: 04010200000000000000000000000000\n")
+    );
+    assert!(body.contains("\nMRTD: 91eb2b44d141d4ece09f0c75c2c53d247a3c68edd7fafe8a3520c942a604a407de03ae6dc5f87f27428b2538873118b7\n"));
+
+    let quote = quote.to_string();
+    assert!(quote.starts_with("Header:\n  Version: 4\n"));
+    assert!(quote.contains("\nBody:\n  TDX version: TDX 1.0\n"));
+    assert!(
+        quote.contains("\nCertification data: QE report certification data (6)\n  QE report:\n")
+    );
+    assert!(quote.contains("\n    ISV SVN: 6\n"));
+    assert!(quote.ends_with("\n  Certification data: PCK certificate chain (5), 3678 bytes\n"));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_compact_quote() {
+    let pck = VerifyingKey::from_sec1_bytes(&KNOWN_PCK).unwrap();
+    let input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let quote = Quote::from_bytes(&input).unwrap();
+    let certification_data = quote
+        .qe_report_certification_data()
+        .unwrap()
+        .certification_data;
+
+    for pck_id in [
+        PckId::from_pck(&pck).unwrap(),
+        PckId::from_certification_data(&certification_data),
+    ] {
+        let quote = Quote::from_bytes(&input).unwrap();
+        let compact = CompactQuote::new(quote, pck_id.clone()).unwrap();
+        let serialized = compact.to_bytes().unwrap();
+        assert!(serialized.len() < input.len() / 3);
+        assert_eq!(CompactQuote::from_bytes(&serialized), Ok(compact.clone()));
+
+        let registry = [(pck_id, pck)];
+        let find_pck = |pck_id: &PckId| {
+            registry
+                .iter()
+                .find(|(id, _)| id == pck_id)
+                .map(|(_, pck)| *pck)
+        };
+        assert_eq!(compact.verify(find_pck), Ok(()));
+        assert_eq!(
+            compact.verify(|_| None),
+            Err(QuoteVerificationError::UnknownPck)
+        );
+
+        let mut tampered = compact.clone();
+        tampered.qe_report[0] ^= 1;
+        assert_eq!(
+            tampered.verify(find_pck),
+            Err(QuoteVerificationError::BadSignature)
+        );
+
+        // Lengths are not truncated when serializing
+        let mut long_qe_authentication_data = compact.clone();
+        long_qe_authentication_data.qe_authentication_data = vec![0; 0x10000];
+        assert_eq!(
+            long_qe_authentication_data.to_bytes(),
+            Err(QuoteSerializeError::TooLong("QE authentication data"))
+        );
+
+        // A missing or truncated PCK ID is located after the QE authentication data
+        let pck_id_length = match compact.pck_id {
+            PckId::Key(key) => key.len(),
+            PckId::CertificationDataHash(hash) => hash.len(),
+        };
+        let offset = serialized.len() - 1 - pck_id_length;
+        assert_eq!(
+            CompactQuote::from_bytes(&serialized[..serialized.len() - 1]),
+            Err(QuoteParseError::Parse {
+                field: QuoteField::PckId,
+                offset
+            })
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_batch_verify_parallel() {
+    let quotes: Vec<_> = fs::read_dir("tests/test-quotes")
+        .unwrap()
+        .map(|entry| Quote::from_bytes(&fs::read(entry.unwrap().path()).unwrap()).unwrap())
+        .collect();
+    let find_pck = |_: &[u8]| Some(VerifyingKey::from_sec1_bytes(&KNOWN_PCK).unwrap());
+    assert_eq!(
+        BatchVerifier::new(find_pck).verify_parallel(&quotes),
+        BatchVerifier::new(find_pck).verify(&quotes)
+    );
+}
+
+#[test]
+fn test_verify_borrowed() {
+    // This does not need an allocator, so also runs without the `alloc` feature
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let entry = entry.unwrap();
+        let mut input = fs::read(entry.path()).unwrap();
+        let quote = QuoteRef::from_bytes(&input).unwrap();
+        assert_eq!(quote.header.version, 4);
+
+        if entry.file_name().to_str().unwrap().starts_with("known_pck") {
+            let pck = VerifyingKey::from_sec1_bytes(&KNOWN_PCK).unwrap();
+            quote.verify_with_pck(pck).unwrap();
+        }
+
+        input[49] += 1;
+        assert_eq!(
+            QuoteRef::from_bytes(&input),
+            Err(QuoteParseError::Verification)
+        );
+        assert_eq!(
+            QuoteRef::parse_unverified(&input)
+                .unwrap()
+                .verify_signature(),
+            Err(QuoteVerificationError::BadAttestationKeySignature)
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_parse_borrowed() {
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+        let quote_ref = QuoteRef::from_bytes(&input).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+
+        assert_eq!(quote_ref.to_quote().unwrap(), quote);
+        assert_eq!(*quote_ref.report_input_data(), quote.report_input_data());
+        assert_eq!(quote_ref.mrtd().copied(), quote.mrtd());
+        assert_eq!(quote_ref.signed_data(), &input[..48 + 584],);
+        assert_eq!(
+            quote_ref
+                .qe_report_certification_data()
+                .unwrap()
+                .qe_report_body()
+                .unwrap()
+                .report_data,
+            &quote
+                .qe_report_certification_data()
+                .unwrap()
+                .qe_report_body()
+                .unwrap()
+                .report_data,
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+
+        // Human readable formats use hex for byte fields
+        let json = serde_json::to_value(&quote).unwrap();
+        assert_eq!(
+            json["body"]["Td"]["mrtd"],
+            serde_json::Value::String(
+                quote
+                    .mrtd()
+                    .unwrap()
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect()
+            )
+        );
+        let decoded: Quote = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, quote);
+
+        // Binary formats use raw bytes
+        let encoded = postcard::to_allocvec(&quote).unwrap();
+        let decoded: Quote = postcard::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, quote);
+        assert_eq!(decoded.verify_signature(), Ok(()));
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_to_json() {
+    use tdx_quote::JsonLayout;
+
+    let input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let quote = Quote::from_bytes(&input).unwrap();
+    let mrtd = "91eb2b44d141d4ece09f0c75c2c53d247a3c68edd7fafe8a3520c942a604a407de03ae6dc5f87f27428b2538873118b7";
+
+    let json: serde_json::Value = serde_json::from_str(&quote.to_json()).unwrap();
+    assert_eq!(json["header"]["tee_type"], "TDX");
+    assert_eq!(
+        json["header"]["qe_vendor_id"],
+        "939a7233-f79c-4ca9-940a-0db3957f0607"
+    );
+    assert_eq!(json["body"]["mrtd"], mrtd);
+    assert_eq!(json["body"]["tdx_module_major_version"], 1);
+    assert_eq!(
+        json["body"]["tdattributes_flags"],
+        serde_json::json!(["SEPT_VE_DISABLE"])
+    );
+    let certification_data = &json["certification_data"];
+    assert_eq!(certification_data["type"], 6);
+    assert_eq!(certification_data["qe_report"]["isv_svn"], 6);
+    let certificates = certification_data["certification_data"]["pem_certificates"]
+        .as_array()
+        .unwrap();
+    assert_eq!(certificates.len(), 3);
+    assert!(certificates[0]
+        .as_str()
+        .unwrap()
+        .starts_with("-----BEGIN CERTIFICATE-----"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&quote.to_json_with_layout(JsonLayout::Intel)).unwrap();
+    assert_eq!(json["header"]["att_key_type"], 2);
+    assert_eq!(json["report_body"]["mr_td"], mrtd);
+    assert_eq!(json["report_body"]["rt_mr"].as_array().unwrap().len(), 4);
+    // The signature data length excludes the length field itself
+    let signature_data_len = quote.to_bytes().unwrap().len() - 48 - 584 - 4;
+    assert_eq!(json["signature_data_len"], signature_data_len);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&quote.to_json_with_layout(JsonLayout::GoTdxGuest)).unwrap();
+    assert_eq!(json["header"]["teeType"], 0x81);
+    assert_eq!(json["signedDataSize"], signature_data_len);
+    assert_eq!(
+        json["tdQuoteBody"]["mrTd"],
+        "kesrRNFB1Ozgnwx1wsU9JHo8aO3X+v6KNSDJQqYEpAfeA65txfh/J0KLJTiHMRi3"
+    );
+    let qe_report_certification_data =
+        &json["signedData"]["certificationData"]["qeReportCertificationData"];
+    assert_eq!(qe_report_certification_data["qeReport"]["isvSvn"], 6);
+    assert_eq!(
+        qe_report_certification_data["pckCertificateChainData"]["size"],
+        3678
+    );
+}
+
+#[cfg(feature = "protobuf")]
+#[test]
+fn test_protobuf_round_trip() {
+    use tdx_quote::{protobuf::QuoteV4, ProtobufError};
+
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+        let encoded = quote.to_protobuf().unwrap();
+        assert_eq!(Quote::from_protobuf(&encoded), Ok(quote));
+    }
+
+    let input = fs::read("tests/test-quotes/known_pck_quote_1.dat").unwrap();
+    let quote = Quote::from_bytes(&input).unwrap();
+    let message = QuoteV4::try_from(&quote).unwrap();
+    assert_eq!(message.signed_data_size, 4300);
+    let qe_report_certification_data = message
+        .signed_data
+        .as_ref()
+        .unwrap()
+        .certification_data
+        .as_ref()
+        .unwrap()
+        .qe_report_certification_data
+        .as_ref()
+        .unwrap();
+    assert_eq!(
+        qe_report_certification_data
+            .qe_report
+            .as_ref()
+            .unwrap()
+            .isv_svn,
+        6
+    );
+    assert_eq!(
+        qe_report_certification_data
+            .pck_certificate_chain_data
+            .as_ref()
+            .unwrap()
+            .certificate_data_type,
+        5
+    );
+
+    let mut missing_body = message.clone();
+    missing_body.td_quote_body = None;
+    assert_eq!(
+        Quote::try_from(missing_body),
+        Err(ProtobufError::MissingField("td_quote_body"))
+    );
+    let mut wrong_size = message.clone();
+    wrong_size.signed_data_size += 1;
+    assert_eq!(
+        Quote::try_from(wrong_size),
+        Err(ProtobufError::InvalidField("signed_data_size"))
+    );
+    let mut sgx_tee_type = message.clone();
+    sgx_tee_type.header.as_mut().unwrap().tee_type = 0;
+    assert_eq!(
+        Quote::try_from(sgx_tee_type),
+        Err(ProtobufError::InvalidField("tee_type"))
+    );
+    let mut short_rtmr = message;
+    short_rtmr.td_quote_body.as_mut().unwrap().rtmrs[3].pop();
+    assert_eq!(
+        Quote::try_from(short_rtmr),
+        Err(ProtobufError::InvalidField("rtmrs"))
+    );
+    assert_eq!(Quote::from_protobuf(&[0xff]), Err(ProtobufError::Decode));
+
+    // A QuoteV4 message for the same quote as encoded by go-tdx-guest, including the zero bytes
+    // which follow the quote in the file
+    let golden = fs::read("tests/test-protobuf/known_pck_quote_1.binpb").unwrap();
+    let extra_bytes = &input[48 + 584 + 4 + 4300..];
+    assert_eq!(extra_bytes.len(), 70);
+    assert_eq!(
+        Quote::from_protobuf(&golden),
+        Err(ProtobufError::ExtraBytes)
+    );
+    assert_eq!(
+        quote.to_protobuf_with_extra_bytes(extra_bytes).unwrap(),
+        golden
+    );
+    assert_eq!(
+        Quote::from_protobuf_with_extra_bytes(&golden),
+        Ok((quote, extra_bytes.to_vec()))
+    );
+}
+
+#[cfg(feature = "scale")]
+#[test]
+fn test_scale_round_trip() {
+    use codec::{Decode, Encode, MaxEncodedLen};
+
+    for entry in fs::read_dir("tests/test-quotes").unwrap() {
+        let input = fs::read(entry.unwrap().path()).unwrap();
+        let quote = Quote::from_bytes(&input).unwrap();
+
+        let encoded = quote.encode();
+        assert!(encoded.len() <= Quote::max_encoded_len());
+        let decoded = Quote::decode(&mut &encoded[..]).unwrap();
+        assert_eq!(decoded, quote);
+        assert_eq!(decoded.verify_signature(), Ok(()));
+
+        // Certification data longer than the bound is rejected, even if the input is that long
+        let nested = quote
+            .qe_report_certification_data()
+            .unwrap()
+            .certification_data;
+        let len_prefix = codec::Compact(nested.len() as u32).encode();
+        let len_offset = encoded.len() - nested.len() - len_prefix.len();
+        let mut too_long = encoded[..len_offset].to_vec();
+        too_long.extend(codec::Compact(1u32 << 20).encode());
+        too_long.resize(too_long.len() + (1 << 20), 0);
+        assert!(Quote::decode(&mut &too_long[..]).is_err());
+    }
+
+    let error = QuoteParseError::Parse {
+        field: QuoteField::CertificationData(6),
+        offset: 770,
+    };
+    assert_eq!(QuoteParseError::decode(&mut &error.encode()[..]), Ok(error));
+}
+
 #[cfg(feature = "mock")]
 #[test]
 fn test_create_mock_quote() {
@@ -52,7 +828,185 @@ fn test_create_mock_quote() {
     quote
         .verify_with_pck(VerifyingKey::from(provisioning_certification_key))
         .unwrap();
-    let quote_bytes = quote.as_bytes();
+    let quote_bytes = quote.to_bytes().unwrap();
+    let quote_deserialized = Quote::from_bytes(&quote_bytes).unwrap();
+    assert_eq!(quote, quote_deserialized);
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn test_mock_quote_with_rtmrs() {
+    use rand_core::OsRng;
+    use tdx_quote::RtmrSet;
+    let attestation_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let provisioning_certification_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+
+    let mut rtmrs = RtmrSet::new();
+    rtmrs.rtmr1.extend_data(b"kernel");
+    rtmrs.get_mut(2).unwrap().extend_data(b"command line");
+
+    let quote = Quote::mock_with_rtmrs(
+        attestation_key,
+        provisioning_certification_key,
+        [0; 64],
+        &rtmrs,
+    );
+    let quote = Quote::from_bytes(&quote.to_bytes().unwrap()).unwrap();
+    let body = quote.td_body().unwrap();
+    assert_ne!(body.rtmr1, [0; 48]);
+    assert!(rtmrs.matches(body));
+    assert!(!RtmrSet::new().matches(body));
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn test_mock_quote_with_report_data_binding() {
+    use rand_core::OsRng;
+    use tdx_quote::ReportDataBinding;
+    let attestation_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let provisioning_certification_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+
+    let binding = ReportDataBinding::with_domain(b"test", 1)
+        .nonce(&[1; 32])
+        .public_key(&[2; 33]);
+    let quote = Quote::mock(attestation_key, provisioning_certification_key, &binding);
+    binding.verify(&quote).unwrap();
+
+    let other_binding = ReportDataBinding::with_domain(b"test", 1)
+        .nonce(&[3; 32])
+        .public_key(&[2; 33]);
+    assert!(other_binding.verify(&quote).is_err());
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn test_create_mock_sgx_quote() {
+    use rand_core::OsRng;
+    let attestation_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let provisioning_certification_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let quote = Quote::mock_sgx(
+        attestation_key,
+        provisioning_certification_key.clone(),
+        [1; 64],
+    );
+    let quote_bytes = quote.to_bytes().unwrap();
     let quote_deserialized = Quote::from_bytes(&quote_bytes).unwrap();
     assert_eq!(quote, quote_deserialized);
+    assert_eq!(quote_deserialized.header.version, 3);
+    assert!(quote_deserialized.td_body().is_none());
+    assert_eq!(quote_deserialized.sgx_body().unwrap().report_data, [1; 64]);
+    assert_eq!(quote_deserialized.report_input_data(), [1; 64]);
+    quote_deserialized
+        .verify_with_pck(VerifyingKey::from(provisioning_certification_key))
+        .unwrap();
+}
+
+#[cfg(all(feature = "mock", feature = "p384"))]
+#[test]
+fn test_create_mock_p384_quote() {
+    use rand_core::OsRng;
+    use tdx_quote::AttestationKey;
+    let attestation_key = p384::ecdsa::SigningKey::random(&mut OsRng);
+    let provisioning_certification_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+    let quote = Quote::mock_p384(
+        attestation_key.clone(),
+        provisioning_certification_key.clone(),
+        [0; 64],
+    );
+    assert_eq!(
+        quote.attestation_key,
+        AttestationKey::P384(p384::ecdsa::VerifyingKey::from(attestation_key))
+    );
+    let mut quote_bytes = quote.to_bytes().unwrap();
+    // Signatures and keys are 96 bytes
+    assert_eq!(quote_bytes.len(), 48 + 584 + 4 + 96 + 96 + 6 + 384 + 64 + 2);
+    let quote_deserialized = Quote::from_bytes(&quote_bytes).unwrap();
+    assert_eq!(quote, quote_deserialized);
+    quote_deserialized
+        .verify_with_pck(VerifyingKey::from(provisioning_certification_key))
+        .unwrap();
+
+    // A P-256 signature cannot be verified with a P-384 key
+    let p256_signature = QuoteSignature::RawP256([0; 64]);
+    assert_eq!(
+        quote
+            .attestation_key
+            .verify(&quote_bytes[..48 + 584], &p256_signature),
+        Err(QuoteVerificationError::BadAttestationKeySignature)
+    );
+
+    quote_bytes[49] += 1;
+    assert_eq!(
+        Quote::from_bytes(&quote_bytes),
+        Err(QuoteParseError::Verification)
+    );
+}
+
+#[cfg(feature = "mock")]
+#[test]
+fn test_layout_v5() {
+    use rand_core::OsRng;
+    use tdx_quote::{ReportBody, TDXVersion, TEEType};
+    let mut quote = Quote::mock(
+        p256::ecdsa::SigningKey::random(&mut OsRng),
+        p256::ecdsa::SigningKey::random(&mut OsRng),
+        [0; 64],
+    );
+    quote.header.version = 5;
+    let ReportBody::Td(body) = &mut quote.body else {
+        panic!("Mock quote should be TDX");
+    };
+    body.tdx_version = TDXVersion::OnePointFive;
+    body.tee_tcb_svn_2 = Some([2; 16]);
+    body.mrservicetd = Some([3; 48]);
+    let quote_bytes = quote.to_bytes().unwrap();
+
+    let layout = QuoteLayout::from_bytes(&quote_bytes);
+    assert_eq!(layout.error, None);
+    assert_eq!(layout.get("body"), Some(48..48 + 6 + 648));
+    assert_eq!(layout.get("body.type"), Some(48..50));
+    assert_eq!(layout.get("body.tee_tcb_svn"), Some(54..70));
+    assert_eq!(
+        layout.get("body.mrservicetd"),
+        Some(48 + 6 + 600..48 + 6 + 648)
+    );
+    assert_eq!(layout.get("certification_data.certification_data"), None);
+    assert_eq!(layout.fields.last().unwrap().range.end, quote_bytes.len());
+
+    // The body type must agree with the TEE type in the header, and every parser gives the same
+    // error if it does not
+    let mut sgx_header = quote_bytes.clone();
+    sgx_header[4] = 0;
+    let mut sgx_body_type = quote_bytes.clone();
+    sgx_body_type[48] = 1;
+    let mut unknown_body_type = quote_bytes.clone();
+    unknown_body_type[48] = 4;
+    for (input, expected) in [
+        (
+            &sgx_header,
+            QuoteParseError::BodyTypeMismatch {
+                tee_type: TEEType::SGX,
+                body_type: 3,
+            },
+        ),
+        (
+            &sgx_body_type,
+            QuoteParseError::BodyTypeMismatch {
+                tee_type: TEEType::TDX,
+                body_type: 1,
+            },
+        ),
+        (&unknown_body_type, QuoteParseError::UnknownBodyType(4)),
+    ] {
+        assert_eq!(Quote::from_bytes(input).err().as_ref(), Some(&expected));
+        assert_eq!(
+            QuoteRef::parse_unverified(input).err().as_ref(),
+            Some(&expected)
+        );
+        assert_eq!(
+            QuoteDecoder::new().push(&mut &input[..]).err().as_ref(),
+            Some(&expected)
+        );
+        assert_eq!(QuoteLayout::from_bytes(input).error, Some(expected));
+    }
 }
@@ -0,0 +1,47 @@
+// Command generate encodes a quote as a QuoteV4 protobuf message using go-tdx-guest, to produce
+// the golden files in tests/test-protobuf. From this directory:
+//
+//	go mod init generate
+//	go get github.com/google/go-tdx-guest google.golang.org/protobuf
+//	go run . ../../test-quotes/known_pck_quote_1.dat ../known_pck_quote_1.binpb
+package main
+
+import (
+	"fmt"
+	"os"
+
+	"github.com/google/go-tdx-guest/abi"
+	pb "github.com/google/go-tdx-guest/proto/tdx"
+	"google.golang.org/protobuf/proto"
+)
+
+func main() {
+	if len(os.Args) != 3 {
+		fmt.Fprintln(os.Stderr, "usage: generate <quote> <output>")
+		os.Exit(2)
+	}
+	if err := generate(os.Args[1], os.Args[2]); err != nil {
+		fmt.Fprintln(os.Stderr, err)
+		os.Exit(1)
+	}
+}
+
+func generate(input, output string) error {
+	raw, err := os.ReadFile(input)
+	if err != nil {
+		return err
+	}
+	parsed, err := abi.QuoteToProto(raw)
+	if err != nil {
+		return err
+	}
+	quote, ok := parsed.(*pb.QuoteV4)
+	if !ok {
+		return fmt.Errorf("%s is not a version 4 quote", input)
+	}
+	encoded, err := proto.Marshal(quote)
+	if err != nil {
+		return err
+	}
+	return os.WriteFile(output, encoded, 0o644)
+}